@@ -94,7 +94,11 @@ export let appState = $state({
 		models: [],
 		loras: [],
 		controls: [],
-	}
+	},
+
+	// Stash Sets from DTC_APP_DIR/stash_sets.json
+	// Each object: {name, models: [file], loras: [file], controlnets: [file], updated}
+	stash_sets: []
 
 });
 
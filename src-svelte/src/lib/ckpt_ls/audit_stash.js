@@ -0,0 +1,62 @@
+/**
+ * audit_stash - Check that every stashed file is intact
+ *
+ * @param {boolean} [compare_mac=false] - Also hash the Mac copy of each file (slower)
+ * @returns {Object} { code: 0|1, result: [{ job_id }], error: [] }
+ *
+ * ERROR CODES:
+ * 11 - Directory not found
+ * 19 - STASH_DIR not configured
+ * 39 - JSON parse error (hash manifest)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Runs the Rust `start_audit_job` command, which queues the audit as a background job.
+ * - Every model and companion in STASH_DIR/Models is hashed in full (BLAKE3) and compared with the hash
+ *   recorded in STASH_DIR/App_Data/stash_hashes.json by earlier audits
+ * - Statuses: ok | new | updated | truncated | corrupt | mac_mismatch | missing | unreadable
+ *   ("updated" = identical to the Mac copy, or rewritten with a new mtime and no Mac copy contradicting it)
+ * - Files listed in the Stash JSON but not on disk are "missing"
+ * - Files this app deletes or renames in the Stash (delete_model, apply_dedupe) have their records dropped or moved
+ * - "audit-progress" events carry { current, files_done, files_total, bytes_done, bytes_total };
+ *   the job result (via "job-update") is the report: { files, counts, bytes_hashed, ... }
+ * - Rust `stash_audit_records` returns each file's recorded hash and last_verified without hashing
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function audit_stash(compare_mac = false) {
+  console.log('[audit_stash] Starting');
+
+  try {
+    const { DT_BASE_DIR, STASH_DIR } = appState.settings;
+    if (!STASH_DIR) {
+      console.error('[audit_stash] STASH_DIR not configured');
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: 19, message: 'STASH_DIR not configured' }]
+      };
+    }
+
+    const job_id = await invoke('start_audit_job', {
+      stashBaseDir: STASH_DIR,
+      macBaseDir: compare_mac ? DT_BASE_DIR : null
+    });
+
+    return {
+      code: 0,
+      result: { job_id },
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[audit_stash] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
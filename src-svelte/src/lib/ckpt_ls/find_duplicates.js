@@ -0,0 +1,62 @@
+/**
+ * find_duplicates - Find models stored under more than one filename on the Mac and in the Stash
+ *
+ * @returns {Object} { code: 0|1, result: [report], error: [] }
+ *
+ * ERROR CODES:
+ * 11 - Directory not found
+ * 18 - DT_BASE_DIR not configured
+ * 19 - STASH_DIR not configured
+ * 41 - Invalid JSON structure (a Draw Things registry has errors; nothing is planned or changed)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Runs the Rust `find_duplicates` command over both Models directories.
+ * - Files are grouped by size (model + companions), then by BLAKE3; only same-sized files with
+ *   different filenames are hashed, and hashes are cached by (path, size, mtime)
+ * - report: { groups, wasted_bytes, files_hashed, warnings }
+ * - Each group: { hash, size, filenames, copies: [{ location, filename, relative_path, companions }],
+ *   references: [{ location, json_file, references, key, entry_file, entry_name }], wasted_bytes, suggested }
+ * - `references` shows which copy each JSON entry points at (`key` is "file" or the encoder key)
+ * - `suggested` keeps the most referenced filename; pass a group to apply_dedupe to choose another
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function find_duplicates() {
+  console.log('[find_duplicates] Starting');
+
+  try {
+    const { DT_BASE_DIR, STASH_DIR } = appState.settings;
+    if (!DT_BASE_DIR || !STASH_DIR) {
+      const errorCode = !DT_BASE_DIR ? 18 : 19;
+      const errorMsg = !DT_BASE_DIR ? 'DT_BASE_DIR not configured' : 'STASH_DIR not configured';
+      console.error('[find_duplicates]', errorMsg);
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: errorCode, message: errorMsg }]
+      };
+    }
+
+    const report = await invoke('find_duplicates', {
+      macBaseDir: DT_BASE_DIR,
+      stashBaseDir: STASH_DIR
+    });
+
+    return {
+      code: 0,
+      result: report,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[find_duplicates] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
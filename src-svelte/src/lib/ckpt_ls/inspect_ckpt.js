@@ -0,0 +1,71 @@
+/**
+ * inspect_ckpt - List the tensors in a Draw Things .ckpt (or .safetensors) and infer what the model is
+ *
+ * @param {string} ckpt_filename - The checkpoint filename (relative to Models)
+ * @param {string} [location='mac'] - 'mac' (DT_BASE_DIR) or 'stash' (STASH_DIR)
+ * @returns {Object} { code: 0|1, result: [inspection], error: [] }
+ *
+ * ERROR CODES:
+ * 5 - File not found
+ * 18 - DT_BASE_DIR not configured
+ * 19 - STASH_DIR not configured
+ * 24 - Database connection error (could not open the file)
+ * 25 - Database schema error (not a Draw Things checkpoint)
+ * 26 - Database query error
+ * 39 - JSON parse error (safetensors header)
+ * 41 - Invalid JSON structure (not a safetensors file)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Draw Things .ckpt files are SQLite databases with a `tensors` table. Runs the Rust `inspect_ckpt`
+ * command, which opens the file read-only and never reads the weights.
+ * - inspection: { path, tensor_count, total_params, dtypes: { f16: n, ... }, family, role, hints,
+ *   tensors: [{ name, dtype, shape, params }] }
+ * - family: sd15 | sd2 | sdxl | sd3 | flux | unknown (from characteristic tensor widths)
+ * - role: unet | vae | text_encoder | lora | control_net | unknown (from tensor names)
+ * - `hints` says what the family/role were inferred from; VAEs and T5 encoders are shared
+ *   between families and usually report "unknown"
+ * - .safetensors: only the JSON header is read; `metadata` holds { base_model_version,
+ *   network_dim, network_alpha, trigger_words, other } from `__metadata__` (null for .ckpt).
+ *   A network dim marks a LoRA and a base model version sets the family
+ * - The Rust scanner keeps the same summary (minus `tensors`) on each safetensors record as `header`
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function inspect_ckpt(ckpt_filename, location = 'mac') {
+  console.log(`[inspect_ckpt] ${location}: ${ckpt_filename}`);
+
+  try {
+    const baseDir = location === 'stash' ? appState.settings.STASH_DIR : appState.settings.DT_BASE_DIR;
+    if (!baseDir) {
+      const errorCode = location === 'stash' ? 19 : 18;
+      const errorMsg = location === 'stash' ? 'STASH_DIR not configured' : 'DT_BASE_DIR not configured';
+      console.error('[inspect_ckpt]', errorMsg);
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: errorCode, message: errorMsg }]
+      };
+    }
+
+    const inspection = await invoke('inspect_ckpt', {
+      path: `${baseDir}/Models/${ckpt_filename}`
+    });
+
+    return {
+      code: 0,
+      result: inspection,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[inspect_ckpt] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
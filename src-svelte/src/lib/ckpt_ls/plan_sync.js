@@ -0,0 +1,69 @@
+/**
+ * plan_sync - Preview what a sync between Mac and Stash would do, without moving anything
+ *
+ * @param {boolean} [compare_hashes=false] - Hash same-sized files instead of trusting size and mtime (slow)
+ * @returns {Object} { code: 0|1, result: [plan], error: [] }
+ *
+ * ERROR CODES:
+ * 11 - Directory not found
+ * 18 - DT_BASE_DIR not configured
+ * 19 - STASH_DIR not configured
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Runs the Rust `plan_sync` command, which compares both `Models` directories and the DrawThings JSON files.
+ * - files: one entry per model file with status new_on_mac | new_in_stash | changed | identical | conflicting
+ *   ("changed" has `newer`: mac|stash; "conflicting" differs but neither side is newer).
+ *   Without compare_hashes, same-sized files with different mtimes are "changed"
+ * - json_entries: the same per JSON entry, matched by `file`; a differing entry is "changed"
+ *   only when its model file has a newer side, else "conflicting"
+ * - totals: file count and bytes per status
+ * - stash_space / warnings: free space on the Stash, exFAT or network volume, unreadable files
+ * - Show the plan to the user for approval before copying
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function plan_sync(compare_hashes = false) {
+  console.log('[plan_sync] Starting');
+
+  try {
+    const { DT_BASE_DIR, STASH_DIR } = appState.settings;
+    if (!DT_BASE_DIR || !STASH_DIR) {
+      const errorCode = !DT_BASE_DIR ? 18 : 19;
+      const errorMsg = !DT_BASE_DIR ? 'DT_BASE_DIR not configured' : 'STASH_DIR not configured';
+      console.error('[plan_sync]', errorMsg);
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: errorCode, message: errorMsg }]
+      };
+    }
+
+    const plan = await invoke('plan_sync', {
+      macBaseDir: DT_BASE_DIR,
+      stashBaseDir: STASH_DIR,
+      hash: compare_hashes
+    });
+
+    for (const warning of plan.warnings) {
+      console.warn('[plan_sync]', warning);
+    }
+    console.log(`[plan_sync] Completed - ${plan.totals.new_on_mac.files} new on Mac, ${plan.totals.changed.files} changed, ${plan.totals.conflicting.files} conflicting`);
+
+    return {
+      code: 0,
+      result: plan,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[plan_sync] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
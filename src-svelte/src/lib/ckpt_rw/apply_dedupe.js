@@ -0,0 +1,79 @@
+/**
+ * apply_dedupe - Keep one copy of a duplicated model and point the JSON entries at it
+ *
+ * @param {Object} group - A group from find_duplicates
+ * @param {string} [keep] - Filename to keep (default: the group's suggested keeper)
+ * @param {boolean} [dry_run=false] - Only return the plan
+ * @returns {Object} { code: 0|1, result: [plan] or [result], error: [] }
+ *
+ * ERROR CODES:
+ * 6 - File already exists (rename destination, or a repoint onto a file the JSON already lists)
+ * 8 - File write error
+ * 9 - File delete error (incl. a duplicate no longer identical to the kept copy)
+ * 18 - DT_BASE_DIR not configured
+ * 19 - STASH_DIR not configured
+ * 28 - Record not found (keep is not one of the group's filenames)
+ * 41 - Invalid JSON structure (a Draw Things registry has errors; nothing is planned or changed)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * - Rust `plan_dedupe` returns { keep, actions, bytes_freed }; actions in order:
+ *   rename (a location without the kept filename renames one copy to it, with companions),
+ *   remove_entry (a duplicate's entry in a custom*.json that already lists the kept file),
+ *   repoint (custom*.json entries and encoder keys), delete (the other copies)
+ * - Rust `apply_dedupe` runs the plan: JSON is backed up to DTC_APP_DIR/backups first, and each copy
+ *   is re-verified (size + BLAKE3) against the kept one before it is deleted
+ * - Stops at the first failure: result { done, failed: [action, error] | null, bytes_freed }
+ * - Re-read the ckpt lists afterwards
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function apply_dedupe(group, keep = null, dry_run = false) {
+  console.log(`[apply_dedupe] ${keep ?? group.suggested.keep}${dry_run ? ' (dry run)' : ''}`);
+
+  try {
+    const { DT_BASE_DIR, STASH_DIR, DTC_APP_DIR } = appState.settings;
+    if (!DT_BASE_DIR || !STASH_DIR) {
+      const errorCode = !DT_BASE_DIR ? 18 : 19;
+      const errorMsg = !DT_BASE_DIR ? 'DT_BASE_DIR not configured' : 'STASH_DIR not configured';
+      console.error('[apply_dedupe]', errorMsg);
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: errorCode, message: errorMsg }]
+      };
+    }
+
+    const plan = await invoke('plan_dedupe', { group, keep });
+    if (dry_run) {
+      return {
+        code: 0,
+        result: plan,
+        error: []
+      };
+    }
+
+    const result = await invoke('apply_dedupe', {
+      macBaseDir: DT_BASE_DIR,
+      stashBaseDir: STASH_DIR,
+      appDir: DTC_APP_DIR,
+      plan
+    });
+
+    return {
+      code: result.failed ? 1 : 0,
+      result,
+      error: result.failed ? [command_error(result.failed[1])] : []
+    };
+
+  } catch (error) {
+    console.error('[apply_dedupe] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
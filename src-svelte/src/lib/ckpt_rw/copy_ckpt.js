@@ -21,13 +21,16 @@
  *
  * IMPLEMENTATION NOTES:
  * Copies a single checkpoint file,  MacHD>Stash or Stash>MacHD.
- * - Use @tauri-apps/plugin-fs for file operations (readFile, writeFile)
+ * - Copy via the Rust `copy_model` command: chunked, written to `<dest>.partial` and renamed when
+ *   complete, resumes an interrupted copy, emits "copy-progress" events (bytes, rate, ETA)
  * - Resolve source/destination paths from settings (DT_BASE_DIR/STASH_DIR + /Models/)
  * - Handle large files efficiently (models can be several GB)
  * - Return detailed error info if copy fails (disk space, permissions, etc.)
  */
-import { readFile, writeFile, exists } from '@tauri-apps/plugin-fs';
+import { exists } from '@tauri-apps/plugin-fs';
+import { invoke } from '@tauri-apps/api/core';
 import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
 
 export async function copy_ckpt(ckpt_filename, source, destination) {
   console.log(`[copy_ckpt] Starting - ${ckpt_filename} from ${source} to ${destination}`);
@@ -98,11 +101,8 @@ export async function copy_ckpt(ckpt_filename, source, destination) {
 
     // Copy the file
     try {
-      console.log(`[copy_ckpt] Reading from: ${sourcePath}`);
-      const fileContent = await readFile(sourcePath);
-
-      console.log(`[copy_ckpt] Writing to: ${destPath}`);
-      await writeFile(destPath, fileContent);
+      console.log(`[copy_ckpt] Copying ${sourcePath} to ${destPath}`);
+      await invoke('copy_model', { source: sourcePath, destination: destPath });
 
       console.log('[copy_ckpt] File copied successfully');
       return {
@@ -112,28 +112,12 @@ export async function copy_ckpt(ckpt_filename, source, destination) {
       };
 
     } catch (copyError) {
+      // The command fails with { code, message, details }: 3 no space, 4 permissions, 47 source missing...
       console.error('[copy_ckpt] File copy error:', copyError);
-
-      // Determine specific error type
-      if (copyError.message && copyError.message.includes('No space')) {
-        return {
-          code: 1,
-          result: false,
-          error: [{ code: 3, message: 'Insufficient disk space', details: copyError.message }]
-        };
-      }
-      if (copyError.message && copyError.message.includes('Permission denied')) {
-        return {
-          code: 1,
-          result: false,
-          error: [{ code: 4, message: 'Insufficient permissions', details: copyError.message }]
-        };
-      }
-
       return {
         code: 1,
         result: false,
-        error: [{ code: 10, message: 'File copy error', details: copyError.message }]
+        error: [command_error(copyError, 10, 'File copy error')]
       };
     }
 
@@ -1,7 +1,8 @@
 /**
  * prune_mac - Copy orphan ckpt from Mac to Stash and delete on Mac
  *
- * @returns {Object} { code: 0|1, result: [success_bool], error: [] }
+ * @param {boolean} [dry_run=false] - Only return the plan, don't move anything
+ * @returns {Object} { code: 0|1, result: [summary], error: [] }
  *
  * @notes Deleting Mac orphans requires checking that each ckpt is not being used by another model
  *
@@ -11,79 +12,61 @@
  * 10 - File copy error
  * 9 - File delete error
  * 36 - Parent models exist (file in use)
+ * 41 - Invalid JSON structure (a Draw Things registry has errors; nothing is pruned)
  * 100 - Unknown error
  *
  * IMPLEMENTATION NOTES:
  * Frees up Mac HD space by moving orphaned checkpoints to Stash.
- * - Query in-memory object for ckpts where location='mac' AND is_orphan=true
- * - For each orphan: copy to Stash, verify copy success, then delete from Mac
- * - Update in-memory object to set location='stash' for moved files
- * - Should check if file already exists in Stash before copying
- * - Return summary: files moved, space freed, any errors
- * - Consider batch processing with progress updates for large operations
+ * - Runs the Rust `prune_mac` command, which builds the plan from the model dependency graph:
+ *   orphans are files not listed in any DrawThings JSON and not used as an encoder by any model
+ * - For each orphan: copy to Stash, verify size + hash, then delete from Mac
+ * - An identical file already in Stash counts as copied; a different one is reported, not overwritten
+ * - Update in-memory object to remove moved files from the Mac lists
+ * - Return summary: files moved, space freed, skipped (still referenced), any errors
  */
-import { appState } from '../../appState.svelte.js';
-import { copy_ckpt } from './copy_ckpt.js';
-import { delete_ckpt } from './delete_ckpt.js';
-import { get_parents } from '../data/get_parents.js';
+import { invoke } from '@tauri-apps/api/core';
+import { appState, removeCkpt } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
 
-export async function prune_mac() {
-  console.log('[prune_mac] Starting');
+export async function prune_mac(dry_run = false) {
+  console.log(`[prune_mac] Starting${dry_run ? ' (dry run)' : ''}`);
 
   try {
-    const summary = {
-      attempted: 0,
-      moved: 0,
-      spaceFre: 0,
-      errors: []
-    };
-
-    // Find all orphans on Mac
-    const orphans = [];
-
-    for (const type of ['models', 'loras', 'controls']) {
-      for (const ckpt of appState.mac[type]) {
-        // Check if orphan (not in DrawThings JSON)
-        const parentsResult = await get_parents(ckpt.filename);
-        if (parentsResult.code === 0 && parentsResult.result.length === 0) {
-          orphans.push({ filename: ckpt.filename, type, size: ckpt.file_size || 0 });
-        }
-      }
+    const { DT_BASE_DIR, STASH_DIR } = appState.settings;
+    if (!DT_BASE_DIR || !STASH_DIR) {
+      const errorCode = !DT_BASE_DIR ? 18 : 19;
+      const errorMsg = !DT_BASE_DIR ? 'DT_BASE_DIR not configured' : 'STASH_DIR not configured';
+      console.error('[prune_mac]', errorMsg);
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: errorCode, message: errorMsg }]
+      };
     }
 
-    console.log(`[prune_mac] Found ${orphans.length} orphans`);
+    const result = await invoke('prune_mac', {
+      macBaseDir: DT_BASE_DIR,
+      stashBaseDir: STASH_DIR,
+      dryRun: dry_run
+    });
 
-    // Process each orphan
-    for (const orphan of orphans) {
-      summary.attempted++;
-
-      try {
-        // Copy to stash
-        const copyResult = await copy_ckpt(orphan.filename, 'mac', 'stash');
-
-        if (copyResult.code === 0) {
-          // Successfully copied, now delete from Mac
-          const deleteResult = await delete_ckpt('mac', orphan.filename);
+    const summary = {
+      attempted: result.planned.length,
+      moved: result.moved.length,
+      spaceFree: result.bytes_freed,
+      skippedReferenced: result.skipped_referenced,
+      errors: result.failures.map(f => ({ file: f.filename, ...f.error })),
+      plan: result.planned
+    };
 
-          if (deleteResult.code === 0) {
-            summary.moved++;
-            summary.spaceFree += orphan.size;
-            console.log(`[prune_mac] Moved: ${orphan.filename}`);
-          } else {
-            summary.errors.push({ file: orphan.filename, error: deleteResult.error });
-            console.warn(`[prune_mac] Failed to delete ${orphan.filename}:`, deleteResult.error);
-          }
-        } else {
-          summary.errors.push({ file: orphan.filename, error: copyResult.error });
-          console.warn(`[prune_mac] Failed to copy ${orphan.filename}:`, copyResult.error);
-        }
-      } catch (error) {
-        summary.errors.push({ file: orphan.filename, error: error.message });
-        console.error(`[prune_mac] Error processing ${orphan.filename}:`, error);
+    // Moved files are no longer on the Mac
+    for (const filename of result.moved) {
+      for (const type of ['model', 'lora', 'control']) {
+        removeCkpt('mac', type, filename);
       }
     }
 
-    console.log(`[prune_mac] Completed - moved ${summary.moved}/${summary.attempted} files`);
+    console.log(`[prune_mac] Completed - moved ${summary.moved}/${summary.attempted} files, freed ${summary.spaceFree} bytes`);
 
     return {
       code: summary.errors.length > 0 ? 1 : 0,
@@ -96,7 +79,7 @@ export async function prune_mac() {
     return {
       code: 1,
       result: null,
-      error: [{ code: 100, message: 'Unknown error', details: error.message }]
+      error: [command_error(error)]
     };
   }
 }
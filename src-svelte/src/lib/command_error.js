@@ -0,0 +1,20 @@
+/**
+ * command_error - Turn a failed Rust command into an entry for the `error` array
+ *
+ * @param {*} error - What the `invoke` promise rejected with
+ * @param {number} [fallbackCode=100] - Code to use if the error didn't come from a command
+ * @param {string} [fallbackMessage='Unknown error'] - Message to go with `fallbackCode`
+ * @returns {Object} { code, message, details }
+ *
+ * IMPLEMENTATION NOTES:
+ * Rust commands fail with `{ code, message, details }`, where `code` is the number from
+ * error_codes.md and `details` holds context such as paths and byte counts. That is already
+ * the shape of the entries in `{ code: 0|1, result, error: [] }`, so it is passed through.
+ * Anything else (JS exceptions, plugin errors) gets the fallback code.
+ */
+export function command_error(error, fallbackCode = 100, fallbackMessage = 'Unknown error') {
+  if (error && typeof error.code === 'number') {
+    return { code: error.code, message: error.message, details: error.details ?? null };
+  }
+  return { code: fallbackCode, message: fallbackMessage, details: error?.message ?? String(error) };
+}
@@ -0,0 +1,52 @@
+/**
+ * lookup_community_model - Finds a checkpoint in the community models dataset by filename or hash
+ *
+ * @param {string} [ckpt_filename] - The checkpoint filename (model or encoder)
+ * @param {string} [sha256] - SHA-256 of the file contents; wins over ckpt_filename when both are given
+ * @returns {Object} { code: 0|1, result: [match_or_null], error: [] }
+ *
+ * @notes match: { file, model_type, model: { id, model_type, version, name, file, url,
+ *        autoencoder, clip_encoder, text_encoder, image_encoder, t5_encoder, hashes, entry } }
+ * @notes match.model_type: model, lora, control, vae, clip, text, image_encoder
+ *
+ * ERROR CODES:
+ * 16 - Settings invalid JSON
+ * 53 - Parquet file unavailable
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Runs the Rust `lookup_community_model` command, which reads community-models.parquet
+ * natively (no DuckDB): STASH_DIR/App_Data/community-models.parquet when
+ * check_parquet_updates has downloaded it, else the copy bundled with the app.
+ * - A filename matches a model's own file first, then any model using it as an encoder
+ * - Hashes are the `converted` SHA-256s from Draw Things; invoke('meta', { filepath, hash: 'sha256' }) gives one
+ * - Not found is NOT an error: result is null
+ * get_model_types already falls back to this dataset for files missing from the JSON.
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { command_error } from '../command_error.js';
+
+export async function lookup_community_model(ckpt_filename = null, sha256 = null) {
+  console.log(`[lookup_community_model] Starting for: ${ckpt_filename ?? sha256}`);
+
+  try {
+    const match = await invoke('lookup_community_model', {
+      file: ckpt_filename,
+      sha256
+    });
+
+    return {
+      code: 0,
+      result: match,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[lookup_community_model] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
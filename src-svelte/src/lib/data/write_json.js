@@ -20,13 +20,14 @@
  * Updates DrawThings' JSON configuration files.
  * - ONLY write to DT_BASE_DIR (Mac) - DrawThings reads from here
  * - Map type to file: model→custom.json, lora→custom_lora.json, control→custom_controlnet.json
- * - Use @tauri-apps/plugin-fs writeTextFile() with JSON.stringify()
- * - Consider backing up original file before writing
- * - Preserve exact JSON structure that DrawThings expects
+ * - Writes via the Rust `write_custom_json` command: temp file + fsync + atomic rename,
+ *   with a timestamped backup of the previous version in DTC_APP_DIR/backups
+ * - Unchanged entries keep their exact text (unknown keys, key order, number formatting)
  * - This modifies DrawThings' config, so be cautious!
  */
-import { writeTextFile } from '@tauri-apps/plugin-fs';
+import { invoke } from '@tauri-apps/api/core';
 import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
 
 export async function write_json(location, type, obj) {
   console.log(`[write_json] Starting - location: ${location}, type: ${type}`);
@@ -70,6 +71,17 @@ export async function write_json(location, type, obj) {
       };
     }
 
+    // Backups go to DTC_APP_DIR
+    const appDir = appState.settings.DTC_APP_DIR;
+    if (!appDir) {
+      console.error('[write_json] DTC_APP_DIR not configured');
+      return {
+        code: 1,
+        result: false,
+        error: [{ code: 11, message: 'Directory not found', details: 'DTC_APP_DIR not configured' }]
+      };
+    }
+
     // Validate JSON structure
     if (!Array.isArray(obj)) {
       console.error('[write_json] Invalid JSON structure - must be array');
@@ -85,11 +97,15 @@ export async function write_json(location, type, obj) {
     console.log('[write_json] Writing to:', jsonPath);
 
     try {
-      // Write JSON file (pretty-printed)
-      const jsonContent = JSON.stringify(obj, null, 2);
-      await writeTextFile(jsonPath, jsonContent);
+      // Atomic write with backup - returns the backup path (null if there was no previous file)
+      const backupPath = await invoke('write_custom_json', {
+        baseDir,
+        appDir,
+        kind: type,
+        entries: obj
+      });
 
-      console.log('[write_json] Successfully wrote JSON file');
+      console.log('[write_json] Successfully wrote JSON file, backup:', backupPath);
       return {
         code: 0,
         result: true,
@@ -101,7 +117,7 @@ export async function write_json(location, type, obj) {
       return {
         code: 1,
         result: false,
-        error: [{ code: 40, message: 'JSON write error', details: writeError.message }]
+        error: [command_error(writeError, 40, 'JSON write error')]
       };
     }
 
@@ -13,13 +13,15 @@
  *
  * IMPLEMENTATION NOTES:
  * Retrieves available disk space for Mac HD or Stash drive.
- * - Use Bash command via Tauri: `df -k` then parse output
+ * - Runs the Rust `disk_space` command (statfs/statvfs), which also reports total/used bytes,
+ *   mount point and file system type, with a warning for exFAT and network volumes
  * - For Mac: query path containing DT_BASE_DIR
  * - For Stash: query STASH_DIR (external drive)
- * - Return bytes free (convert from KB)
+ * - Return bytes free
  */
-import { Command } from '@tauri-apps/plugin-shell';
+import { invoke } from '@tauri-apps/api/core';
 import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
 
 export async function get_disk_space(location) {
   console.log(`[get_disk_space] Starting - location: ${location}`);
@@ -40,63 +42,28 @@ export async function get_disk_space(location) {
     }
 
     try {
-      // Use df command to get disk space (returns KB)
-      const command = Command.create('df', ['-k', targetDir]);
-      const output = await command.execute();
+      // { path, mount_point, fs_type, total_bytes, used_bytes, available_bytes, warning }
+      const space = await invoke('disk_space', { path: targetDir });
 
-      if (output.code !== 0) {
-        console.error('[get_disk_space] df command failed:', output.stderr);
-        return {
-          code: 1,
-          result: null,
-          error: [{ code: 100, message: 'Unknown error', details: output.stderr }]
-        };
+      if (space.warning) {
+        console.warn(`[get_disk_space] ${space.mount_point} is ${space.fs_type} (${space.warning})`);
       }
-
-      // Parse df output
-      const lines = output.stdout.trim().split('\n');
-      if (lines.length < 2) {
-        console.error('[get_disk_space] Unexpected df output format');
-        return {
-          code: 1,
-          result: null,
-          error: [{ code: 100, message: 'Unknown error', details: 'Could not parse df output' }]
-        };
-      }
-
-      // Parse the data line (skip header)
-      const dataLine = lines[1].trim();
-      const parts = dataLine.split(/\s+/);
-
-      // Available space is typically the 4th column (index 3) in KB
-      const availableKB = parseInt(parts[3], 10);
-
-      if (isNaN(availableKB)) {
-        console.error('[get_disk_space] Could not parse available space');
-        return {
-          code: 1,
-          result: null,
-          error: [{ code: 100, message: 'Unknown error', details: 'Invalid disk space value' }]
-        };
-      }
-
-      // Convert KB to bytes
-      const availableBytes = availableKB * 1024;
-
-      console.log(`[get_disk_space] Available space: ${availableBytes} bytes`);
+      console.log(`[get_disk_space] Available space: ${space.available_bytes} bytes`);
 
       return {
         code: 0,
-        result: availableBytes,
+        result: space.available_bytes,
         error: []
       };
 
     } catch (error) {
-      console.error('[get_disk_space] Error executing df command:', error);
+      console.error('[get_disk_space] Error reading disk space:', error);
+      const errorCode = location === 'mac' ? 20 : 21;
+      const errorMsg = location === 'mac' ? 'DT_BASE_DIR path invalid' : 'STASH_DIR path invalid';
       return {
         code: 1,
         result: null,
-        error: [{ code: 100, message: 'Unknown error', details: error.message }]
+        error: [command_error(error, errorCode, errorMsg)]
       };
     }
 
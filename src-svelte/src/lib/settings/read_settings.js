@@ -17,6 +17,7 @@
  * - Read settings.json from DTC_APP_DIR using @tauri-apps/plugin-fs
  * - Merge: settings.json values override .env defaults
  * - Expand tilde paths (~) to full paths
+ * - Send `model_extensions` / `companion_suffixes` to Rust (`set_extension_policy`)
  * - Return merged configuration object
  * - If settings.json missing, return .env defaults only
  * - Called at app startup and when settings UI opens
@@ -24,6 +25,7 @@
 
 import { readTextFile, exists } from '@tauri-apps/plugin-fs';
 import { homeDir } from '@tauri-apps/api/path';
+import { invoke } from '@tauri-apps/api/core';
 import { appState } from '../../appState.svelte.js';
 
 export async function read_settings() {
@@ -53,7 +55,9 @@ export async function read_settings() {
       initialized_date: null,
       locations: ["mac", "stash"],
       ckpt_types: ["model", "lora", "control"],
-      ckpt_keys_types: ["file", "clip_encoder", "text_encoder", "autoencoder", "image_encoder", "preprocessor"]
+      ckpt_keys_types: ["file", "clip_encoder", "text_encoder", "autoencoder", "image_encoder", "preprocessor"],
+      model_extensions: ["ckpt", "safetensors", "pth", "pt"],
+      companion_suffixes: ["-tensordata"]
 
     };
 
@@ -119,6 +123,19 @@ export async function read_settings() {
     // Update appState with merged settings
     appState.settings = mergedSettings;
 
+    // Every Rust scan, prune and sync uses the same extension policy
+    try {
+      await invoke('set_extension_policy', {
+        value: {
+          model_extensions: mergedSettings.model_extensions,
+          companion_suffixes: mergedSettings.companion_suffixes
+        }
+      });
+    }
+    catch (policyError) {
+      console.warn('[read_settings] Could not set extension policy:', policyError);
+    }
+
     // Update init flag
     appState.init.settings_init = true;
 
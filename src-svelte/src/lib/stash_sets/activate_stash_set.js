@@ -0,0 +1,75 @@
+/**
+ * activate_stash_set - Make a Stash Set the models Draw Things shows
+ *
+ * @param {string} name - Name of the set
+ * @param {boolean} [evict=false] - Move every model not in the set to the Stash
+ * @param {boolean} [dry_run=false] - Only return what would happen
+ * @returns {Object} { code: 0|1, result: [activation] or [{ job_id }], error: [] }
+ *
+ * ERROR CODES:
+ * 3 - Insufficient disk space
+ * 10 - File copy error
+ * 18 - DT_BASE_DIR not configured
+ * 19 - STASH_DIR not configured
+ * 28 - Record not found (no such set)
+ * 37 - Child files missing (set members on neither Mac nor Stash)
+ * 41 - Invalid JSON structure (evict refused while a Mac registry has errors)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * - dry_run: Rust `plan_stash_set` returns { copied, bytes_copied, missing, registries, stashed, evicted, bytes_freed }
+ * - Otherwise Rust `start_activate_set_job` queues the activation and returns the job id;
+ *   progress and the final activation arrive as "job-update" events
+ * - Activation copies members, their encoders and companions from Stash to Mac, then rewrites
+ *   custom.json / custom_lora.json / custom_controlnet.json (backed up first), then evicts
+ * - Mac entries the set displaces (stashed) are saved to the Stash's JSON first, so switching
+ *   back to an earlier set finds them again
+ * - Nothing starts if a member is missing; evicted files are always verified in Stash first
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function activate_stash_set(name, evict = false, dry_run = false) {
+  console.log(`[activate_stash_set] "${name}"${dry_run ? ' (dry run)' : ''}`);
+
+  try {
+    const { DT_BASE_DIR, STASH_DIR, DTC_APP_DIR } = appState.settings;
+    if (!DT_BASE_DIR || !STASH_DIR) {
+      const errorCode = !DT_BASE_DIR ? 18 : 19;
+      const errorMsg = !DT_BASE_DIR ? 'DT_BASE_DIR not configured' : 'STASH_DIR not configured';
+      console.error('[activate_stash_set]', errorMsg);
+      return {
+        code: 1,
+        result: null,
+        error: [{ code: errorCode, message: errorMsg }]
+      };
+    }
+
+    const args = {
+      macBaseDir: DT_BASE_DIR,
+      stashBaseDir: STASH_DIR,
+      appDir: DTC_APP_DIR,
+      name,
+      evict
+    };
+
+    const result = dry_run
+      ? await invoke('plan_stash_set', args)
+      : { job_id: await invoke('start_activate_set_job', args) };
+
+    return {
+      code: 0,
+      result,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[activate_stash_set] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
@@ -0,0 +1,39 @@
+/**
+ * delete_stash_set - Remove a saved Stash Set (models are not touched)
+ *
+ * @param {string} name - Name of the set
+ * @returns {Object} { code: 0|1, result: [sets], error: [] }
+ *
+ * ERROR CODES:
+ * 8 - File write error
+ * 28 - Record not found
+ * 100 - Unknown error
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function delete_stash_set(name) {
+  try {
+    const sets = await invoke('delete_stash_set', {
+      appDir: appState.settings.DTC_APP_DIR,
+      name
+    });
+    appState.stash_sets = sets;
+    console.log(`[delete_stash_set] Deleted "${name}"`);
+
+    return {
+      code: 0,
+      result: sets,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[delete_stash_set] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
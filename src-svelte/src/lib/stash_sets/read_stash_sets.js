@@ -0,0 +1,41 @@
+/**
+ * read_stash_sets - Load the saved Stash Sets into appState.stash_sets
+ *
+ * @returns {Object} { code: 0|1, result: [sets], error: [] }
+ *
+ * ERROR CODES:
+ * 7 - File read error
+ * 39 - JSON parse error
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Stash Sets live in DTC_APP_DIR/stash_sets.json (Rust `list_stash_sets`).
+ * Each set: { name, models: [file], loras: [file], controlnets: [file], updated }
+ * - Lists are registry `file` names in display order
+ * - No file yet means no sets
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function read_stash_sets() {
+  try {
+    const sets = await invoke('list_stash_sets', { appDir: appState.settings.DTC_APP_DIR });
+    appState.stash_sets = sets;
+    console.log(`[read_stash_sets] Loaded ${sets.length} sets`);
+
+    return {
+      code: 0,
+      result: sets,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[read_stash_sets] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
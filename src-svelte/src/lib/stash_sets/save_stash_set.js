@@ -0,0 +1,44 @@
+/**
+ * save_stash_set - Create a Stash Set, or replace the one with the same name
+ *
+ * @param {Object} set - { name, models: [file], loras: [file], controlnets: [file] }
+ * @returns {Object} { code: 0|1, result: [sets], error: [] }
+ *
+ * ERROR CODES:
+ * 8 - File write error
+ * 41 - Invalid JSON structure (no name)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Runs the Rust `save_stash_set` command, which writes DTC_APP_DIR/stash_sets.json atomically.
+ * - List order is the display order the set gives Draw Things
+ * - Updates appState.stash_sets with the saved list
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { appState } from '../../appState.svelte.js';
+import { command_error } from '../command_error.js';
+
+export async function save_stash_set(set) {
+  try {
+    const sets = await invoke('save_stash_set', {
+      appDir: appState.settings.DTC_APP_DIR,
+      set
+    });
+    appState.stash_sets = sets;
+    console.log(`[save_stash_set] Saved "${set.name}"`);
+
+    return {
+      code: 0,
+      result: sets,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[save_stash_set] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
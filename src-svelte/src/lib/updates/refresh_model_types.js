@@ -0,0 +1,57 @@
+/**
+ * refresh_model_types - Revalidates the cached model type lists (models, loras, controlnets, embeddings)
+ *
+ * @param {boolean} [offline=false] - Only read the cache, no network
+ * @returns {Object} { code: 0|1, result: [{ registry, outcomes }], error: [] }
+ *
+ * ERROR CODES:
+ * 7 - File read error
+ * 8 - File write error
+ * 17 - Settings corrupt (github_model_types is not an object of strings)
+ * 52 - Invalid download URL (relative list without base_url)
+ * 100 - Unknown error
+ *
+ * IMPLEMENTATION NOTES:
+ * Lists come from settings.github_model_types: { base_url, models, loras, controlnets, embeddings },
+ * each a URL or a path under base_url (MODEL_TYPES_BASE_URL in .env overrides base_url).
+ * The Rust side keeps them in DTC_APP_DIR/model_types_cache.json with etag, last_modified,
+ * fetched_at and updated_at per list, and sends If-None-Match / If-Modified-Since so an
+ * unchanged list costs a 304.
+ * - A network failure is NOT an error: that list's outcome has status "failed" and its
+ *   cached filenames are still served (and used by get_model_types)
+ * - outcomes[].status: "updated" | "not_modified" | "failed"
+ * - With offline=true, result is { registry, outcomes: [] } straight from the cache
+ * - An unparsable cache is logged and read as empty; the next refresh rewrites it
+ */
+import { invoke } from '@tauri-apps/api/core';
+import { command_error } from '../command_error.js';
+
+export async function refresh_model_types(offline = false) {
+  console.log(`[refresh_model_types] Starting (offline: ${offline})`);
+
+  try {
+    const report = offline
+      ? { registry: await invoke('get_model_type_registry'), outcomes: [] }
+      : await invoke('refresh_model_types');
+
+    for (const outcome of report.outcomes) {
+      if (outcome.status === 'failed') {
+        console.warn(`[refresh_model_types] ${outcome.kind}: using cache (${outcome.error})`);
+      }
+    }
+
+    return {
+      code: 0,
+      result: report,
+      error: []
+    };
+
+  } catch (error) {
+    console.error('[refresh_model_types] Error:', error);
+    return {
+      code: 1,
+      result: null,
+      error: [command_error(error)]
+    };
+  }
+}
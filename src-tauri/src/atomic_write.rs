@@ -0,0 +1,131 @@
+use chrono::Utc;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Subdirectory of DTC_APP_DIR that holds previous versions of overwritten files
+pub const BACKUP_DIR: &str = "backups";
+
+/// Replace `path` with `contents` so that readers (and a crash) only ever see the
+/// old file or the new one: write a temp file beside it, fsync, then rename over.
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let filename = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+        .to_string_lossy();
+
+    // Same directory, so the rename never crosses filesystems
+    let temp_path = dir.join(format!(".{}.{}.tmp", filename, std::process::id()));
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+
+        // Keep the permissions of the file being replaced
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+
+        fs::rename(&temp_path, path)?;
+        sync_dir(dir)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Flush a directory entry change (the rename) to disk
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Copy the current version of `path` to `<app_dir>/backups/<stem>.<timestamp>.<ext>`.
+/// Returns the backup path, or None if there was nothing to back up.
+pub fn backup_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    app_dir: Q,
+) -> io::Result<Option<PathBuf>> {
+    let path = path.as_ref();
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let backup_dir = app_dir.as_ref().join(BACKUP_DIR);
+    fs::create_dir_all(&backup_dir)?;
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+
+    // Two writes in the same millisecond get a counter rather than overwriting each other
+    let mut backup_path = backup_dir.join(format!("{}.{}{}", stem, timestamp, extension));
+    let mut counter = 1;
+    while backup_path.exists() {
+        backup_path = backup_dir.join(format!("{}.{}-{}{}", stem, timestamp, counter, extension));
+        counter += 1;
+    }
+
+    fs::copy(path, &backup_path)?;
+    Ok(Some(backup_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic_replaces_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.json");
+
+        write_atomic(&path, b"[]").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[]");
+
+        write_atomic(&path, b"[{}]").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[{}]");
+
+        // Only the target is left behind, no temp files
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        assert!(write_atomic(dir.path().join("missing").join("x.json"), b"[]").is_err());
+    }
+
+    #[test]
+    fn test_backup_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let app_dir = dir.path().join("app");
+        let path = dir.path().join("custom_lora.json");
+
+        assert_eq!(backup_file(&path, &app_dir).unwrap(), None);
+
+        fs::write(&path, b"[1]").unwrap();
+        let first = backup_file(&path, &app_dir).unwrap().unwrap();
+        let second = backup_file(&path, &app_dir).unwrap().unwrap();
+
+        assert_ne!(first, second);
+        assert!(first.starts_with(app_dir.join(BACKUP_DIR)));
+        let name = first.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("custom_lora.") && name.ends_with(".json"));
+        assert_eq!(fs::read(&second).unwrap(), b"[1]");
+    }
+}
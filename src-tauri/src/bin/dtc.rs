@@ -0,0 +1,903 @@
+//! `dtc` - the companion's model management without the GUI, for scripts, cron jobs and
+//! SSH sessions. Runs the same library code as the app and reads the same settings.json.
+//!
+//! Every command prints text, or JSON with `--json` (the same shapes the app's commands
+//! return). Errors go to stderr as `{ code, message, details }` with the error_codes.md
+//! number. Exit status is 1 on error, and also when `sync`/`prune` had failures or
+//! `verify`/`audit` found problems.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use draw_things_companion_lib::community_models::CommunityModels;
+use draw_things_companion_lib::copy_engine::CopyProgress;
+use draw_things_companion_lib::dt_json::{CustomJsonFile, CustomJsonKind};
+use draw_things_companion_lib::duplicates;
+use draw_things_companion_lib::error::{AppError, AppResult, ErrorCode};
+use draw_things_companion_lib::file_hash::{self, HashCache, HashMode};
+use draw_things_companion_lib::model_inspect;
+use draw_things_companion_lib::model_scan;
+use draw_things_companion_lib::model_types::{
+    self, GithubModelTypes, ModelTypeRegistry, RefreshReport, RefreshStatus,
+};
+use draw_things_companion_lib::prune;
+use draw_things_companion_lib::settings::{self, Settings};
+use draw_things_companion_lib::stash_audit::{self, AuditOptions};
+use draw_things_companion_lib::stash_sets::{self, ActivateOptions};
+use draw_things_companion_lib::sync_plan::{self, PlanOptions, SyncPlan, SyncStatus};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(
+    name = "dtc",
+    version,
+    about = "Draw Things Companion: Mac and stash model management"
+)]
+struct Cli {
+    /// Print JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Draw Things data directory [default: DT_BASE_DIR from settings.json or the environment]
+    #[arg(long, global = true, value_name = "DIR")]
+    mac: Option<PathBuf>,
+
+    /// Stash directory [default: STASH_DIR from settings.json or the environment]
+    #[arg(long, global = true, value_name = "DIR")]
+    stash: Option<PathBuf>,
+
+    /// Directory holding settings.json and JSON backups [default: DTC_APP_DIR or ~/.drawthings_companion]
+    #[arg(long, global = true, value_name = "DIR")]
+    app_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List and classify the files in a Models directory
+    Scan {
+        #[arg(value_enum, default_value_t = Location::Mac)]
+        location: Location,
+    },
+    /// Compare the Mac and the stash without changing anything
+    Plan {
+        /// Hash same-sized files instead of trusting size and mtime (slow)
+        #[arg(long)]
+        hash: bool,
+        /// Also list identical files and entries
+        #[arg(long)]
+        all: bool,
+    },
+    /// Copy new and newer models and registry entries from the Mac to the stash
+    Sync {
+        /// Hash same-sized files instead of trusting size and mtime (slow)
+        #[arg(long)]
+        hash: bool,
+        /// Only print the plan
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move orphaned models (in no registry, used by no model) from the Mac to the stash
+    Prune {
+        /// Only print what would be moved
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Hash every model on both sides and report stash copies that differ from the Mac
+    Verify,
+    /// Check every stash file against its recorded hash; flag truncated, corrupt and missing files
+    Audit {
+        /// Also compare with the Mac copies
+        #[arg(long)]
+        compare_mac: bool,
+    },
+    /// Find models stored under more than one filename, and which JSON entries use each
+    Dupes {
+        /// Keep the suggested copy of each, repoint the JSON at it and delete the rest
+        #[arg(long)]
+        apply: bool,
+    },
+    /// List the tensors in a .ckpt or .safetensors and infer its family and role
+    Inspect {
+        file: PathBuf,
+        /// Print every tensor's name, dtype and shape
+        #[arg(long)]
+        tensors: bool,
+    },
+    /// Find a model or encoder in the community models dataset by filename or SHA-256.
+    /// A local file not found by name is hashed and looked up by content.
+    Lookup {
+        /// Filename, path or SHA-256
+        query: String,
+    },
+    /// Show the cached model type lists (github_model_types in settings.json)
+    Types {
+        /// Revalidate each list over the network first; unreachable lists keep their cache
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// List the Draw Things registries (custom.json, custom_lora.json, ...) in display order
+    List {
+        #[arg(value_enum, default_value_t = Location::Mac)]
+        location: Location,
+        /// Only this registry: model, lora, control, embedding, upscaler, face_restorer
+        #[arg(long, value_parser = parse_kind)]
+        kind: Option<CustomJsonKind>,
+    },
+    /// Read or change settings.json
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// List or activate Stash Sets
+    #[command(subcommand)]
+    Sets(SetsCommand),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print one setting, or all of them
+    Get { key: Option<String> },
+    /// Set a value; JSON (`true`, `["ckpt"]`) is stored as such, anything else as a string
+    Set { key: String, value: String },
+}
+
+#[derive(Subcommand)]
+enum SetsCommand {
+    /// Print the saved sets
+    List,
+    /// Copy a set's models from the stash and make Draw Things show only them
+    Activate {
+        name: String,
+        /// Move every model not in the set to the stash
+        #[arg(long)]
+        evict: bool,
+        /// Only print what would happen
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Location {
+    Mac,
+    Stash,
+}
+
+fn parse_kind(value: &str) -> Result<CustomJsonKind, String> {
+    serde_json::from_value(Value::from(value)).map_err(|_| format!("unknown registry: {}", value))
+}
+
+/// Resolved directories and output mode
+struct Context {
+    json: bool,
+    app_dir: PathBuf,
+    settings: Settings,
+    mac: Option<PathBuf>,
+    stash: Option<PathBuf>,
+}
+
+impl Context {
+    fn mac(&self) -> AppResult<PathBuf> {
+        self.mac
+            .clone()
+            .map_or_else(|| self.settings.dt_base_dir(), Ok)
+    }
+
+    fn stash(&self) -> AppResult<PathBuf> {
+        self.stash
+            .clone()
+            .map_or_else(|| self.settings.stash_dir(), Ok)
+    }
+
+    fn base_dir(&self, location: Location) -> AppResult<PathBuf> {
+        match location {
+            Location::Mac => self.mac(),
+            Location::Stash => self.stash(),
+        }
+    }
+
+    /// JSON to stdout in `--json` mode, else the text from `human`
+    fn print<T: Serialize>(&self, value: &T, human: impl FnOnce(&T)) {
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(value).unwrap_or_default()
+            );
+        } else {
+            human(value);
+        }
+    }
+
+    /// Copy progress on stderr (text mode only), so stdout stays clean for pipes
+    fn progress(&self) -> impl FnMut(&CopyProgress) {
+        let quiet = self.json;
+        move |p: &CopyProgress| {
+            if quiet {
+                return;
+            }
+            let percent = match p.total_bytes {
+                0 => 100,
+                total => p.bytes_copied * 100 / total,
+            };
+            let name = Path::new(&p.destination)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            eprint!("\r  {} {:>3}%", name, percent);
+            if p.done {
+                eprintln!();
+            }
+            let _ = std::io::stderr().flush();
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    settings::load_env();
+    let cli = Cli::parse();
+
+    let json = cli.json;
+    let app_dir = cli.app_dir.unwrap_or_else(settings::default_app_dir);
+    let result = Settings::load(&app_dir).and_then(|settings| {
+        let context = Context {
+            json,
+            app_dir,
+            settings,
+            mac: cli.mac,
+            stash: cli.stash,
+        };
+        run(cli.command, context)
+    });
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            if json {
+                eprintln!(
+                    "{}",
+                    serde_json::to_string_pretty(&error).unwrap_or_default()
+                );
+            } else {
+                eprintln!("error {}: {}", error.code.code(), error.message);
+                for (key, value) in &error.details {
+                    eprintln!("  {}: {}", key, value);
+                }
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run one command; `Ok(false)` means it finished but something needs attention
+fn run(command: Command, mut context: Context) -> AppResult<bool> {
+    let policy = context.settings.extension_policy();
+    let cache = HashCache::new();
+
+    match command {
+        Command::Scan { location } => {
+            let listing = model_scan::scan_models_dir_with(context.base_dir(location)?, &policy)?;
+            context.print(&listing, |listing| {
+                for file in &listing.files {
+                    println!(
+                        "{:>10}  {:<12}  {}",
+                        format_bytes(file.file_size),
+                        format!("{:?}", file.class),
+                        file.relative_path
+                    );
+                }
+                let total: u64 = listing.files.iter().map(|f| f.file_size).sum();
+                println!(
+                    "{} files, {} in {}",
+                    listing.files.len(),
+                    format_bytes(total),
+                    listing.models_dir
+                );
+                for error in &listing.errors {
+                    eprintln!("warning: {}", error);
+                }
+            });
+            Ok(true)
+        }
+
+        Command::Plan { hash, all } => {
+            let options = PlanOptions {
+                compare_hashes: hash,
+                policy,
+            };
+            let plan = sync_plan::plan_sync(context.mac()?, context.stash()?, &options, &cache)?;
+            context.print(&plan, |plan| print_plan(plan, all));
+            Ok(true)
+        }
+
+        Command::Sync { hash, dry_run } => {
+            let options = PlanOptions {
+                compare_hashes: hash,
+                policy,
+            };
+            let plan = sync_plan::plan_sync(context.mac()?, context.stash()?, &options, &cache)?;
+            if dry_run {
+                context.print(&plan, |plan| print_plan(plan, false));
+                return Ok(true);
+            }
+
+            let summary =
+                sync_plan::sync_to_stash(&plan, &context.app_dir, None, context.progress())?;
+            context.print(&summary, |summary| {
+                println!(
+                    "Copied {} files ({}), updated {} registry entries",
+                    summary.copied.len(),
+                    format_bytes(summary.bytes_copied),
+                    summary.json_updated.len()
+                );
+                for conflict in &summary.conflicts {
+                    println!("  conflict (not synced): {}", conflict);
+                }
+                for failure in &summary.failures {
+                    println!(
+                        "  failed: {}: {}",
+                        failure.relative_path, failure.error.message
+                    );
+                }
+            });
+            Ok(summary.failures.is_empty())
+        }
+
+        Command::Prune { dry_run } => {
+            let progress = context.progress();
+            let summary = prune::prune_mac(
+                context.mac()?,
+                context.stash()?,
+                &policy,
+                dry_run,
+                None,
+                progress,
+            )?;
+            context.print(&summary, |summary| {
+                for item in &summary.planned {
+                    println!("{:>10}  {}", format_bytes(item.size), item.relative_path);
+                }
+                for skipped in &summary.skipped_referenced {
+                    println!(
+                        "  kept {} (used by {})",
+                        skipped.filename,
+                        skipped.parents.join(", ")
+                    );
+                }
+                match summary.dry_run {
+                    true => println!(
+                        "Would move {} files ({})",
+                        summary.planned.len(),
+                        format_bytes(summary.planned_bytes)
+                    ),
+                    false => println!(
+                        "Moved {} files, freed {}",
+                        summary.moved.len(),
+                        format_bytes(summary.bytes_freed)
+                    ),
+                }
+                for failure in &summary.failures {
+                    println!("  failed: {}: {}", failure.filename, failure.error.message);
+                }
+            });
+            Ok(summary.failures.is_empty())
+        }
+
+        Command::Verify => {
+            let options = PlanOptions {
+                compare_hashes: true,
+                policy,
+            };
+            let plan = sync_plan::plan_sync(context.mac()?, context.stash()?, &options, &cache)?;
+            let on_both: Vec<_> = plan
+                .files
+                .iter()
+                .filter(|f| f.mac.is_some() && f.stash.is_some())
+                .collect();
+            let differing: Vec<_> = on_both
+                .iter()
+                .filter(|f| f.status != SyncStatus::Identical)
+                .collect();
+            let ok = differing.is_empty() && plan.warnings.is_empty();
+
+            context.print(&differing, |differing| {
+                for file in differing.iter() {
+                    println!("  differs: {}", file.relative_path);
+                }
+                for warning in &plan.warnings {
+                    eprintln!("warning: {}", warning);
+                }
+                println!(
+                    "{} of {} files on both sides match",
+                    on_both.len() - differing.len(),
+                    on_both.len()
+                );
+            });
+            Ok(ok)
+        }
+
+        Command::Audit { compare_mac } => {
+            let options = AuditOptions {
+                mac_base_dir: match compare_mac {
+                    true => Some(context.mac()?),
+                    false => None,
+                },
+                policy,
+            };
+            let quiet = context.json;
+            let report = stash_audit::audit_stash(context.stash()?, &options, None, |p| {
+                if !quiet && p.files_done < p.files_total {
+                    eprint!(
+                        "\r  {}/{} {:<60.60}",
+                        p.files_done + 1,
+                        p.files_total,
+                        p.current
+                    );
+                }
+            })?;
+            if !quiet {
+                eprintln!();
+            }
+
+            context.print(&report, |report| {
+                for entry in report.problems() {
+                    println!(
+                        "{:<12}  {}",
+                        serde_label(&entry.status),
+                        entry.relative_path
+                    );
+                }
+                let counts: Vec<String> = report
+                    .counts
+                    .iter()
+                    .map(|(status, n)| format!("{} {}", n, serde_label(status)))
+                    .collect();
+                println!(
+                    "Hashed {} ({}); manifest {}",
+                    format_bytes(report.bytes_hashed),
+                    counts.join(", "),
+                    report.manifest
+                );
+            });
+            Ok(!report.files.iter().any(|f| f.status.is_problem()))
+        }
+
+        Command::Dupes { apply } => {
+            let (mac, stash) = (context.mac()?, context.stash()?);
+            let report = duplicates::find_duplicates(&mac, &stash, &policy, &cache)?;
+            if !apply {
+                context.print(&report, |report| {
+                    for group in &report.groups {
+                        println!(
+                            "{}  keep {}",
+                            format_bytes(group.size),
+                            group.suggested.keep
+                        );
+                        for copy in &group.copies {
+                            println!(
+                                "  {:<6} {}",
+                                serde_label(&copy.location),
+                                copy.relative_path
+                            );
+                        }
+                        for r in &group.references {
+                            println!(
+                                "  {:<6} {}: {} -> {} ({})",
+                                serde_label(&r.location),
+                                r.json_file,
+                                r.entry_name.as_deref().unwrap_or(&r.entry_file),
+                                r.references,
+                                r.key
+                            );
+                        }
+                    }
+                    for warning in &report.warnings {
+                        eprintln!("warning: {}", warning);
+                    }
+                    println!(
+                        "{} duplicated models, {} reclaimable",
+                        report.groups.len(),
+                        format_bytes(report.wasted_bytes)
+                    );
+                });
+                return Ok(true);
+            }
+
+            let results: Vec<_> = report
+                .groups
+                .iter()
+                .map(|group| {
+                    duplicates::apply_dedupe(
+                        &group.suggested,
+                        &mac,
+                        &stash,
+                        &context.app_dir,
+                        &policy,
+                    )
+                })
+                .collect::<Result<_, _>>()?;
+            context.print(&results, |results| {
+                for (group, result) in report.groups.iter().zip(results.iter()) {
+                    match &result.failed {
+                        None => println!("kept {}", group.suggested.keep),
+                        Some((_, error)) => {
+                            println!("  failed: {}: {}", group.suggested.keep, error.message)
+                        }
+                    }
+                }
+                let freed: u64 = results.iter().map(|r| r.bytes_freed).sum();
+                println!("Freed {}", format_bytes(freed));
+            });
+            Ok(results.iter().all(|r| r.failed.is_none()))
+        }
+
+        Command::Inspect { file, tensors } => {
+            let mut info = model_inspect::inspect_model(&file)?;
+            if !tensors {
+                info.tensors.clear();
+            }
+            context.print(&info, |info| {
+                for tensor in &info.tensors {
+                    println!(
+                        "{:<6} {:<24} {}",
+                        tensor.dtype,
+                        format!("{:?}", tensor.shape),
+                        tensor.name
+                    );
+                }
+                let dtypes: Vec<String> = info
+                    .dtypes
+                    .iter()
+                    .map(|(dtype, n)| format!("{} {}", n, dtype))
+                    .collect();
+                println!(
+                    "{} tensors ({}), {} parameters",
+                    info.tensor_count,
+                    dtypes.join(", "),
+                    info.total_params
+                );
+                println!(
+                    "{} {} ({})",
+                    serde_label(&info.family),
+                    serde_label(&info.role),
+                    info.hints.join("; ")
+                );
+                if let Some(metadata) = &info.metadata {
+                    if let Some(version) = &metadata.base_model_version {
+                        println!("base model: {}", version);
+                    }
+                    if let Some(dim) = metadata.network_dim {
+                        println!("network dim: {}", dim);
+                    }
+                    if !metadata.trigger_words.is_empty() {
+                        println!("trigger words: {}", metadata.trigger_words.join(", "));
+                    }
+                }
+            });
+            Ok(true)
+        }
+
+        Command::Lookup { query } => {
+            let community = CommunityModels::load_default(context.stash().ok().as_deref())?;
+            let path = Path::new(&query);
+            let filename = path
+                .file_name()
+                .map_or(query.clone(), |n| n.to_string_lossy().to_string());
+            let mut found = if query.len() == 64 && query.chars().all(|c| c.is_ascii_hexdigit()) {
+                community.find_by_hash(&query)
+            } else {
+                community.find(&filename)
+            };
+            if found.is_none() && path.is_file() {
+                let sha256 = file_hash::hash_file(path, HashMode::Sha256)
+                    .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", path))?;
+                found = community.find_by_hash(&sha256);
+            }
+
+            context.print(&found, |found| match found {
+                Some(found) => {
+                    let model = &found.model;
+                    println!("{} ({})", found.file, found.model_type);
+                    println!(
+                        "{}: {} [{}]",
+                        model.model_type,
+                        model.name,
+                        model.version.as_deref().unwrap_or("?")
+                    );
+                    if found.file != model.file {
+                        println!("used by: {}", model.file);
+                    }
+                    for (file, kind) in model.encoders() {
+                        println!("  {:<13} {}", kind, file);
+                    }
+                    if let Some(sha256) = model.hashes.get(&found.file) {
+                        println!("sha256: {}", sha256);
+                    }
+                }
+                None => println!("{} is not in the community models dataset", query),
+            });
+            Ok(found.is_some())
+        }
+
+        Command::Types { refresh } => {
+            let report = if refresh {
+                let config = GithubModelTypes::from_settings(&context.settings)?;
+                model_types::refresh_registry(&context.app_dir, &config)?
+            } else {
+                RefreshReport {
+                    registry: ModelTypeRegistry::load(&context.app_dir)?,
+                    outcomes: Vec::new(),
+                }
+            };
+            if let Some(reason) = &report.registry.discarded {
+                eprintln!("warning: {}", reason);
+            }
+            context.print(&report, |report| {
+                for outcome in &report.outcomes {
+                    println!(
+                        "{:<12} {:<13} {}",
+                        serde_label(&outcome.kind),
+                        serde_label(&outcome.status),
+                        outcome.error.as_deref().unwrap_or(&outcome.url)
+                    );
+                }
+                for (kind, list) in &report.registry.lists {
+                    println!(
+                        "{:<12} {:>6} files, fetched {}  {}",
+                        serde_label(kind),
+                        list.filenames.len(),
+                        list.fetched_at.as_deref().unwrap_or("never"),
+                        list.url
+                    );
+                }
+                if report.registry.lists.is_empty() {
+                    println!("no model type lists cached");
+                }
+            });
+            Ok(report
+                .outcomes
+                .iter()
+                .all(|o| o.status != RefreshStatus::Failed))
+        }
+
+        Command::List { location, kind } => {
+            let models_dir = context.base_dir(location)?.join("Models");
+            let kinds: Vec<CustomJsonKind> = match kind {
+                Some(kind) => vec![kind],
+                None => CustomJsonKind::ALL.to_vec(),
+            };
+
+            let mut registries = serde_json::Map::new();
+            for kind in kinds {
+                let json = CustomJsonFile::read(&models_dir, kind)?;
+                registries.insert(
+                    kind.file_name().to_string(),
+                    Value::from(json.entries::<Value>()?),
+                );
+            }
+
+            context.print(&registries, |registries| {
+                for (file_name, entries) in registries {
+                    let entries = entries.as_array().cloned().unwrap_or_default();
+                    if entries.is_empty() {
+                        continue;
+                    }
+                    println!("{}", file_name);
+                    for (i, entry) in entries.iter().enumerate() {
+                        let file = entry["file"].as_str().unwrap_or("");
+                        let missing = match models_dir.join(file).exists() {
+                            true => "",
+                            false => "  (missing)",
+                        };
+                        println!(
+                            "{:>4}. {}  [{}]{}",
+                            i + 1,
+                            entry["name"].as_str().unwrap_or(file),
+                            file,
+                            missing
+                        );
+                    }
+                }
+            });
+            Ok(true)
+        }
+
+        Command::Sets(SetsCommand::List) => {
+            let sets = stash_sets::load_sets(&context.app_dir)?;
+            context.print(&sets, |sets| {
+                for set in sets {
+                    println!(
+                        "{}  ({} models, {} LoRAs, {} ControlNets)",
+                        set.name,
+                        set.models.len(),
+                        set.loras.len(),
+                        set.controlnets.len()
+                    );
+                }
+            });
+            Ok(true)
+        }
+
+        Command::Sets(SetsCommand::Activate {
+            name,
+            evict,
+            dry_run,
+        }) => {
+            let set = stash_sets::find_set(&context.app_dir, &name)?;
+            let options = ActivateOptions {
+                evict,
+                dry_run,
+                policy,
+            };
+            let activation = stash_sets::activate_set(
+                context.mac()?,
+                context.stash()?,
+                &context.app_dir,
+                &set,
+                &options,
+                None,
+                context.progress(),
+            )?;
+            context.print(&activation, |activation| {
+                let (copy, evict) = match activation.dry_run {
+                    true => ("Would copy", "would evict"),
+                    false => ("Copied", "evicted"),
+                };
+                for file in &activation.missing {
+                    println!("  missing: {}", file);
+                }
+                for failure in &activation.failures {
+                    println!("  failed: {}: {}", failure.file, failure.error.message);
+                }
+                println!(
+                    "{} {} files ({}), {} {} files ({})",
+                    copy,
+                    activation.copied.len(),
+                    format_bytes(activation.bytes_copied),
+                    evict,
+                    activation.evicted.len(),
+                    format_bytes(activation.bytes_freed)
+                );
+            });
+            Ok(activation.failures.is_empty() && activation.missing.is_empty())
+        }
+
+        Command::Config(ConfigCommand::Get { key }) => {
+            let value = match key {
+                Some(key) => context.settings.get(&key).cloned().ok_or_else(|| {
+                    AppError::new(
+                        ErrorCode::RecordNotFound,
+                        format!("No setting named {}", key),
+                    )
+                    .with("key", &key)
+                    .with("path", context.settings.path())
+                })?,
+                None => Value::Object(context.settings.values().clone()),
+            };
+            context.print(&value, |value| match value {
+                Value::String(s) => println!("{}", s),
+                other => println!(
+                    "{}",
+                    serde_json::to_string_pretty(other).unwrap_or_default()
+                ),
+            });
+            Ok(true)
+        }
+
+        Command::Config(ConfigCommand::Set { key, value }) => {
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            context.settings.set(&key, value.clone());
+            context.settings.save()?;
+            context.print(&value, |value| println!("{} = {}", key, value));
+            Ok(true)
+        }
+    }
+}
+
+fn print_plan(plan: &SyncPlan, all: bool) {
+    let label = |status: SyncStatus| match status {
+        SyncStatus::NewOnMac => "new on mac",
+        SyncStatus::NewInStash => "new in stash",
+        SyncStatus::Changed => "changed",
+        SyncStatus::Identical => "identical",
+        SyncStatus::Conflicting => "conflicting",
+    };
+
+    for file in &plan.files {
+        if all || file.status != SyncStatus::Identical {
+            let newer = match file.newer {
+                Some(side) => format!(" ({:?} newer)", side).to_lowercase(),
+                None => String::new(),
+            };
+            println!(
+                "{:<12}  {}{}",
+                label(file.status),
+                file.relative_path,
+                newer
+            );
+        }
+    }
+    for entry in &plan.json_entries {
+        if all || entry.status != SyncStatus::Identical {
+            println!(
+                "{:<12}  {}: {}",
+                label(entry.status),
+                entry.kind.file_name(),
+                entry.file
+            );
+        }
+    }
+
+    let totals = &plan.totals;
+    for (status, tally) in [
+        (SyncStatus::NewOnMac, &totals.new_on_mac),
+        (SyncStatus::NewInStash, &totals.new_in_stash),
+        (SyncStatus::Changed, &totals.changed),
+        (SyncStatus::Conflicting, &totals.conflicting),
+        (SyncStatus::Identical, &totals.identical),
+    ] {
+        println!(
+            "{:>5} {:<12} {}",
+            tally.files,
+            label(status),
+            format_bytes(tally.bytes)
+        );
+    }
+    if let Some(space) = &plan.stash_space {
+        println!("Stash free: {}", format_bytes(space.available_bytes));
+    }
+    for warning in &plan.warnings {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+/// How an enum appears in the JSON output, e.g. `mac_mismatch`
+fn serde_label<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(label)) => label,
+        _ => String::new(),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", value, UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli =
+            Cli::try_parse_from(["dtc", "list", "stash", "--kind", "lora", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(
+            cli.command,
+            Command::List {
+                location: Location::Stash,
+                kind: Some(CustomJsonKind::Lora)
+            }
+        ));
+        assert!(Cli::try_parse_from(["dtc", "list", "--kind", "nope"]).is_err());
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
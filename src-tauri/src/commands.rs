@@ -0,0 +1,758 @@
+//! Tauri commands: thin wrappers over the library modules, registered in `run`
+
+use crate::community_models::{CommunityMatch, CommunityModelsCache};
+use crate::copy_engine::{self, CopyOptions, CopyProgress};
+use crate::diagnostics::{self, SupportBundle};
+use crate::dt_json::{CustomJsonFile, CustomJsonKind, DrawThingsConfig};
+use crate::dt_lint::Diagnostic;
+use crate::duplicates::{self, DedupePlan, DedupeResult, DuplicateGroup, DuplicateReport};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_hash::{HashCache, HashMode};
+use crate::file_meta::{self, FileMetadata};
+use crate::file_ops::{self, DiskSpace};
+use crate::file_policy::ExtensionPolicy;
+use crate::jobs::{self, JobId, JobInfo, JobManager};
+use crate::logger::{FileLogger, LogEvent, LogLevel, LogPage, LogQuery, LOG_EVENT};
+use crate::model_graph::{GraphReport, ModelGraph};
+use crate::model_inspect::{self, ModelInspection};
+use crate::model_scan::{self, ModelsListing};
+use crate::model_types::{self, GithubModelTypes, ModelTypeRegistry, RefreshReport};
+use crate::prune::{self, PruneSummary};
+use crate::settings;
+use crate::stash_audit::{self, AuditManifest, AuditOptions};
+use crate::stash_sets::{self, ActivateOptions, Activation, StashSet};
+use crate::sync_plan::{self, PlanOptions, SyncPlan};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+// ################################################################################
+// Extension policy used by every scan, prune and sync; set from settings.json by the frontend
+type PolicyState<'a> = State<'a, RwLock<ExtensionPolicy>>;
+
+/// Run blocking work (file copies, HTTP) on the runtime's blocking pool, off the async workers
+async fn blocking<T, F>(work: F) -> AppResult<T>
+where
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?
+}
+
+fn current_policy(policy: &PolicyState<'_>) -> ExtensionPolicy {
+    policy.read().map(|p| p.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+fn get_extension_policy(policy: PolicyState<'_>) -> ExtensionPolicy {
+    current_policy(&policy)
+}
+
+// Which extensions are models, which filename suffixes are companions (`-tensordata`), which are partials
+#[tauri::command]
+fn set_extension_policy(policy: PolicyState<'_>, value: ExtensionPolicy) {
+    if let Ok(mut current) = policy.write() {
+        *current = value;
+    }
+}
+
+// ################################################################################
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum MetaResult {
+    Object(FileMetadata),
+    String(String),
+}
+
+// ################################################################################
+// A function that takes a single filepath as an arguments and; (1) checks the files exists, returns array of filesize, create datetime, update datetime
+// Optional `hash` ("sha256" | "blake3" | "fingerprint") adds a content hash, cached by (path, size, mtime)
+// Hashing reads the whole file, so the work runs on the blocking pool
+#[tauri::command]
+async fn meta(
+    app: AppHandle,
+    filepath: String,
+    stringify: Option<bool>,
+    hash: Option<HashMode>,
+) -> AppResult<MetaResult> {
+    let should_stringify = stringify.unwrap_or(false);
+
+    let metadata_result = blocking(move || {
+        let cache = app.state::<HashCache>();
+        Ok(file_meta::read_metadata(&filepath, hash, &cache))
+    })
+    .await?;
+
+    if should_stringify {
+        let json_string = serde_json::to_string(&metadata_result)
+            .unwrap_or_else(|_| "Error serializing metadata".to_string());
+        Ok(MetaResult::String(json_string))
+    } else {
+        Ok(MetaResult::Object(metadata_result))
+    }
+}
+
+// ################################################################################
+// Batch version of `meta`: takes a list of filepaths and/or a directory plus glob patterns (eg "*.ckpt")
+// Metadata is gathered in parallel and returned keyed by path; failures are reported per entry
+#[tauri::command]
+async fn meta_many(
+    app: AppHandle,
+    filepaths: Option<Vec<String>>,
+    dir: Option<String>,
+    patterns: Option<Vec<String>>,
+    hash: Option<HashMode>,
+) -> AppResult<HashMap<String, FileMetadata>> {
+    blocking(move || {
+        let mut paths = filepaths.unwrap_or_default();
+
+        if let Some(dir) = dir {
+            let patterns = patterns.unwrap_or_default();
+            paths.extend(file_meta::list_matching_files(&dir, &patterns)?);
+        }
+
+        let cache = app.state::<HashCache>();
+        Ok(file_meta::read_metadata_many(&paths, hash, &cache))
+    })
+    .await
+}
+
+// ################################################################################
+// Recursively scans `<base_dir>/Models` (DT_BASE_DIR or STASH_DIR) and classifies every file:
+// ckpt, safetensors, other model formats, companions, sidecar JSON, quantized variants (_f16/_q6p/_q8p)
+// and temp/partial files
+#[tauri::command]
+fn scan_models_dir(base_dir: String, policy: PolicyState<'_>) -> AppResult<ModelsListing> {
+    model_scan::scan_models_dir_with(&base_dir, &current_policy(&policy))
+}
+
+// ################################################################################
+// Type of every model file in `<base_dir>/Models`, taken from the Draw Things registries
+// (custom*.json incl. embeddings, upscalers and face restorers), then the cached model type lists and the
+// community models dataset. Unlisted safetensors are typed from their header (lora, model, vae, text, control);
+// anything else is "unknown".
+#[tauri::command]
+fn get_model_types(
+    base_dir: String,
+    log: State<'_, FileLogger>,
+    policy: PolicyState<'_>,
+    community: State<'_, CommunityModelsCache>,
+) -> AppResult<HashMap<String, String>> {
+    let listing = model_scan::scan_models_dir_with(&base_dir, &current_policy(&policy))?;
+    let config = DrawThingsConfig::parse_from_directory(&listing.models_dir)?;
+    let registry = load_model_types(&log)?;
+    let community = community.get(log.app_dir())?;
+    Ok(listing.model_types(&config, &registry, &community))
+}
+
+// ################################################################################
+// Looks a file up in the community models dataset (community-models.parquet: the copy downloaded to
+// `<STASH_DIR>/App_Data`, else the one bundled with the app) by `file` name or by `sha256`.
+// Matches a model's own file or one of its encoders; returns { file, model_type, model } or null.
+// The dataset stays loaded between calls and is re-read when the downloaded copy changes.
+#[tauri::command]
+fn lookup_community_model(
+    log: State<'_, FileLogger>,
+    community: State<'_, CommunityModelsCache>,
+    file: Option<String>,
+    sha256: Option<String>,
+) -> AppResult<Option<CommunityMatch>> {
+    let community = community.get(log.app_dir())?;
+    Ok(match (file, sha256) {
+        (_, Some(sha256)) => community.find_by_hash(&sha256),
+        (Some(file), None) => community.find(&file),
+        (None, None) => None,
+    })
+}
+
+// ################################################################################
+// Model type lists (`github_model_types` in settings.json), cached in `<DTC_APP_DIR>/model_types_cache.json`.
+// Returns the cache as is, with each list's url, etag, fetched_at/updated_at and last error; no network.
+#[tauri::command]
+fn get_model_type_registry(log: State<'_, FileLogger>) -> AppResult<ModelTypeRegistry> {
+    load_model_types(&log)
+}
+
+// Revalidates each list with a conditional GET (ETag/Last-Modified). Offline or on an HTTP error the cached
+// list is kept and the error recorded. Returns { registry, outcomes: [{ kind, url, status, count, error }] }.
+#[tauri::command]
+async fn refresh_model_types(log: State<'_, FileLogger>) -> AppResult<RefreshReport> {
+    let app_dir = log.app_dir().to_path_buf();
+    let config = GithubModelTypes::from_settings(&settings::Settings::load(&app_dir)?)?;
+    let report = blocking(move || model_types::refresh_registry(&app_dir, &config)).await?;
+    log_discarded(&log, &report.registry);
+    Ok(report)
+}
+
+/// A corrupt cache reads as empty (a refresh rewrites it); the reason goes to the log
+fn load_model_types(log: &FileLogger) -> AppResult<ModelTypeRegistry> {
+    let registry = ModelTypeRegistry::load(log.app_dir())?;
+    log_discarded(log, &registry);
+    Ok(registry)
+}
+
+fn log_discarded(log: &FileLogger, registry: &ModelTypeRegistry) {
+    if let Some(reason) = &registry.discarded {
+        let _ = log.log(LogLevel::Warning, reason);
+    }
+}
+
+// ################################################################################
+// Validates every Draw Things registry in `<base_dir>/Models` and returns one diagnostic per problem
+// (file, index, problem): missing keys, duplicate files, encoders missing on disk, LoRA bounds, unknown versions
+#[tauri::command]
+fn lint_custom_json(base_dir: String) -> Vec<Diagnostic> {
+    let models_dir = Path::new(&base_dir).join("Models");
+    DrawThingsConfig::parse_with_report(&models_dir).1
+}
+
+// ################################################################################
+// Parent/child links between models and their encoders (VAE, CLIP, T5) in `<base_dir>/Models`,
+// with the number of models using each encoder file and the list of shared ones
+#[tauri::command]
+fn model_graph(base_dir: String) -> AppResult<GraphReport> {
+    let models_dir = Path::new(&base_dir).join("Models");
+    let config = DrawThingsConfig::parse_from_directory(&models_dir)?;
+    Ok(ModelGraph::from_config(&config).report())
+}
+
+// ################################################################################
+// Opens a Draw Things `.ckpt` (SQLite tensor store) read-only and lists its tensors: name, shape, dtype, params.
+// Infers the family (sd15, sd2, sdxl, sd3, flux) and role (unet, vae, text_encoder, lora, control_net)
+// from tensor names and shapes instead of the filename. `.safetensors` are read from their JSON header,
+// including the `__metadata__` block (base model, LoRA dim, trigger words).
+#[tauri::command]
+fn inspect_ckpt(path: String) -> AppResult<ModelInspection> {
+    model_inspect::inspect_model(&path)
+}
+
+// ################################################################################
+// Deletes `<base_dir>/Models/<filename>`, refusing while other models still use it (error 36, or 44 if shared)
+// and while any registry has errors (41), since the references in it can't be checked
+#[tauri::command]
+fn delete_model(base_dir: String, filename: String) -> AppResult<()> {
+    let models_dir = Path::new(&base_dir).join("Models");
+    let config = DrawThingsConfig::parse_checked(&models_dir)?;
+    ModelGraph::from_config(&config).check_deletable(&filename)?;
+
+    let path = models_dir.join(&filename);
+    if !path.is_file() {
+        return Err(AppError::from(ErrorCode::FileNotFound).with("path", &path));
+    }
+    file_ops::delete_file(&path)?;
+    // A stash keeps audit records; drop this one so it isn't reported missing (no-op elsewhere)
+    let _ = AuditManifest::forget(&base_dir, &[filename]);
+    Ok(())
+}
+
+// ################################################################################
+// Moves orphaned files (not listed in any registry, not used by any model) from the Mac to the stash,
+// together with their companions. Each file is copied, verified by size + BLAKE3, then deleted from the Mac.
+// `dry_run` only returns the plan.
+#[tauri::command]
+async fn prune_mac(
+    mac_base_dir: String,
+    stash_base_dir: String,
+    dry_run: Option<bool>,
+    policy: PolicyState<'_>,
+) -> AppResult<PruneSummary> {
+    let policy = current_policy(&policy);
+    blocking(move || {
+        prune::prune_mac(
+            &mac_base_dir,
+            &stash_base_dir,
+            &policy,
+            dry_run.unwrap_or(false),
+            None,
+            |_| {},
+        )
+    })
+    .await
+}
+
+// ################################################################################
+// Dry run of a Mac <-> stash sync: compares both `Models` directories and the Draw Things registries and
+// returns each file/entry as new_on_mac, new_in_stash, changed, identical or conflicting, with byte totals.
+// Nothing is copied; the UI previews the plan for approval. `hash` compares same-sized files by BLAKE3.
+#[tauri::command]
+async fn plan_sync(
+    app: AppHandle,
+    mac_base_dir: String,
+    stash_base_dir: String,
+    hash: Option<bool>,
+    policy: PolicyState<'_>,
+) -> AppResult<SyncPlan> {
+    let options = PlanOptions {
+        compare_hashes: hash.unwrap_or(false),
+        policy: current_policy(&policy),
+    };
+    blocking(move || {
+        let cache = app.state::<HashCache>();
+        sync_plan::plan_sync(&mac_base_dir, &stash_base_dir, &options, &cache)
+    })
+    .await
+}
+
+// ################################################################################
+// Copies a (large) model file in chunks via `<destination>.partial`, resuming an interrupted copy.
+// Emits "copy-progress" events with bytes copied, rate and ETA. Returns the file size.
+#[tauri::command]
+async fn copy_model(app: AppHandle, source: String, destination: String) -> AppResult<u64> {
+    if !Path::new(&source).is_file() {
+        return Err(AppError::from(ErrorCode::SourceMissing).with("source", &source));
+    }
+    blocking(move || {
+        copy_engine::copy_with_progress(
+            &source,
+            &destination,
+            &CopyOptions::default(),
+            None,
+            |progress: &CopyProgress| {
+                let _ = app.emit(copy_engine::PROGRESS_EVENT, progress.clone());
+            },
+        )
+        .map_err(|e| {
+            AppError::io(ErrorCode::FileCopy, &e)
+                .with("source", &source)
+                .with("destination", &destination)
+        })
+    })
+    .await
+}
+
+// ################################################################################
+// Capacity (total/used/available bytes), mount point and file system type of the volume holding `path`.
+// `warning` is set for volumes that make a poor stash: "exfat" (no journal) or "network_share".
+#[tauri::command]
+fn disk_space(path: String) -> AppResult<DiskSpace> {
+    file_ops::disk_space(&path)
+}
+
+// ################################################################################
+// # Background jobs
+// Long-running copies and prunes run in the app's job queue: one worker per destination volume,
+// so two copies never race on the same disk. Jobs emit "job-update" events (status + copy progress)
+// and can be inspected with `list_jobs` / `get_job` and stopped with `cancel_job`.
+
+// Queue a copy; returns the job ID. Cancelling removes the `.partial` file.
+#[tauri::command]
+fn start_copy_job(jobs: State<'_, JobManager>, source: String, destination: String) -> JobId {
+    let volume = jobs::volume_key(&destination);
+    let description = format!("Copy {} to {}", source, destination);
+
+    jobs.submit("copy", &description, &volume, move |ctx| {
+        let result = copy_engine::copy_with_progress(
+            &source,
+            &destination,
+            &CopyOptions::default(),
+            Some(ctx.cancel_flag()),
+            |progress| ctx.report_progress(progress),
+        );
+        match result {
+            Ok(bytes) => Ok(serde_json::Value::from(bytes)),
+            Err(e) => {
+                if ctx.is_cancelled() {
+                    let _ = std::fs::remove_file(copy_engine::partial_path(&destination));
+                }
+                Err(AppError::io(ErrorCode::FileCopy, &e)
+                    .with("source", &source)
+                    .with("destination", &destination))
+            }
+        }
+    })
+}
+
+// Queue `prune_mac` (see above); the job result is the prune summary
+#[tauri::command]
+fn start_prune_job(
+    jobs: State<'_, JobManager>,
+    mac_base_dir: String,
+    stash_base_dir: String,
+    dry_run: Option<bool>,
+    policy: PolicyState<'_>,
+) -> JobId {
+    let volume = jobs::volume_key(&stash_base_dir);
+    let description = format!("Prune {} to {}", mac_base_dir, stash_base_dir);
+    let policy = current_policy(&policy);
+
+    jobs.submit("prune", &description, &volume, move |ctx| {
+        let summary = prune::prune_mac(
+            &mac_base_dir,
+            &stash_base_dir,
+            &policy,
+            dry_run.unwrap_or(false),
+            Some(ctx.cancel_flag()),
+            |progress| ctx.report_progress(progress),
+        )?;
+        serde_json::to_value(summary).map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))
+    })
+}
+
+// Queue a stash integrity audit: every stash model is hashed in full and checked against the hashes recorded
+// in `<stash>/App_Data/stash_hashes.json` (and against the Mac copy when `mac_base_dir` is given).
+// Emits "audit-progress" events; the job result is the audit report (ok/new/updated/truncated/corrupt/missing...).
+#[tauri::command]
+fn start_audit_job(
+    app: AppHandle,
+    jobs: State<'_, JobManager>,
+    stash_base_dir: String,
+    mac_base_dir: Option<String>,
+    policy: PolicyState<'_>,
+) -> JobId {
+    let volume = jobs::volume_key(&stash_base_dir);
+    let description = format!("Audit {}", stash_base_dir);
+    let options = AuditOptions {
+        mac_base_dir: mac_base_dir.map(Into::into),
+        policy: current_policy(&policy),
+    };
+
+    jobs.submit("audit", &description, &volume, move |ctx| {
+        let report = stash_audit::audit_stash(
+            &stash_base_dir,
+            &options,
+            Some(ctx.cancel_flag()),
+            |progress| {
+                let _ = app.emit(stash_audit::AUDIT_EVENT, progress.clone());
+            },
+        )?;
+        serde_json::to_value(report).map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))
+    })
+}
+
+// Recorded hash, last verified time and last status of every stash file, without hashing anything
+#[tauri::command]
+fn stash_audit_records(stash_base_dir: String) -> AppResult<AuditManifest> {
+    AuditManifest::load(&stash_base_dir)
+}
+
+// ################################################################################
+// Duplicate finder: models stored under more than one filename across the Mac and stash `Models` directories,
+// grouped by size then BLAKE3. Each group lists its copies, the JSON entries referencing each filename, and a
+// suggested plan keeping one filename. `plan_dedupe` re-plans for a chosen keeper; `apply_dedupe` carries it out.
+#[tauri::command]
+async fn find_duplicates(
+    app: AppHandle,
+    mac_base_dir: String,
+    stash_base_dir: String,
+    policy: PolicyState<'_>,
+) -> AppResult<DuplicateReport> {
+    let policy = current_policy(&policy);
+    blocking(move || {
+        let cache = app.state::<HashCache>();
+        duplicates::find_duplicates(&mac_base_dir, &stash_base_dir, &policy, &cache)
+    })
+    .await
+}
+
+#[tauri::command]
+fn plan_dedupe(group: DuplicateGroup, keep: Option<String>) -> AppResult<DedupePlan> {
+    duplicates::plan_dedupe(&group, keep.as_deref())
+}
+
+// Renames, repoints the JSON (backed up to `<app_dir>/backups`) and deletes each duplicate after re-verifying it
+// against the kept copy. Stops at the first failure; the result lists what was done. Refuses to start (41)
+// while a registry on either side has errors.
+#[tauri::command]
+async fn apply_dedupe(
+    mac_base_dir: String,
+    stash_base_dir: String,
+    app_dir: String,
+    plan: DedupePlan,
+    policy: PolicyState<'_>,
+) -> AppResult<DedupeResult> {
+    let policy = current_policy(&policy);
+    blocking(move || {
+        duplicates::apply_dedupe(&plan, &mac_base_dir, &stash_base_dir, &app_dir, &policy)
+    })
+    .await
+}
+
+#[tauri::command]
+fn list_jobs(jobs: State<'_, JobManager>) -> Vec<JobInfo> {
+    jobs.list()
+}
+
+#[tauri::command]
+fn get_job(jobs: State<'_, JobManager>, id: JobId) -> Option<JobInfo> {
+    jobs.get(id)
+}
+
+// Returns false if the job is unknown or already finished
+#[tauri::command]
+fn cancel_job(jobs: State<'_, JobManager>, id: JobId) -> bool {
+    jobs.cancel(id)
+}
+
+// ################################################################################
+// # Stash Sets
+// Named groups of models (e.g. "SDXL", "Flux Full") kept in `<app_dir>/stash_sets.json`, each a list of
+// models, LoRAs and ControlNets in display order. Activating a set copies its files (with encoders and
+// companions) from the stash, makes custom.json / custom_lora.json / custom_controlnet.json list only the set
+// and, with `evict`, moves every other model to the stash.
+
+#[tauri::command]
+fn list_stash_sets(app_dir: String) -> AppResult<Vec<StashSet>> {
+    stash_sets::load_sets(&app_dir)
+}
+
+// Add or replace (by name) a set; returns all sets
+#[tauri::command]
+fn save_stash_set(app_dir: String, set: StashSet) -> AppResult<Vec<StashSet>> {
+    stash_sets::save_set(&app_dir, set)
+}
+
+// Returns the remaining sets
+#[tauri::command]
+fn delete_stash_set(app_dir: String, name: String) -> AppResult<Vec<StashSet>> {
+    stash_sets::delete_set(&app_dir, &name)
+}
+
+// What activating the set would copy, rewrite and evict, and which files are missing. Nothing is touched.
+#[tauri::command]
+fn plan_stash_set(
+    mac_base_dir: String,
+    stash_base_dir: String,
+    app_dir: String,
+    name: String,
+    evict: Option<bool>,
+    policy: PolicyState<'_>,
+) -> AppResult<Activation> {
+    let set = stash_sets::find_set(&app_dir, &name)?;
+    let options = ActivateOptions {
+        evict: evict.unwrap_or(false),
+        dry_run: true,
+        policy: current_policy(&policy),
+    };
+    stash_sets::activate_set(
+        &mac_base_dir,
+        &stash_base_dir,
+        &app_dir,
+        &set,
+        &options,
+        None,
+        |_| {},
+    )
+}
+
+// Queue an activation; the job result is the activation summary
+#[tauri::command]
+fn start_activate_set_job(
+    jobs: State<'_, JobManager>,
+    mac_base_dir: String,
+    stash_base_dir: String,
+    app_dir: String,
+    name: String,
+    evict: Option<bool>,
+    policy: PolicyState<'_>,
+) -> AppResult<JobId> {
+    let set = stash_sets::find_set(&app_dir, &name)?;
+    let volume = jobs::volume_key(&mac_base_dir);
+    let description = format!("Activate Stash Set {}", name);
+    let options = ActivateOptions {
+        evict: evict.unwrap_or(false),
+        dry_run: false,
+        policy: current_policy(&policy),
+    };
+
+    Ok(
+        jobs.submit("activate_set", &description, &volume, move |ctx| {
+            let activation = stash_sets::activate_set(
+                &mac_base_dir,
+                &stash_base_dir,
+                &app_dir,
+                &set,
+                &options,
+                Some(ctx.cancel_flag()),
+                |progress| ctx.report_progress(progress),
+            )?;
+            serde_json::to_value(activation)
+                .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))
+        }),
+    )
+}
+
+// ################################################################################
+// # Draw Things JSON writers
+// All writers take DT_BASE_DIR (`base_dir`) and DTC_APP_DIR (`app_dir`). Each write goes to a temp file,
+// is fsynced and renamed over the original, after a timestamped backup is copied to `<app_dir>/backups`.
+// Unchanged entries keep their exact text, so the file only differs where it was edited.
+// Returns the backup path, or null if there was no previous file.
+fn edit_custom_json(
+    base_dir: &str,
+    app_dir: &str,
+    kind: CustomJsonKind,
+    edit: impl FnOnce(&mut CustomJsonFile) -> AppResult<()>,
+) -> AppResult<Option<String>> {
+    let models_dir = Path::new(base_dir).join("Models");
+    let mut file = CustomJsonFile::read(&models_dir, kind)?;
+    edit(&mut file)?;
+    let backup = file.write(&models_dir, app_dir)?;
+    Ok(backup.map(|p| p.to_string_lossy().to_string()))
+}
+
+// Replace the whole list for `kind` ("model" | "lora" | "control")
+#[tauri::command]
+fn write_custom_json(
+    base_dir: String,
+    app_dir: String,
+    kind: CustomJsonKind,
+    entries: Vec<serde_json::Value>,
+) -> AppResult<Option<String>> {
+    edit_custom_json(&base_dir, &app_dir, kind, |file| {
+        file.replace_entries(&entries)
+    })
+}
+
+// Move the entry for `file` to `position` (0-based display order)
+#[tauri::command]
+fn reorder_custom_json(
+    base_dir: String,
+    app_dir: String,
+    kind: CustomJsonKind,
+    file: String,
+    position: usize,
+) -> AppResult<Option<String>> {
+    edit_custom_json(&base_dir, &app_dir, kind, |json| {
+        json.move_to(&file, position)
+    })
+}
+
+// Change the display name of the entry for `file`
+#[tauri::command]
+fn rename_custom_json(
+    base_dir: String,
+    app_dir: String,
+    kind: CustomJsonKind,
+    file: String,
+    name: String,
+) -> AppResult<Option<String>> {
+    edit_custom_json(&base_dir, &app_dir, kind, |json| json.rename(&file, &name))
+}
+
+// Set the default weight of a LoRA in custom_lora.json
+#[tauri::command]
+fn set_lora_weight(
+    base_dir: String,
+    app_dir: String,
+    file: String,
+    value: f64,
+) -> AppResult<Option<String>> {
+    edit_custom_json(&base_dir, &app_dir, CustomJsonKind::Lora, |json| {
+        json.set_lora_weight(&file, value)
+    })
+}
+
+// ################################################################################
+// Development logging (see logging.md): the frontend sends each log line here instead of the console.
+// Appends a JSON line to `<DTC_APP_DIR>/z_logs/logger.log` (rotated at 5 MB, 5 files kept) and emits "log-event".
+#[tauri::command]
+fn logger(
+    app: AppHandle,
+    log: State<'_, FileLogger>,
+    level: LogLevel,
+    message: String,
+) -> AppResult<LogEvent> {
+    let event = log.log(level, &message)?;
+    let _ = app.emit(LOG_EVENT, event.clone());
+    Ok(event)
+}
+
+// Logged history from logger.log and its rotated files, newest first.
+// `query`: { levels: ["error", ...], offset, limit }; returns { events, total, offset, skipped }
+#[tauri::command]
+fn get_all_logs(log: State<'_, FileLogger>, query: Option<LogQuery>) -> AppResult<LogPage> {
+    log.read(&query.unwrap_or_default())
+}
+
+// ################################################################################
+// State snapshots for debugging (see logging.md): writes/overwrites `<DTC_APP_DIR>/z_logs/<name>.json`.
+// Only mac_ckpts, mac_models, mac_loras, mac_controls, stash_*, settings and deleted_ckpts are accepted (error 48);
+// `json` is the frontend's `JSON.stringify(object)`.
+#[tauri::command]
+fn log_json_file(log: State<'_, FileLogger>, name: String, json: String) -> AppResult<String> {
+    let path = diagnostics::log_json_file(log.app_dir(), &name, &json)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+// Zips every snapshot, logger.log with its rotated files and settings.json (secrets redacted, home dir as `~`)
+// into `z_logs/support_bundle_<time>.zip` for attaching to bug reports. Returns { path, files, bytes }.
+#[tauri::command]
+fn create_support_bundle(log: State<'_, FileLogger>) -> AppResult<SupportBundle> {
+    diagnostics::create_support_bundle(log.app_dir())
+}
+
+// ################################################################################
+// # Tauri App Entry Point
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_http::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_sql::Builder::default().build())
+        .plugin(tauri_plugin_opener::init())
+        .manage(HashCache::new())
+        .manage(CommunityModelsCache::new())
+        .manage(JobManager::new())
+        .manage(RwLock::new(ExtensionPolicy::default()))
+        .manage({
+            settings::load_env();
+            FileLogger::new(settings::default_app_dir())
+        })
+        .setup(|app| {
+            let handle = app.handle().clone();
+            app.state::<JobManager>().set_listener(move |job| {
+                let _ = handle.emit(jobs::JOB_EVENT, job.clone());
+            });
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            get_extension_policy,
+            set_extension_policy,
+            meta,
+            meta_many,
+            scan_models_dir,
+            get_model_types,
+            get_model_type_registry,
+            refresh_model_types,
+            lookup_community_model,
+            lint_custom_json,
+            model_graph,
+            inspect_ckpt,
+            delete_model,
+            prune_mac,
+            plan_sync,
+            copy_model,
+            disk_space,
+            start_copy_job,
+            start_prune_job,
+            start_audit_job,
+            stash_audit_records,
+            find_duplicates,
+            plan_dedupe,
+            apply_dedupe,
+            logger,
+            get_all_logs,
+            log_json_file,
+            create_support_bundle,
+            list_jobs,
+            get_job,
+            cancel_job,
+            list_stash_sets,
+            save_stash_set,
+            delete_stash_set,
+            plan_stash_set,
+            start_activate_set_job,
+            write_custom_json,
+            reorder_custom_json,
+            rename_custom_json,
+            set_lora_weight
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -0,0 +1,382 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::settings::{Settings, SETTINGS_FILE};
+use bytes::Bytes;
+use parquet::file::reader::{ChunkReader, FileReader, SerializedFileReader};
+use parquet::record::Field;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// Name of the dataset, bundled from `src-svelte/public` and refreshed by
+/// `check_parquet_updates` into `<STASH_DIR>/App_Data`
+pub const PARQUET_FILE: &str = "community-models.parquet";
+
+const BUNDLED: &[u8] = include_bytes!("../../src-svelte/public/community-models.parquet");
+
+/// One row of the community models dataset: a Draw Things model, LoRA or ControlNet entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommunityModel {
+    pub id: Option<i32>,
+    /// "model", "uncurated_model", "lora" or "controlnet"
+    pub model_type: String,
+    /// Draw Things `version`, e.g. "v1", "sdxl_base_v0.9", "flux1"
+    pub version: Option<String>,
+    pub name: String,
+    pub file: String,
+    pub url: Option<String>,
+    pub autoencoder: Option<String>,
+    pub clip_encoder: Option<String>,
+    pub text_encoder: Option<String>,
+    pub image_encoder: Option<String>,
+    pub t5_encoder: Option<String>,
+    /// SHA-256 of each converted file (the model and its encoders), from `converted`
+    pub hashes: BTreeMap<String, String>,
+    /// The full Draw Things JSON entry
+    pub entry: Value,
+}
+
+impl CommunityModel {
+    /// The type `get_model_types` reports for the model's own file
+    pub fn file_type(&self) -> &'static str {
+        match self.model_type.as_str() {
+            "lora" => "lora",
+            "controlnet" => "control",
+            _ => "model",
+        }
+    }
+
+    /// Encoders this model uses, with the type they are reported as
+    pub fn encoders(&self) -> Vec<(&str, &'static str)> {
+        [
+            (&self.autoencoder, "vae"),
+            (&self.clip_encoder, "clip"),
+            (&self.text_encoder, "text"),
+            (&self.t5_encoder, "text"),
+            (&self.image_encoder, "image_encoder"),
+        ]
+        .into_iter()
+        .filter_map(|(file, kind)| file.as_deref().map(|file| (file, kind)))
+        .collect()
+    }
+}
+
+/// A file found in the dataset by name or hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommunityMatch {
+    /// The matched file: the model itself or one of its encoders
+    pub file: String,
+    pub model_type: String,
+    pub model: CommunityModel,
+}
+
+/// The community models dataset, indexed by filename and SHA-256
+#[derive(Debug, Clone, Default)]
+pub struct CommunityModels {
+    models: Vec<CommunityModel>,
+    by_file: HashMap<String, usize>,
+    by_hash: HashMap<String, (usize, String)>,
+    by_encoder: HashMap<String, (usize, &'static str)>,
+}
+
+impl CommunityModels {
+    /// The copy compiled into the app
+    pub fn bundled() -> AppResult<Self> {
+        Self::read(Bytes::from_static(BUNDLED), "bundled")
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> AppResult<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| AppError::io(ErrorCode::ParquetUnavailable, &e).with("path", path))?;
+        Self::read(file, &path.to_string_lossy())
+    }
+
+    /// `<stash_dir>/App_Data/community-models.parquet` when it has been downloaded
+    /// and reads, else the bundled copy
+    pub fn load_default(stash_dir: Option<&Path>) -> AppResult<Self> {
+        let downloaded = stash_dir.map(downloaded_path).filter(|path| path.is_file());
+        match downloaded.map(Self::load) {
+            Some(Ok(models)) => Ok(models),
+            _ => Self::bundled(),
+        }
+    }
+
+    fn read<R: ChunkReader + 'static>(reader: R, source: &str) -> AppResult<Self> {
+        let parquet_error = |e: parquet::errors::ParquetError| {
+            AppError::new(ErrorCode::ParquetUnavailable, e.to_string()).with("source", source)
+        };
+        let reader = SerializedFileReader::new(reader).map_err(parquet_error)?;
+
+        let mut models = Self::default();
+        for row in reader.get_row_iter(None).map_err(parquet_error)? {
+            let row = row.map_err(parquet_error)?;
+            let mut columns: HashMap<&str, &Field> = HashMap::new();
+            for (name, field) in row.get_column_iter() {
+                columns.insert(name.as_str(), field);
+            }
+            let text = |name: &str| match columns.get(name) {
+                Some(Field::Str(value)) if !value.is_empty() => Some(value.clone()),
+                _ => None,
+            };
+
+            let entry: Value = text("full_json")
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or(Value::Null);
+            let entry_text = |key: &str| {
+                entry
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+            };
+            let Some(file) = text("ckpt_file").or_else(|| entry_text("file")) else {
+                continue;
+            };
+
+            models.push(CommunityModel {
+                id: match columns.get("id") {
+                    Some(Field::Int(id)) => Some(*id),
+                    _ => None,
+                },
+                model_type: text("model_type").unwrap_or_else(|| "model".to_string()),
+                version: text("model_family").or_else(|| entry_text("version")),
+                name: text("model_name").unwrap_or_else(|| file.clone()),
+                url: text("url"),
+                autoencoder: text("autoencoder").or_else(|| entry_text("autoencoder")),
+                clip_encoder: text("clip_encoder").or_else(|| entry_text("clip_encoder")),
+                text_encoder: entry_text("text_encoder"),
+                image_encoder: text("image_encoder").or_else(|| entry_text("image_encoder")),
+                t5_encoder: text("t5_encoder").or_else(|| entry_text("t5_encoder")),
+                hashes: entry
+                    .get("converted")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .unwrap_or_default(),
+                file,
+                entry,
+            });
+        }
+        Ok(models)
+    }
+
+    fn push(&mut self, model: CommunityModel) {
+        let index = self.models.len();
+        self.by_file.entry(model.file.clone()).or_insert(index);
+        for (file, hash) in &model.hashes {
+            self.by_hash
+                .entry(hash.to_lowercase())
+                .or_insert((index, file.clone()));
+        }
+        for (file, kind) in model.encoders() {
+            self.by_encoder
+                .entry(file.to_string())
+                .or_insert((index, kind));
+        }
+        self.models.push(model);
+    }
+
+    pub fn models(&self) -> &[CommunityModel] {
+        &self.models
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// The entry whose own file is `filename`
+    pub fn find_by_file(&self, filename: &str) -> Option<&CommunityModel> {
+        self.by_file.get(filename).map(|&i| &self.models[i])
+    }
+
+    /// The entry that lists a file with this SHA-256, and which of its files it is
+    pub fn find_by_hash(&self, sha256: &str) -> Option<CommunityMatch> {
+        let (index, file) = self.by_hash.get(&sha256.to_lowercase())?;
+        let model = &self.models[*index];
+        Some(CommunityMatch {
+            file: file.clone(),
+            model_type: self.get_model_type(file).unwrap_or("unknown").to_string(),
+            model: model.clone(),
+        })
+    }
+
+    /// A model's own file, else the first model using it as an encoder
+    pub fn find(&self, filename: &str) -> Option<CommunityMatch> {
+        let (model, model_type) = self.locate(filename)?;
+        Some(CommunityMatch {
+            file: filename.to_string(),
+            model_type: model_type.to_string(),
+            model: model.clone(),
+        })
+    }
+
+    /// "model", "lora", "control", or for encoders "vae", "clip", "text", "image_encoder"
+    pub fn get_model_type(&self, filename: &str) -> Option<&'static str> {
+        self.locate(filename).map(|(_, model_type)| model_type)
+    }
+
+    fn locate(&self, filename: &str) -> Option<(&CommunityModel, &'static str)> {
+        if let Some(model) = self.find_by_file(filename) {
+            return Some((model, model.file_type()));
+        }
+        let &(index, kind) = self.by_encoder.get(filename)?;
+        Some((&self.models[index], kind))
+    }
+}
+
+/// `<stash_dir>/App_Data/community-models.parquet`
+pub fn downloaded_path(stash_dir: &Path) -> PathBuf {
+    stash_dir.join("App_Data").join(PARQUET_FILE)
+}
+
+/// Size and mtime of a file, None when it doesn't exist
+type Stamp = Option<(u64, Option<SystemTime>)>;
+
+fn stamp(path: &Path) -> Stamp {
+    fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| (metadata.len(), metadata.modified().ok()))
+}
+
+struct Loaded {
+    settings: Stamp,
+    stash_dir: Option<PathBuf>,
+    downloaded: Stamp,
+    models: Arc<CommunityModels>,
+}
+
+/// The dataset loaded once and shared between calls. settings.json is re-read only when it
+/// changes, and the dataset only when the Stash or its downloaded copy changes.
+pub struct CommunityModelsCache {
+    loaded: Mutex<Option<Loaded>>,
+}
+
+impl CommunityModelsCache {
+    pub fn new() -> Self {
+        Self {
+            loaded: Mutex::new(None),
+        }
+    }
+
+    /// The dataset for the Stash configured in `<app_dir>/settings.json`, as `load_default`
+    pub fn get(&self, app_dir: &Path) -> AppResult<Arc<CommunityModels>> {
+        // Held while loading so concurrent callers don't parse the file twice
+        let mut loaded = self.loaded.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let settings = stamp(&app_dir.join(SETTINGS_FILE));
+        let stash_dir = match loaded.as_ref() {
+            Some(current) if current.settings == settings => current.stash_dir.clone(),
+            _ => Settings::load(app_dir)?.stash_dir().ok(),
+        };
+        let downloaded = stash_dir
+            .as_deref()
+            .map(downloaded_path)
+            .and_then(|p| stamp(&p));
+
+        let models = match loaded.take() {
+            Some(current) if current.stash_dir == stash_dir && current.downloaded == downloaded => {
+                current.models
+            }
+            _ => Arc::new(CommunityModels::load_default(stash_dir.as_deref())?),
+        };
+        *loaded = Some(Loaded {
+            settings,
+            stash_dir,
+            downloaded,
+            models: Arc::clone(&models),
+        });
+        Ok(models)
+    }
+}
+
+impl Default for CommunityModelsCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_lookups() {
+        let models = CommunityModels::bundled().unwrap();
+        assert!(models.len() > 500);
+
+        let analog = models.find_by_file("analog_v1_f16.ckpt").unwrap();
+        assert_eq!(analog.name, "Analog (v1)");
+        assert_eq!(analog.version.as_deref(), Some("v1"));
+        assert_eq!(
+            analog.text_encoder.as_deref(),
+            Some("analog_v1_clip_vit_l14_f16.ckpt")
+        );
+        assert_eq!(analog.file_type(), "model");
+
+        let found = models
+            .find_by_hash("FFED9BB928A20F90F9881AC0D51E918C1580562F099FDD45C061C292DEC63AB5")
+            .unwrap();
+        assert_eq!(found.file, "analog_v1_f16.ckpt");
+        let found = models
+            .find_by_hash("f144ac4ad344c82c3b1dc69e46aba8d9c6bc20d24de9e48105a3db3e4437108d")
+            .unwrap();
+        assert_eq!(found.file, "analog_v1_clip_vit_l14_f16.ckpt");
+        assert_eq!(found.model_type, "text");
+
+        assert_eq!(models.get_model_type("sdxl_vae_v1.0_f16.ckpt"), Some("vae"));
+        assert!(models.find("not_a_model.ckpt").is_none());
+        assert!(models
+            .models()
+            .iter()
+            .any(|m| m.model_type == "lora" && m.file_type() == "lora"));
+    }
+
+    #[test]
+    fn test_load_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = CommunityModels::load(dir.path().join(PARQUET_FILE)).unwrap_err();
+        assert_eq!(error.code, ErrorCode::FileNotFound);
+
+        // A broken download falls back to the bundled copy
+        std::fs::create_dir_all(dir.path().join("App_Data")).unwrap();
+        std::fs::write(
+            dir.path().join("App_Data").join(PARQUET_FILE),
+            "PAR1 truncated",
+        )
+        .unwrap();
+        let error =
+            CommunityModels::load(dir.path().join("App_Data").join(PARQUET_FILE)).unwrap_err();
+        assert_eq!(error.code, ErrorCode::ParquetUnavailable);
+        let models = CommunityModels::load_default(Some(dir.path())).unwrap();
+        assert_eq!(models.len(), CommunityModels::bundled().unwrap().len());
+    }
+
+    #[test]
+    fn test_cache_reloads_on_download() {
+        let app_dir = tempfile::tempdir().unwrap();
+        let stash = tempfile::tempdir().unwrap();
+        let mut settings = Settings::load(app_dir.path()).unwrap();
+        settings.set("STASH_DIR", Value::from(stash.path().to_string_lossy()));
+        settings.save().unwrap();
+
+        let cache = CommunityModelsCache::new();
+        let first = cache.get(app_dir.path()).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get(app_dir.path()).unwrap()));
+
+        // A new download is picked up (this one is broken, so it falls back to the bundled copy)
+        let downloaded = downloaded_path(stash.path());
+        std::fs::create_dir_all(downloaded.parent().unwrap()).unwrap();
+        std::fs::write(&downloaded, "PAR1 truncated").unwrap();
+        let second = cache.get(app_dir.path()).unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(second.len(), first.len());
+        assert!(Arc::ptr_eq(&second, &cache.get(app_dir.path()).unwrap()));
+    }
+}
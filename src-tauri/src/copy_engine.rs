@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Tauri event carrying `CopyProgress` payloads
+pub const PROGRESS_EVENT: &str = "copy-progress";
+
+/// Bytes read and written per step
+pub const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Bytes of an existing `.partial` file compared against the source before resuming
+const RESUME_CHECK: u64 = 1024 * 1024;
+
+/// Minimum time between progress reports (the final report is always sent)
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Progress of one file copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopyProgress {
+    pub source: String,
+    pub destination: String,
+    pub bytes_copied: u64,
+    pub total_bytes: u64,
+    /// Bytes already in the `.partial` file when this copy started
+    pub resumed_from: u64,
+    pub bytes_per_sec: f64,
+    pub eta_secs: Option<f64>,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    pub chunk_size: usize,
+    /// Pick up an existing `.partial` file instead of starting over
+    pub resume: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: CHUNK_SIZE,
+            resume: true,
+        }
+    }
+}
+
+/// Where an in-progress copy to `destination` is written
+pub fn partial_path<P: AsRef<Path>>(destination: P) -> PathBuf {
+    let mut name = destination.as_ref().as_os_str().to_os_string();
+    name.push(".partial");
+    PathBuf::from(name)
+}
+
+/// Copy `source` to `destination` in chunks via `<destination>.partial`, which is
+/// renamed into place once complete and fsynced, with the source's modification time. A `.partial` left by an earlier
+/// attempt is resumed if its bytes match the source. `cancel` stops the copy
+/// between chunks with `ErrorKind::Interrupted`, leaving the `.partial` for a later resume.
+pub fn copy_with_progress<P, Q, F>(
+    source: P,
+    destination: Q,
+    options: &CopyOptions,
+    cancel: Option<&AtomicBool>,
+    mut on_progress: F,
+) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(&CopyProgress),
+{
+    let source = source.as_ref();
+    let destination = destination.as_ref();
+    let partial = partial_path(destination);
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut reader = fs::File::open(source)?;
+    let metadata = reader.metadata()?;
+    let total_bytes = metadata.len();
+
+    let resumed_from = if options.resume {
+        resumable_length(&mut reader, &partial, total_bytes)?
+    } else {
+        0
+    };
+
+    let mut writer = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&partial)?;
+    writer.set_len(resumed_from)?;
+    writer.seek(SeekFrom::Start(resumed_from))?;
+    reader.seek(SeekFrom::Start(resumed_from))?;
+
+    let mut progress = CopyProgress {
+        source: source.to_string_lossy().to_string(),
+        destination: destination.to_string_lossy().to_string(),
+        bytes_copied: resumed_from,
+        total_bytes,
+        resumed_from,
+        bytes_per_sec: 0.0,
+        eta_secs: None,
+        done: false,
+    };
+
+    let started = Instant::now();
+    let mut last_report: Option<Instant> = None;
+    let mut buffer = vec![0; options.chunk_size.max(1)];
+
+    loop {
+        if cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false) {
+            writer.sync_all()?;
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "Copy cancelled"));
+        }
+
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..bytes_read])?;
+        progress.bytes_copied += bytes_read as u64;
+
+        if last_report
+            .map(|at| at.elapsed() >= PROGRESS_INTERVAL)
+            .unwrap_or(true)
+        {
+            update_rate(&mut progress, started.elapsed());
+            on_progress(&progress);
+            last_report = Some(Instant::now());
+        }
+    }
+
+    // Size and mtime comparisons (sync plans, audits) must see the copy as the same version
+    if let Ok(modified) = metadata.modified() {
+        writer.set_modified(modified)?;
+    }
+    writer.sync_all()?;
+    drop(writer);
+
+    if progress.bytes_copied != total_bytes {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "Source changed during copy: expected {} bytes, copied {}",
+                total_bytes, progress.bytes_copied
+            ),
+        ));
+    }
+
+    fs::rename(&partial, destination)?;
+
+    update_rate(&mut progress, started.elapsed());
+    progress.eta_secs = Some(0.0);
+    progress.done = true;
+    on_progress(&progress);
+
+    Ok(total_bytes)
+}
+
+/// Length of `partial` that can be kept: it must be no longer than the source and
+/// its last bytes must match the source at the same offset. Otherwise start over.
+fn resumable_length(source: &mut fs::File, partial: &Path, total_bytes: u64) -> io::Result<u64> {
+    let length = match fs::metadata(partial) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(0),
+    };
+    if length == 0 || length > total_bytes {
+        return Ok(0);
+    }
+
+    let check = length.min(RESUME_CHECK);
+    let offset = length - check;
+
+    let mut expected = vec![0; check as usize];
+    source.seek(SeekFrom::Start(offset))?;
+    source.read_exact(&mut expected)?;
+
+    let mut actual = vec![0; check as usize];
+    let mut existing = fs::File::open(partial)?;
+    existing.seek(SeekFrom::Start(offset))?;
+    existing.read_exact(&mut actual)?;
+
+    Ok(if expected == actual { length } else { 0 })
+}
+
+fn update_rate(progress: &mut CopyProgress, elapsed: Duration) {
+    let copied_now = progress.bytes_copied - progress.resumed_from;
+    let secs = elapsed.as_secs_f64();
+
+    if secs > 0.0 && copied_now > 0 {
+        progress.bytes_per_sec = copied_now as f64 / secs;
+        let remaining = progress.total_bytes.saturating_sub(progress.bytes_copied);
+        progress.eta_secs = Some(remaining as f64 / progress.bytes_per_sec);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> CopyOptions {
+        CopyOptions {
+            chunk_size: 1024,
+            resume: true,
+        }
+    }
+
+    fn source_bytes() -> Vec<u8> {
+        (0..10_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_copy_reports_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("flux.ckpt");
+        let destination = dir.path().join("stash").join("flux.ckpt");
+        fs::write(&source, source_bytes()).unwrap();
+
+        let mut reports = Vec::new();
+        let copied = copy_with_progress(&source, &destination, &options(), None, |p| {
+            reports.push(p.clone())
+        })
+        .unwrap();
+
+        assert_eq!(copied, 10_000);
+        assert_eq!(fs::read(&destination).unwrap(), source_bytes());
+        assert!(!partial_path(&destination).exists());
+
+        let last = reports.last().unwrap();
+        assert!(last.done);
+        assert_eq!(last.bytes_copied, 10_000);
+        assert_eq!(last.total_bytes, 10_000);
+        assert_eq!(last.resumed_from, 0);
+        assert_eq!(last.eta_secs, Some(0.0));
+        assert_eq!(
+            fs::metadata(&destination).unwrap().modified().unwrap(),
+            fs::metadata(&source).unwrap().modified().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_resume_from_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("flux.ckpt");
+        let destination = dir.path().join("copy.ckpt");
+        let bytes = source_bytes();
+        fs::write(&source, &bytes).unwrap();
+
+        // A matching partial is continued where it stopped
+        fs::write(partial_path(&destination), &bytes[..4096]).unwrap();
+        let mut resumed = 0;
+        copy_with_progress(&source, &destination, &options(), None, |p| {
+            resumed = p.resumed_from
+        })
+        .unwrap();
+        assert_eq!(resumed, 4096);
+        assert_eq!(fs::read(&destination).unwrap(), bytes);
+
+        // A partial from some other file is discarded
+        fs::remove_file(&destination).unwrap();
+        fs::write(partial_path(&destination), vec![7u8; 4096]).unwrap();
+        copy_with_progress(&source, &destination, &options(), None, |p| {
+            resumed = p.resumed_from
+        })
+        .unwrap();
+        assert_eq!(resumed, 0);
+        assert_eq!(fs::read(&destination).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_cancel_keeps_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("flux.ckpt");
+        let destination = dir.path().join("copy.ckpt");
+        fs::write(&source, source_bytes()).unwrap();
+
+        let cancel = AtomicBool::new(true);
+        let error = copy_with_progress(&source, &destination, &options(), Some(&cancel), |_| {})
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+        assert!(!destination.exists());
+        assert!(partial_path(&destination).exists());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::atomic_write::write_atomic;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::logger::{LOG_DIR, LOG_FILE};
+use crate::settings::Settings;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// The state snapshots the frontend may write to `z_logs` (see logging.md)
+pub const SNAPSHOT_NAMES: [&str; 10] = [
+    "mac_ckpts",
+    "mac_models",
+    "mac_loras",
+    "mac_controls",
+    "stash_ckpts",
+    "stash_models",
+    "stash_loras",
+    "stash_controls",
+    "settings",
+    "deleted_ckpts",
+];
+
+/// Keys whose values never leave the machine in a support bundle
+const SECRET_KEY_PARTS: [&str; 6] = ["token", "secret", "password", "api_key", "apikey", "auth"];
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportBundle {
+    pub path: String,
+    /// Entries in the archive
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+/// Write (or overwrite) `<app_dir>/z_logs/<name>.json`. `name` must be one of
+/// `SNAPSHOT_NAMES` (a trailing `.json` is accepted) and `json` must parse; it is stored
+/// pretty-printed.
+pub fn log_json_file<P: AsRef<Path>>(app_dir: P, name: &str, json: &str) -> AppResult<PathBuf> {
+    let name = name.strip_suffix(".json").unwrap_or(name);
+    if !SNAPSHOT_NAMES.contains(&name) {
+        return Err(AppError::new(
+            ErrorCode::InvalidDestination,
+            format!("{} is not a log snapshot name", name),
+        )
+        .with("name", name)
+        .with("allowed", SNAPSHOT_NAMES));
+    }
+
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| AppError::new(ErrorCode::JsonParse, e.to_string()).with("name", name))?;
+    let content = serde_json::to_string_pretty(&value)
+        .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?;
+
+    let log_dir = app_dir.as_ref().join(LOG_DIR);
+    fs::create_dir_all(&log_dir)
+        .map_err(|e| AppError::io(ErrorCode::DirectoryCreate, &e).with("path", &log_dir))?;
+    let path = log_dir.join(format!("{}.json", name));
+    write_atomic(&path, content.as_bytes())
+        .map_err(|e| AppError::io(ErrorCode::FileWrite, &e).with("path", &path))?;
+    Ok(path)
+}
+
+/// Zip every snapshot, `logger.log` with its rotated files and a redacted copy of
+/// settings.json into `<app_dir>/z_logs/support_bundle_<time>.zip` for bug reports.
+/// Secrets (keys containing token/secret/password/api_key/auth) are blanked in every JSON
+/// file and the home directory is shown as `~`.
+pub fn create_support_bundle<P: AsRef<Path>>(app_dir: P) -> AppResult<SupportBundle> {
+    let app_dir = app_dir.as_ref();
+    let log_dir = app_dir.join(LOG_DIR);
+    fs::create_dir_all(&log_dir)
+        .map_err(|e| AppError::io(ErrorCode::DirectoryCreate, &e).with("path", &log_dir))?;
+
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+    let settings = Settings::load(app_dir)?;
+    entries.push((
+        "settings.json".into(),
+        redacted_json(&Value::Object(settings.values().clone())),
+    ));
+
+    for name in SNAPSHOT_NAMES {
+        let path = log_dir.join(format!("{}.json", name));
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let content = match serde_json::from_str::<Value>(&content) {
+            Ok(value) => redacted_json(&value),
+            Err(_) => redact_home(&content).into_bytes(),
+        };
+        entries.push((format!("{}/{}.json", LOG_DIR, name), content));
+    }
+
+    let mut logs: Vec<PathBuf> = fs::read_dir(&log_dir)
+        .map_err(|e| AppError::io(ErrorCode::DirectoryNotReadable, &e).with("path", &log_dir))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(LOG_FILE))
+        })
+        .collect();
+    logs.sort();
+    for path in logs {
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", &path))?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        entries.push((
+            format!("{}/{}", LOG_DIR, name),
+            redact_home(&content).into_bytes(),
+        ));
+    }
+
+    let created = Utc::now();
+    let manifest = serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "created": created.to_rfc3339_opts(SecondsFormat::Secs, true),
+        "os": env::consts::OS,
+        "arch": env::consts::ARCH,
+        "files": entries.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+    });
+    entries.push(("manifest.json".into(), redacted_json(&manifest)));
+
+    let path = log_dir.join(format!(
+        "support_bundle_{}.zip",
+        created.format("%Y%m%d_%H%M%S")
+    ));
+    write_zip(&path, &entries)
+        .map_err(|e| AppError::new(ErrorCode::FileWrite, e.to_string()).with("path", &path))?;
+
+    Ok(SupportBundle {
+        bytes: fs::metadata(&path).map_or(0, |m| m.len()),
+        path: path.to_string_lossy().to_string(),
+        files: entries.into_iter().map(|(name, _)| name).collect(),
+    })
+}
+
+fn write_zip(path: &Path, entries: &[(String, Vec<u8>)]) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, content) in entries {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(content)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn redacted_json(value: &Value) -> Vec<u8> {
+    let content = serde_json::to_string_pretty(&redact(value)).unwrap_or_default();
+    redact_home(&content).into_bytes()
+}
+
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let lower = key.to_lowercase();
+                    let secret = SECRET_KEY_PARTS.iter().any(|part| lower.contains(part));
+                    let value = match (secret, value) {
+                        (true, Value::String(s)) if !s.is_empty() => REDACTED.into(),
+                        _ => redact(value),
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        _ => value.clone(),
+    }
+}
+
+fn redact_home(text: &str) -> String {
+    match env::var("HOME") {
+        Ok(home) if home.len() > 1 => text.replace(&home, "~"),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_log_json_file_whitelist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = log_json_file(dir.path(), "mac_models.json", r#"[{"file":"a.ckpt"}]"#).unwrap();
+        assert!(path.ends_with("z_logs/mac_models.json"));
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"file\": \"a.ckpt\""));
+
+        let error = log_json_file(dir.path(), "../settings", "{}").unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidDestination);
+        let error = log_json_file(dir.path(), "stash_ckpts", "{not json").unwrap_err();
+        assert_eq!(error.code, ErrorCode::JsonParse);
+        assert!(!dir.path().join("z_logs/stash_ckpts.json").exists());
+    }
+
+    #[test]
+    fn test_support_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut settings = Settings::load(dir.path()).unwrap();
+        settings.set("STASH_DIR", "/Volumes/Stash".into());
+        settings.set("HF_TOKEN", "hf_abc123".into());
+        settings.save().unwrap();
+        log_json_file(dir.path(), "deleted_ckpts", r#"{"api_key":"k","files":[]}"#).unwrap();
+        fs::write(dir.path().join("z_logs/logger.log"), "{}\n").unwrap();
+        fs::write(dir.path().join("z_logs/logger.log.1"), "{}\n").unwrap();
+        fs::write(dir.path().join("z_logs/unrelated.txt"), "x").unwrap();
+
+        let bundle = create_support_bundle(dir.path()).unwrap();
+        assert_eq!(
+            bundle.files,
+            vec![
+                "settings.json",
+                "z_logs/deleted_ckpts.json",
+                "z_logs/logger.log",
+                "z_logs/logger.log.1",
+                "manifest.json",
+            ]
+        );
+
+        let mut zip = zip::ZipArchive::new(File::open(&bundle.path).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            zip.by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        let settings = read("settings.json");
+        assert!(settings.contains("/Volumes/Stash"));
+        assert!(!settings.contains("hf_abc123"));
+        assert!(read("z_logs/deleted_ckpts.json").contains(REDACTED));
+    }
+}
@@ -0,0 +1,1052 @@
+use crate::atomic_write::{backup_file, write_atomic, BACKUP_DIR};
+use crate::dt_lint::{self, Diagnostic, Problem, Severity};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::json_doc::JsonArrayDoc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Main model entry from custom.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomModel {
+    pub name: String,
+    pub file: String,
+    #[serde(default)]
+    pub autoencoder: Option<String>,
+    #[serde(default)]
+    pub clip_encoder: Option<String>,
+    #[serde(default)]
+    pub text_encoder: Option<String>,
+    #[serde(default)]
+    pub t5_encoder: Option<String>,
+    #[serde(default)]
+    pub image_encoder: Option<String>,
+    #[serde(default)]
+    pub preprocessor: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    // Other fields are preserved by `CustomJsonFile`, not here
+}
+
+impl CustomModel {
+    /// Encoder files this model uses, as (role, file)
+    pub fn encoders(&self) -> Vec<(&'static str, &str)> {
+        encoder_refs([
+            ("vae", &self.autoencoder),
+            ("clip", &self.clip_encoder),
+            ("text", &self.text_encoder),
+            ("text", &self.t5_encoder),
+            ("image_encoder", &self.image_encoder),
+            ("preprocessor", &self.preprocessor),
+        ])
+    }
+}
+
+/// LoRA weight structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoraWeight {
+    pub value: f64,
+    #[serde(default)]
+    pub lower_bound: Option<f64>,
+    #[serde(default)]
+    pub upper_bound: Option<f64>,
+}
+
+/// LoRA entry from custom_lora.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomLora {
+    pub name: String,
+    pub file: String,
+    #[serde(default)]
+    pub weight: Option<LoraWeight>,
+    #[serde(default)]
+    pub version: Option<String>,
+    // Other fields are preserved by `CustomJsonFile`, not here
+}
+
+/// ControlNet entry from custom_controlnet.json. IP adapters and PuLID reference an
+/// image encoder and a preprocessor (e.g. arcface) too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomControlNet {
+    pub name: String,
+    pub file: String,
+    #[serde(default)]
+    pub autoencoder: Option<String>,
+    #[serde(default)]
+    pub image_encoder: Option<String>,
+    #[serde(default)]
+    pub preprocessor: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    // Other fields are preserved by `CustomJsonFile`, not here
+}
+
+impl CustomControlNet {
+    /// Encoder files this ControlNet uses, as (role, file)
+    pub fn encoders(&self) -> Vec<(&'static str, &str)> {
+        encoder_refs([
+            ("vae", &self.autoencoder),
+            ("image_encoder", &self.image_encoder),
+            ("preprocessor", &self.preprocessor),
+        ])
+    }
+}
+
+fn encoder_refs<'a, const N: usize>(
+    refs: [(&'static str, &'a Option<String>); N],
+) -> Vec<(&'static str, &'a str)> {
+    refs.into_iter()
+        .filter_map(|(role, file)| file.as_deref().map(|file| (role, file)))
+        .collect()
+}
+
+/// Textual inversion (embedding) entry from custom_textual_inversions.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomTextualInversion {
+    pub name: String,
+    pub file: String,
+    #[serde(default)]
+    pub keyword: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    // Other fields are preserved by `CustomJsonFile`, not here
+}
+
+/// Upscaler entry from custom_upscaler.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomUpscaler {
+    pub name: String,
+    pub file: String,
+    #[serde(default)]
+    pub scale_factor: Option<u32>,
+    // Other fields are preserved by `CustomJsonFile`, not here
+}
+
+/// Face restorer entry from custom_face_restorer.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomFaceRestorer {
+    pub name: String,
+    pub file: String,
+    // Other fields are preserved by `CustomJsonFile`, not here
+}
+
+/// The DrawThings JSON registries, named after the frontend's model|lora|control types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomJsonKind {
+    Model,
+    Lora,
+    Control,
+    Embedding,
+    Upscaler,
+    FaceRestorer,
+}
+
+impl CustomJsonKind {
+    pub const ALL: [CustomJsonKind; 6] = [
+        CustomJsonKind::Model,
+        CustomJsonKind::Lora,
+        CustomJsonKind::Control,
+        CustomJsonKind::Embedding,
+        CustomJsonKind::Upscaler,
+        CustomJsonKind::FaceRestorer,
+    ];
+
+    /// Registry filename inside a `Models` directory
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            CustomJsonKind::Model => "custom.json",
+            CustomJsonKind::Lora => "custom_lora.json",
+            CustomJsonKind::Control => "custom_controlnet.json",
+            CustomJsonKind::Embedding => "custom_textual_inversions.json",
+            CustomJsonKind::Upscaler => "custom_upscaler.json",
+            CustomJsonKind::FaceRestorer => "custom_face_restorer.json",
+        }
+    }
+
+    /// Model type given to files listed in this registry
+    pub fn model_type(&self) -> &'static str {
+        match self {
+            CustomJsonKind::Model => "model",
+            CustomJsonKind::Lora => "lora",
+            CustomJsonKind::Control => "control",
+            CustomJsonKind::Embedding => "embedding",
+            CustomJsonKind::Upscaler => "upscaler",
+            CustomJsonKind::FaceRestorer => "face_restorer",
+        }
+    }
+}
+
+// ################################################################################
+// # Lossless registry file
+/// Editable, lossless view of one DrawThings registry file.
+/// Unknown keys, key order and number formatting are kept exactly, so a saved
+/// file differs from the original only where it was edited.
+#[derive(Debug, Clone)]
+pub struct CustomJsonFile {
+    pub kind: CustomJsonKind,
+    pub doc: JsonArrayDoc,
+}
+
+impl CustomJsonFile {
+    /// Read a registry from a `Models` directory. A missing file reads as an empty list.
+    pub fn read<P: AsRef<Path>>(models_dir: P, kind: CustomJsonKind) -> AppResult<Self> {
+        let path = models_dir.as_ref().join(kind.file_name());
+
+        if !path.exists() {
+            return Ok(Self {
+                kind,
+                doc: JsonArrayDoc::empty(),
+            });
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", &path))?;
+        Self::parse(kind, &content).map_err(|e| e.with("path", &path))
+    }
+
+    pub fn parse(kind: CustomJsonKind, content: &str) -> AppResult<Self> {
+        let doc = JsonArrayDoc::parse(content).map_err(|e| {
+            AppError::new(
+                ErrorCode::JsonParse,
+                format!("Failed to parse {}: {}", kind.file_name(), e),
+            )
+            .with("json_file", kind.file_name())
+        })?;
+        Ok(Self { kind, doc })
+    }
+
+    /// Display position of the entry for `file`
+    pub fn position_of(&self, file: &str) -> Option<usize> {
+        self.doc.position_where("file", file)
+    }
+
+    fn require(&self, file: &str) -> AppResult<usize> {
+        self.position_of(file).ok_or_else(|| {
+            AppError::new(
+                ErrorCode::ModelNotInJson,
+                format!("{} not found in {}", file, self.kind.file_name()),
+            )
+            .with("file", file)
+            .with("json_file", self.kind.file_name())
+        })
+    }
+
+    /// Errors from editing the document are structural (bad index, entry not an object)
+    fn structure_error(&self, message: String) -> AppError {
+        AppError::new(ErrorCode::InvalidJsonStructure, message)
+            .with("json_file", self.kind.file_name())
+    }
+
+    /// Change the display name of an entry
+    pub fn rename(&mut self, file: &str, name: &str) -> AppResult<()> {
+        let index = self.require(file)?;
+        self.doc
+            .set(index, &["name"], &Value::String(name.to_string()))
+            .map_err(|e| self.structure_error(e))
+    }
+
+    /// Move an entry to a new display position (0-based)
+    pub fn move_to(&mut self, file: &str, position: usize) -> AppResult<()> {
+        let index = self.require(file)?;
+        self.doc
+            .move_element(index, position)
+            .map_err(|e| self.structure_error(e))
+    }
+
+    /// Set the default weight of a LoRA entry
+    pub fn set_lora_weight(&mut self, file: &str, value: f64) -> AppResult<()> {
+        if self.kind != CustomJsonKind::Lora {
+            return Err(
+                self.structure_error(format!("{} has no LoRA weights", self.kind.file_name()))
+            );
+        }
+        let number = serde_json::Number::from_f64(value)
+            .ok_or_else(|| self.structure_error(format!("Invalid LoRA weight: {}", value)))?;
+        let index = self.require(file)?;
+        self.doc
+            .set(index, &["weight", "value"], &Value::Number(number))
+            .map_err(|e| self.structure_error(e))
+    }
+
+    /// Remove every entry for `file`. Returns how many were removed.
+    pub fn remove_entry(&mut self, file: &str) -> AppResult<usize> {
+        self.require(file)?;
+        let mut removed = 0;
+        while let Some(index) = self.position_of(file) {
+            self.doc
+                .remove(index)
+                .map_err(|e| self.structure_error(e))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Point every reference to `from` - an entry's `file` or an encoder key - at `to`.
+    /// Fails (6) when both have an entry, rather than listing `to` twice; remove one first.
+    /// Returns the number of changes.
+    pub fn repoint(&mut self, from: &str, to: &str) -> AppResult<usize> {
+        if self.position_of(from).is_some() && self.position_of(to).is_some() {
+            return Err(AppError::new(
+                ErrorCode::AlreadyExists,
+                format!("{} already lists {}", self.kind.file_name(), to),
+            )
+            .with("file", to)
+            .with("json_file", self.kind.file_name()));
+        }
+
+        let mut changes = 0;
+        let mut index = 0;
+
+        while let Some(entry) = self.doc.get(index) {
+            let refers = |key: &str| entry.get(key).and_then(|v| v.as_str()) == Some(from);
+
+            if refers("file") {
+                self.doc
+                    .set(index, &["file"], &Value::from(to))
+                    .map_err(|e| self.structure_error(e))?;
+                changes += 1;
+            }
+            for key in dt_lint::ENCODER_KEYS {
+                if refers(key) {
+                    self.doc
+                        .set(index, &[key], &Value::from(to))
+                        .map_err(|e| self.structure_error(e))?;
+                    changes += 1;
+                }
+            }
+            index += 1;
+        }
+
+        Ok(changes)
+    }
+
+    /// Typed view of every entry; fails on the first entry that doesn't fit `T`
+    pub fn entries<T: DeserializeOwned>(&self) -> AppResult<Vec<T>> {
+        (0..self.doc.len())
+            .map(|i| {
+                self.doc.entry(i).map_err(|e| {
+                    AppError::new(
+                        ErrorCode::JsonParse,
+                        format!("Failed to parse {}: {}", self.kind.file_name(), e),
+                    )
+                    .with("json_file", self.kind.file_name())
+                    .with("index", i)
+                })
+            })
+            .collect()
+    }
+
+    /// Replace the whole list. Entries that are unchanged keep their original text.
+    pub fn replace_entries(&mut self, entries: &[Value]) -> AppResult<()> {
+        for (index, entry) in entries.iter().enumerate() {
+            if !entry.get("file").map(|f| f.is_string()).unwrap_or(false) {
+                return Err(self
+                    .structure_error(format!(
+                        "Invalid JSON structure: entry {} has no \"file\"",
+                        index
+                    ))
+                    .with("index", index));
+            }
+        }
+        self.doc.assign(entries);
+        Ok(())
+    }
+
+    /// Replace the entries for the same `file`s as `updates` and append the rest.
+    /// Returns whether anything changed, so callers can skip an unneeded write.
+    pub fn upsert_entries(&mut self, updates: &[Value]) -> AppResult<bool> {
+        let current = self.entries::<Value>()?;
+        let mut entries = current.clone();
+
+        for entry in updates {
+            let position = entry
+                .get("file")
+                .and_then(Value::as_str)
+                .and_then(|file| self.position_of(file));
+            match position {
+                Some(index) => entries[index] = entry.clone(),
+                None => entries.push(entry.clone()),
+            }
+        }
+
+        if entries == current {
+            return Ok(false);
+        }
+        self.replace_entries(&entries)?;
+        Ok(true)
+    }
+
+    /// Write to `<models_dir>/<file_name>` atomically, first backing up the
+    /// current version under `<app_dir>/backups`. Returns the backup path, if any.
+    pub fn write<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        models_dir: P,
+        app_dir: Q,
+    ) -> AppResult<Option<PathBuf>> {
+        let path = models_dir.as_ref().join(self.kind.file_name());
+
+        let backup = backup_file(&path, &app_dir).map_err(|e| {
+            AppError::io(ErrorCode::FileWrite, &e)
+                .with("path", &path)
+                .with("backup_dir", app_dir.as_ref().join(BACKUP_DIR))
+        })?;
+
+        write_atomic(&path, self.to_json_string().as_bytes())
+            .map_err(|e| AppError::io(ErrorCode::JsonWrite, &e).with("path", &path))?;
+
+        Ok(backup)
+    }
+
+    /// The file contents as they would be written to disk
+    pub fn to_json_string(&self) -> String {
+        self.doc.to_string()
+    }
+}
+
+/// Parsed DrawThings configuration data
+#[derive(Debug, Clone)]
+pub struct DrawThingsConfig {
+    pub models: Vec<CustomModel>,
+    pub loras: Vec<CustomLora>,
+    pub controlnets: Vec<CustomControlNet>,
+    pub embeddings: Vec<CustomTextualInversion>,
+    pub upscalers: Vec<CustomUpscaler>,
+    pub face_restorers: Vec<CustomFaceRestorer>,
+
+    // Lookup maps for efficient queries
+    pub file_to_model_name: HashMap<String, String>,
+    pub file_to_model_type: HashMap<String, String>,
+    pub file_to_display_order: HashMap<String, i32>,
+    pub file_to_lora_strength: HashMap<String, i32>, // value × 10
+
+    // Relationship tracking
+    pub main_model_to_encoders: HashMap<String, Vec<String>>,
+}
+
+impl DrawThingsConfig {
+    /// Parse all DrawThings JSON config files from the DT_BASE_DIR/Models directory.
+    /// Invalid entries are skipped; use `parse_with_report` to see why.
+    pub fn parse_from_directory<P: AsRef<Path>>(models_dir: P) -> AppResult<Self> {
+        Ok(Self::parse_with_report(models_dir).0)
+    }
+
+    /// Parse for operations that move or delete files. A file missing from the config
+    /// looks unused, so any Error-severity diagnostic (an unreadable or invalid registry,
+    /// a skipped entry) fails with 41 and the diagnostics in `details`.
+    pub fn parse_checked<P: AsRef<Path>>(models_dir: P) -> AppResult<Self> {
+        let models_dir = models_dir.as_ref();
+        let (config, diagnostics) = Self::parse_with_report(models_dir);
+        let errors: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .filter(|d| d.severity == Severity::Error)
+            .collect();
+        if errors.is_empty() {
+            return Ok(config);
+        }
+        Err(AppError::new(
+            ErrorCode::InvalidJsonStructure,
+            format!(
+                "{} error(s) in the Draw Things JSON in {}; fix them before moving or deleting models",
+                errors.len(),
+                models_dir.display()
+            ),
+        )
+        .with("models_dir", models_dir)
+        .with("diagnostics", errors))
+    }
+
+    /// Parse all DrawThings JSON config files, validating every entry.
+    /// Returns the config built from the valid entries plus one diagnostic per problem.
+    pub fn parse_with_report<P: AsRef<Path>>(models_dir: P) -> (Self, Vec<Diagnostic>) {
+        let models_dir = models_dir.as_ref();
+        let mut diagnostics = Vec::new();
+
+        let models: Vec<CustomModel> =
+            read_valid_entries(models_dir, CustomJsonKind::Model, &mut diagnostics);
+        let loras: Vec<CustomLora> =
+            read_valid_entries(models_dir, CustomJsonKind::Lora, &mut diagnostics);
+        let controlnets: Vec<CustomControlNet> =
+            read_valid_entries(models_dir, CustomJsonKind::Control, &mut diagnostics);
+        let embeddings: Vec<CustomTextualInversion> =
+            read_valid_entries(models_dir, CustomJsonKind::Embedding, &mut diagnostics);
+        let upscalers: Vec<CustomUpscaler> =
+            read_valid_entries(models_dir, CustomJsonKind::Upscaler, &mut diagnostics);
+        let face_restorers: Vec<CustomFaceRestorer> =
+            read_valid_entries(models_dir, CustomJsonKind::FaceRestorer, &mut diagnostics);
+
+        // Build lookup maps
+        let mut file_to_model_name = HashMap::new();
+        let mut file_to_model_type = HashMap::new();
+        let mut file_to_display_order = HashMap::new();
+        let mut file_to_lora_strength = HashMap::new();
+        let mut main_model_to_encoders = HashMap::new();
+
+        // Process main models
+        for (index, model) in models.iter().enumerate() {
+            file_to_model_name.insert(model.file.clone(), model.name.clone());
+            file_to_model_type.insert(model.file.clone(), "model".to_string());
+            file_to_display_order.insert(model.file.clone(), index as i32);
+
+            track_encoders(
+                &model.file,
+                model.encoders(),
+                &mut file_to_model_type,
+                &mut main_model_to_encoders,
+            );
+        }
+
+        // Process LoRAs
+        for (index, lora) in loras.iter().enumerate() {
+            file_to_model_name.insert(lora.file.clone(), lora.name.clone());
+            file_to_model_type.insert(lora.file.clone(), "lora".to_string());
+            file_to_display_order.insert(lora.file.clone(), index as i32);
+
+            // Convert weight to integer (× 10 for storage)
+            if let Some(ref weight) = lora.weight {
+                let strength = (weight.value * 10.0).round() as i32;
+                file_to_lora_strength.insert(lora.file.clone(), strength);
+            }
+        }
+
+        // Process ControlNets
+        for (index, controlnet) in controlnets.iter().enumerate() {
+            file_to_model_name.insert(controlnet.file.clone(), controlnet.name.clone());
+            file_to_model_type.insert(controlnet.file.clone(), "control".to_string());
+            file_to_display_order.insert(controlnet.file.clone(), index as i32);
+        }
+        for controlnet in &controlnets {
+            track_encoders(
+                &controlnet.file,
+                controlnet.encoders(),
+                &mut file_to_model_type,
+                &mut main_model_to_encoders,
+            );
+        }
+
+        // Process embeddings, upscalers and face restorers - name, type and order only
+        let others = [
+            (
+                CustomJsonKind::Embedding,
+                embeddings
+                    .iter()
+                    .map(|e| (&e.file, &e.name))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                CustomJsonKind::Upscaler,
+                upscalers.iter().map(|u| (&u.file, &u.name)).collect(),
+            ),
+            (
+                CustomJsonKind::FaceRestorer,
+                face_restorers.iter().map(|f| (&f.file, &f.name)).collect(),
+            ),
+        ];
+        for (kind, entries) in others {
+            for (index, (file, name)) in entries.into_iter().enumerate() {
+                file_to_model_name.insert(file.clone(), name.clone());
+                file_to_model_type.insert(file.clone(), kind.model_type().to_string());
+                file_to_display_order.insert(file.clone(), index as i32);
+            }
+        }
+
+        let config = DrawThingsConfig {
+            models,
+            loras,
+            controlnets,
+            embeddings,
+            upscalers,
+            face_restorers,
+            file_to_model_name,
+            file_to_model_type,
+            file_to_display_order,
+            file_to_lora_strength,
+            main_model_to_encoders,
+        };
+
+        (config, diagnostics)
+    }
+
+    /// Get display name for a file, or None if not in JSON
+    pub fn get_display_name(&self, filename: &str) -> Option<String> {
+        self.file_to_model_name.get(filename).cloned()
+    }
+
+    /// Get model type for a file, or None if not in JSON
+    pub fn get_model_type(&self, filename: &str) -> Option<String> {
+        self.file_to_model_type.get(filename).cloned()
+    }
+
+    /// Model type for every file in `filenames`; files not listed in any registry
+    /// are "unknown" rather than guessed from their name
+    pub fn get_model_types(&self, filenames: &[String]) -> HashMap<String, String> {
+        filenames
+            .iter()
+            .map(|f| {
+                let model_type = self
+                    .get_model_type(f)
+                    .unwrap_or_else(|| "unknown".to_string());
+                (f.clone(), model_type)
+            })
+            .collect()
+    }
+
+    /// Get display order for a file (Mac HD only), or None if not in JSON
+    pub fn get_display_order(&self, filename: &str) -> Option<i32> {
+        self.file_to_display_order.get(filename).cloned()
+    }
+
+    /// Get LoRA strength for a file, or None if not a LoRA or no weight specified
+    pub fn get_lora_strength(&self, filename: &str) -> Option<i32> {
+        self.file_to_lora_strength.get(filename).cloned()
+    }
+
+    /// Get encoder files used by a main model
+    pub fn get_model_encoders(&self, filename: &str) -> Option<Vec<String>> {
+        self.main_model_to_encoders.get(filename).cloned()
+    }
+
+    /// Check if a file is an entry in one of the registries (not just an encoder reference)
+    pub fn is_listed(&self, filename: &str) -> bool {
+        self.file_to_display_order.contains_key(filename)
+    }
+
+    /// Check if a file is referenced in any JSON config
+    pub fn is_file_in_config(&self, filename: &str) -> bool {
+        self.file_to_model_type.contains_key(filename)
+    }
+}
+
+/// Record the encoders of one model or ControlNet: their type (unless the file is
+/// already known) and the parent -> encoders link
+fn track_encoders(
+    parent: &str,
+    encoders: Vec<(&'static str, &str)>,
+    file_to_model_type: &mut HashMap<String, String>,
+    main_model_to_encoders: &mut HashMap<String, Vec<String>>,
+) {
+    if encoders.is_empty() {
+        return;
+    }
+    for (role, file) in &encoders {
+        file_to_model_type
+            .entry(file.to_string())
+            .or_insert(role.to_string());
+    }
+    main_model_to_encoders.insert(
+        parent.to_string(),
+        encoders
+            .into_iter()
+            .map(|(_, file)| file.to_string())
+            .collect(),
+    );
+}
+
+/// Read one registry, keeping only the entries that pass validation.
+/// A missing registry is empty; an unreadable or unparseable one is reported and treated as empty.
+fn read_valid_entries<T: DeserializeOwned>(
+    models_dir: &Path,
+    kind: CustomJsonKind,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<T> {
+    let path = models_dir.join(kind.file_name());
+    if !path.exists() {
+        return Vec::new();
+    }
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            diagnostics.push(dt_lint::file_diagnostic(
+                kind,
+                Problem::UnreadableFile,
+                e.to_string(),
+            ));
+            return Vec::new();
+        }
+    };
+
+    let json = match CustomJsonFile::parse(kind, &content) {
+        Ok(json) => json,
+        Err(e) => {
+            diagnostics.push(dt_lint::file_diagnostic(
+                kind,
+                Problem::InvalidJson,
+                e.message,
+            ));
+            return Vec::new();
+        }
+    };
+
+    let (entries, found) = dt_lint::lint_entries(&json, models_dir);
+    diagnostics.extend(found);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pretty-printed, with keys and number formats we don't model
+    const LORAS: &str = r#"[
+  {
+    "file": "detail_tweaker_lora_f16.ckpt",
+    "name": "Detail Tweaker",
+    "prefix": "",
+    "version": "v1",
+    "weight": {
+      "value": 0.60,
+      "lower_bound": -1.5,
+      "upper_bound": 2.5e0
+    },
+    "is_loha": false
+  },
+  {
+    "file": "ghibli_style_lora_f16.ckpt",
+    "name": "Ghibli",
+    "version": "sdxl_base_v0.9",
+    "modifier": "none"
+  }
+]
+"#;
+
+    // Compact, as written by DrawThings itself
+    const MODELS: &str = r#"[{"version":"flux1","name":"FLUX.1 [dev]","file":"flux_1_dev_q8p.ckpt","autoencoder":"flux_1_vae_f16.ckpt","text_encoder":"t5_xxl_encoder_q6p.ckpt","clip_encoder":"clip_vit_l14_f16.ckpt","high_precision_autoencoder":true,"objective":{"u":{"condition_scale":1000}}},{"version":"v1","name":"SD 1.5","file":"sd_v1.5_f16.ckpt","upcast_attention":false}]"#;
+
+    /// Lines that differ between two texts of equal line count
+    fn changed_lines(a: &str, b: &str) -> Vec<(String, String)> {
+        assert_eq!(a.lines().count(), b.lines().count());
+        a.lines()
+            .zip(b.lines())
+            .filter(|(x, y)| x != y)
+            .map(|(x, y)| (x.to_string(), y.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_is_byte_identical() {
+        for (kind, text) in [
+            (CustomJsonKind::Lora, LORAS),
+            (CustomJsonKind::Model, MODELS),
+        ] {
+            let file = CustomJsonFile::parse(kind, text).unwrap();
+            assert_eq!(file.to_json_string(), text);
+        }
+        let empty = CustomJsonFile::parse(CustomJsonKind::Control, "[ ]\n").unwrap();
+        assert_eq!(empty.to_json_string(), "[ ]\n");
+    }
+
+    #[test]
+    fn test_rename_only_changes_name() {
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Lora, LORAS).unwrap();
+        file.rename("ghibli_style_lora_f16.ckpt", "Ghibli \"v2\"")
+            .unwrap();
+
+        let changed = changed_lines(LORAS, &file.to_json_string());
+        assert_eq!(
+            changed,
+            vec![(
+                r#"    "name": "Ghibli","#.to_string(),
+                r#"    "name": "Ghibli \"v2\"","#.to_string()
+            )]
+        );
+
+        let mut models = CustomJsonFile::parse(CustomJsonKind::Model, MODELS).unwrap();
+        models.rename("sd_v1.5_f16.ckpt", "SD v1.5").unwrap();
+        assert_eq!(
+            models.to_json_string(),
+            MODELS.replace(r#""name":"SD 1.5""#, r#""name":"SD v1.5""#)
+        );
+    }
+
+    #[test]
+    fn test_repoint_files_and_encoders() {
+        let mut models = CustomJsonFile::parse(CustomJsonKind::Model, MODELS).unwrap();
+        assert_eq!(
+            models
+                .repoint("flux_1_vae_f16.ckpt", "flux_vae_copy_f16.ckpt")
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            models.to_json_string(),
+            MODELS.replace("flux_1_vae_f16.ckpt", "flux_vae_copy_f16.ckpt")
+        );
+
+        // Both names listed: the caller has to remove one entry first
+        let error = models
+            .repoint("sd_v1.5_f16.ckpt", "flux_1_dev_q8p.ckpt")
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::AlreadyExists);
+        assert_eq!(models.doc.len(), 2);
+        assert_eq!(models.remove_entry("sd_v1.5_f16.ckpt").unwrap(), 1);
+        assert_eq!(models.doc.len(), 1);
+        assert!(models.remove_entry("sd_v1.5_f16.ckpt").is_err());
+        assert_eq!(models.repoint("not_listed.ckpt", "x.ckpt").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_lora_weight_keeps_neighbouring_numbers() {
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Lora, LORAS).unwrap();
+        file.set_lora_weight("detail_tweaker_lora_f16.ckpt", 0.75)
+            .unwrap();
+
+        let changed = changed_lines(LORAS, &file.to_json_string());
+        assert_eq!(
+            changed,
+            vec![(
+                r#"      "value": 0.60,"#.to_string(),
+                r#"      "value": 0.75,"#.to_string()
+            )]
+        );
+
+        // No weight object yet: one is added in the entry's own style
+        file.set_lora_weight("ghibli_style_lora_f16.ckpt", 1.0)
+            .unwrap();
+        let out = file.to_json_string();
+        assert!(out.contains(
+            "    \"modifier\": \"none\",\n    \"weight\": {\n      \"value\": 1.0\n    }\n  }"
+        ));
+        let loras: Vec<CustomLora> = file.entries().unwrap();
+        assert_eq!(loras[1].weight.as_ref().unwrap().value, 1.0);
+
+        let mut models = CustomJsonFile::parse(CustomJsonKind::Model, MODELS).unwrap();
+        assert!(models.set_lora_weight("sd_v1.5_f16.ckpt", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_reorder_moves_whole_entries() {
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Model, MODELS).unwrap();
+        file.move_to("sd_v1.5_f16.ckpt", 0).unwrap();
+
+        let out = file.to_json_string();
+        assert!(out.starts_with(r#"[{"version":"v1","name":"SD 1.5""#));
+        assert!(out.contains(r#""objective":{"u":{"condition_scale":1000}}}]"#));
+        assert_eq!(file.position_of("flux_1_dev_q8p.ckpt"), Some(1));
+
+        // Moving back restores the original bytes
+        file.move_to("sd_v1.5_f16.ckpt", 1).unwrap();
+        assert_eq!(file.to_json_string(), MODELS);
+        assert!(file.move_to("missing.ckpt", 0).is_err());
+        assert!(file.move_to("sd_v1.5_f16.ckpt", 5).is_err());
+    }
+
+    #[test]
+    fn test_push_and_remove_follow_existing_style() {
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Lora, LORAS).unwrap();
+        let removed = file.doc.remove(0).unwrap();
+        assert_eq!(removed["file"], "detail_tweaker_lora_f16.ckpt");
+        file.doc.push(&removed);
+        file.doc.move_element(1, 0).unwrap();
+
+        // A re-rendered entry keeps its key order and layout; only number
+        // formats are normalised
+        assert_eq!(
+            file.to_json_string(),
+            LORAS.replace("0.60", "0.6").replace("2.5e0", "2.5")
+        );
+
+        let mut compact = CustomJsonFile::parse(CustomJsonKind::Model, MODELS).unwrap();
+        compact.doc.remove(1).unwrap();
+        compact
+            .doc
+            .push(&serde_json::json!({"file": "sd_v1.5_f16.ckpt", "name": "SD 1.5"}));
+        assert!(compact
+            .to_json_string()
+            .ends_with(r#"}}},{"file":"sd_v1.5_f16.ckpt","name":"SD 1.5"}]"#));
+    }
+
+    #[test]
+    fn test_remove_key() {
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Lora, LORAS).unwrap();
+        assert!(file.doc.remove_key(0, &["prefix"]).unwrap());
+        assert!(file.doc.remove_key(1, &["modifier"]).unwrap());
+        assert!(!file.doc.remove_key(1, &["modifier"]).unwrap());
+        assert!(file.doc.remove_key(0, &["weight", "upper_bound"]).unwrap());
+
+        let out = file.to_json_string();
+        assert!(!out.contains("prefix"));
+        assert!(!out.contains("modifier"));
+        assert!(out.contains("\"lower_bound\": -1.5\n    },"));
+        assert!(out.contains("\"version\": \"sdxl_base_v0.9\"\n  }"));
+    }
+
+    #[test]
+    fn test_replace_entries_keeps_unchanged_text() {
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Lora, LORAS).unwrap();
+        let mut entries: Vec<Value> = serde_json::from_str(LORAS).unwrap();
+        entries.swap(0, 1);
+        entries[0]["name"] = Value::String("Ghibli Style".to_string());
+        file.replace_entries(&entries).unwrap();
+
+        let out = file.to_json_string();
+        // The untouched entry is moved verbatim, number formats and all
+        assert!(out.contains("\"value\": 0.60,"));
+        assert!(out.contains("\"upper_bound\": 2.5e0"));
+        let reparsed: Vec<Value> = serde_json::from_str(&out).unwrap();
+        assert_eq!(reparsed, entries);
+
+        // Editing an entry in place touches only the edited lines, keeping key order,
+        // number formats and the position of a removed key's neighbours
+        let mut file = CustomJsonFile::parse(CustomJsonKind::Lora, LORAS).unwrap();
+        let mut entries: Vec<Value> = serde_json::from_str(LORAS).unwrap();
+        entries[0]["name"] = Value::String("Detail Tweaker XL".to_string());
+        entries[0]["weight"]["value"] = serde_json::json!(0.8);
+        file.replace_entries(&entries).unwrap();
+        assert_eq!(
+            changed_lines(LORAS, &file.to_json_string()),
+            vec![
+                (
+                    r#"    "name": "Detail Tweaker","#.to_string(),
+                    r#"    "name": "Detail Tweaker XL","#.to_string()
+                ),
+                (
+                    r#"      "value": 0.60,"#.to_string(),
+                    r#"      "value": 0.8,"#.to_string()
+                ),
+            ]
+        );
+        entries[1].as_object_mut().unwrap().remove("version");
+        entries[1]["prefix"] = Value::String("ghibli style".to_string());
+        file.replace_entries(&entries).unwrap();
+        assert!(file.to_json_string().ends_with(
+            "    \"name\": \"Ghibli\",\n    \"modifier\": \"none\",\n    \"prefix\": \"ghibli style\"\n  }\n]\n"
+        ));
+
+        file.replace_entries(&[]).unwrap();
+        assert_eq!(file.to_json_string(), "[]\n");
+
+        assert!(file
+            .replace_entries(&[serde_json::json!({"name": "No file"})])
+            .is_err());
+    }
+
+    #[test]
+    fn test_write_backs_up_previous_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let models_dir = dir.path().join("Models");
+        let app_dir = dir.path().join("app");
+        fs::create_dir_all(&models_dir).unwrap();
+
+        // First write: nothing to back up
+        let mut file = CustomJsonFile::read(&models_dir, CustomJsonKind::Model).unwrap();
+        file.replace_entries(&serde_json::from_str::<Vec<Value>>(MODELS).unwrap())
+            .unwrap();
+        assert_eq!(file.write(&models_dir, &app_dir).unwrap(), None);
+
+        let mut file = CustomJsonFile::read(&models_dir, CustomJsonKind::Model).unwrap();
+        let before = file.to_json_string();
+        file.rename("sd_v1.5_f16.ckpt", "SD v1.5").unwrap();
+        let backup = file.write(&models_dir, &app_dir).unwrap().unwrap();
+
+        assert_eq!(fs::read_to_string(backup).unwrap(), before);
+        assert_eq!(
+            fs::read_to_string(models_dir.join("custom.json")).unwrap(),
+            file.to_json_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_config_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("custom.json"), MODELS).unwrap();
+        fs::write(dir.path().join("custom_lora.json"), LORAS).unwrap();
+
+        let (config, diagnostics) = DrawThingsConfig::parse_with_report(dir.path());
+        assert_eq!(config.models.len(), 2);
+        assert_eq!(config.loras.len(), 2);
+        assert!(config.controlnets.is_empty());
+        assert_eq!(
+            config.get_model_type("t5_xxl_encoder_q6p.ckpt"),
+            Some("text".to_string())
+        );
+        assert_eq!(
+            config.get_lora_strength("detail_tweaker_lora_f16.ckpt"),
+            Some(6)
+        );
+        assert_eq!(config.get_display_order("sd_v1.5_f16.ckpt"), Some(1));
+
+        // Encoders referenced by FLUX aren't on disk in this test
+        assert!(diagnostics
+            .iter()
+            .all(|d| d.problem == Problem::MissingEncoder && d.index == Some(0)));
+        assert_eq!(diagnostics.len(), 3);
+    }
+
+    #[test]
+    fn test_bad_entries_dont_fail_the_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("custom.json"),
+            r#"[{"name":"Broken"},{"name":"SD 1.5","file":"sd_v1.5_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("custom_lora.json"), "[{").unwrap();
+
+        let (config, diagnostics) = DrawThingsConfig::parse_with_report(dir.path());
+        assert_eq!(config.models.len(), 1);
+        assert_eq!(config.get_display_order("sd_v1.5_f16.ckpt"), Some(0));
+        assert!(config.loras.is_empty());
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.json_file.as_str(), d.index, d.problem))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("custom.json", Some(0), Problem::MissingKey),
+                ("custom_lora.json", None, Problem::InvalidJson),
+            ]
+        );
+        assert!(DrawThingsConfig::parse_from_directory(dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_parse_other_registries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("custom_textual_inversions.json"),
+            r#"[{"name":"EasyNegative","file":"easynegative_ti_f16.ckpt","keyword":"easynegative","version":"v1","negative":true}]"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("custom_upscaler.json"),
+            r#"[{"name":"4x UltraSharp","file":"4x_ultrasharp_f16.ckpt","scale_factor":4}]"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("custom_face_restorer.json"),
+            r#"[{"name":"RestoreFormer","file":"restoreformer_v1.0_f16.ckpt"}]"#,
+        )
+        .unwrap();
+
+        let config = DrawThingsConfig::parse_from_directory(dir.path()).unwrap();
+        assert_eq!(
+            config.embeddings[0].keyword.as_deref(),
+            Some("easynegative")
+        );
+        assert_eq!(config.upscalers[0].scale_factor, Some(4));
+        assert_eq!(config.face_restorers.len(), 1);
+
+        let files = vec![
+            "easynegative_ti_f16.ckpt".to_string(),
+            "4x_ultrasharp_f16.ckpt".to_string(),
+            "restoreformer_v1.0_f16.ckpt".to_string(),
+            "realesrgan_x2plus_f16.ckpt".to_string(),
+        ];
+        let types = config.get_model_types(&files);
+        assert_eq!(types["easynegative_ti_f16.ckpt"], "embedding");
+        assert_eq!(types["4x_ultrasharp_f16.ckpt"], "upscaler");
+        assert_eq!(types["restoreformer_v1.0_f16.ckpt"], "face_restorer");
+        // Not listed anywhere: no guessing from "esrgan" in the name
+        assert_eq!(types["realesrgan_x2plus_f16.ckpt"], "unknown");
+        assert_eq!(
+            config.get_display_name("4x_ultrasharp_f16.ckpt"),
+            Some("4x UltraSharp".to_string())
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use crate::dt_json::{CustomJsonFile, CustomJsonKind};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// `version` strings DrawThings is known to write
+pub const KNOWN_VERSIONS: &[&str] = &[
+    "v1",
+    "v2",
+    "kandinsky2.1",
+    "sdxl_base_v0.9",
+    "sdxl_refiner_v0.9",
+    "ssd_1b",
+    "svd_i2v",
+    "wurstchen_v3.0_stage_c",
+    "wurstchen_v3.0_stage_b",
+    "sd3",
+    "sd3_large",
+    "pixart",
+    "auraflow",
+    "flux1",
+    "hunyuan_video",
+    "wan_v2.1_1.3b",
+    "wan_v2.1_14b",
+    "hidream_i1",
+    "qwen_image",
+];
+
+/// Weight range the DrawThings LoRA slider allows when an entry has no bounds of its own
+pub const LORA_WEIGHT_RANGE: (f64, f64) = (-1.5, 2.5);
+
+/// Keys that name encoder files in custom.json and custom_controlnet.json
+/// (same list as `ckpt_keys_types` in the frontend settings, plus `t5_encoder`)
+pub const ENCODER_KEYS: &[&str] = &[
+    "autoencoder",
+    "clip_encoder",
+    "text_encoder",
+    "t5_encoder",
+    "image_encoder",
+    "preprocessor",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The entry is skipped
+    Error,
+    /// The entry is kept but probably won't work as expected
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    UnreadableFile,
+    InvalidJson,
+    NotAnObject,
+    MissingKey,
+    InvalidValue,
+    DuplicateFile,
+    MissingEncoder,
+    LoraBounds,
+    UnknownVersion,
+}
+
+/// One problem found in a DrawThings registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Registry filename, e.g. `custom_lora.json`
+    pub json_file: String,
+    /// Position of the entry in the array, None for whole-file problems
+    pub index: Option<usize>,
+    /// The entry's `file` value, when it has one
+    pub file: Option<String>,
+    pub severity: Severity,
+    pub problem: Problem,
+    pub message: String,
+}
+
+/// Validate every entry of a registry. Returns the entries that can be used,
+/// typed as `T`, plus one diagnostic per problem found.
+/// Encoder references are checked against the files in `models_dir`.
+pub fn lint_entries<T: DeserializeOwned>(
+    json: &CustomJsonFile,
+    models_dir: &Path,
+) -> (Vec<T>, Vec<Diagnostic>) {
+    let kind = json.kind;
+    let mut valid = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut seen_files = HashSet::new();
+
+    for index in 0..json.doc.len() {
+        let value = json.doc.get(index).unwrap_or(Value::Null);
+        let file = value
+            .get("file")
+            .and_then(|f| f.as_str())
+            .map(|f| f.to_string());
+
+        let mut report = |severity, problem, message: String| {
+            diagnostics.push(Diagnostic {
+                json_file: kind.file_name().to_string(),
+                index: Some(index),
+                file: file.clone(),
+                severity,
+                problem,
+                message,
+            });
+        };
+
+        if !value.is_object() {
+            report(
+                Severity::Error,
+                Problem::NotAnObject,
+                "Entry is not an object".to_string(),
+            );
+            continue;
+        }
+
+        let missing: Vec<&str> = ["name", "file"]
+            .into_iter()
+            .filter(|key| !value.get(*key).map(|v| v.is_string()).unwrap_or(false))
+            .collect();
+        if !missing.is_empty() {
+            report(
+                Severity::Error,
+                Problem::MissingKey,
+                format!("Missing required key(s): {}", missing.join(", ")),
+            );
+            continue;
+        }
+
+        // Checked above
+        let file_name = file.clone().unwrap_or_default();
+
+        if !seen_files.insert(file_name.clone()) {
+            report(
+                Severity::Error,
+                Problem::DuplicateFile,
+                format!(
+                    "{} is listed more than once; only the first entry is used",
+                    file_name
+                ),
+            );
+            continue;
+        }
+
+        let typed: T = match serde_json::from_value(value.clone()) {
+            Ok(typed) => typed,
+            Err(e) => {
+                report(Severity::Error, Problem::InvalidValue, e.to_string());
+                continue;
+            }
+        };
+
+        if let Some(version) = value.get("version").and_then(|v| v.as_str()) {
+            if !KNOWN_VERSIONS.contains(&version) {
+                report(
+                    Severity::Warning,
+                    Problem::UnknownVersion,
+                    format!("Unknown version \"{}\"", version),
+                );
+            }
+        }
+
+        if matches!(kind, CustomJsonKind::Model | CustomJsonKind::Control) {
+            for key in ENCODER_KEYS {
+                if let Some(encoder) = value.get(*key).and_then(|v| v.as_str()) {
+                    if !models_dir.join(encoder).exists() {
+                        report(
+                            Severity::Warning,
+                            Problem::MissingEncoder,
+                            format!("{} {} not found in Models", key, encoder),
+                        );
+                    }
+                }
+            }
+        }
+
+        if kind == CustomJsonKind::Lora {
+            if let Some(message) = check_lora_weight(value.get("weight")) {
+                report(Severity::Warning, Problem::LoraBounds, message);
+            }
+        }
+
+        valid.push(typed);
+    }
+
+    (valid, diagnostics)
+}
+
+/// Check a LoRA `weight` object: bounds must be ordered and contain the default value
+fn check_lora_weight(weight: Option<&Value>) -> Option<String> {
+    let weight = weight?;
+    let value = weight.get("value").and_then(|v| v.as_f64())?;
+    let lower = weight
+        .get("lower_bound")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(LORA_WEIGHT_RANGE.0);
+    let upper = weight
+        .get("upper_bound")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(LORA_WEIGHT_RANGE.1);
+
+    if lower > upper {
+        return Some(format!(
+            "lower_bound {} is greater than upper_bound {}",
+            lower, upper
+        ));
+    }
+    if value < lower || value > upper {
+        return Some(format!(
+            "Weight {} is outside the range {} to {}",
+            value, lower, upper
+        ));
+    }
+    None
+}
+
+/// Diagnostic for a registry that couldn't be read or parsed at all
+pub fn file_diagnostic(kind: CustomJsonKind, problem: Problem, message: String) -> Diagnostic {
+    Diagnostic {
+        json_file: kind.file_name().to_string(),
+        index: None,
+        file: None,
+        severity: Severity::Error,
+        problem,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dt_json::{CustomLora, CustomModel};
+    use std::fs;
+
+    fn problems(diagnostics: &[Diagnostic]) -> Vec<(Option<usize>, Problem)> {
+        diagnostics.iter().map(|d| (d.index, d.problem)).collect()
+    }
+
+    #[test]
+    fn test_lint_models() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("flux_1_vae_f16.ckpt"), b"").unwrap();
+
+        let json = CustomJsonFile::parse(
+            CustomJsonKind::Model,
+            r#"[
+                {"name": "Flux", "file": "flux.ckpt", "version": "flux1",
+                 "autoencoder": "flux_1_vae_f16.ckpt", "text_encoder": "t5_missing.ckpt"},
+                {"file": "no_name.ckpt"},
+                "not an object",
+                {"name": "Flux again", "file": "flux.ckpt"},
+                {"name": "Odd", "file": "odd.ckpt", "version": "v99"},
+                {"name": "Bad encoder", "file": "bad.ckpt", "autoencoder": 5}
+            ]"#,
+        )
+        .unwrap();
+
+        let (models, diagnostics) = lint_entries::<CustomModel>(&json, dir.path());
+        let files: Vec<_> = models.iter().map(|m| m.file.as_str()).collect();
+        assert_eq!(files, vec!["flux.ckpt", "odd.ckpt"]);
+
+        assert_eq!(
+            problems(&diagnostics),
+            vec![
+                (Some(0), Problem::MissingEncoder),
+                (Some(1), Problem::MissingKey),
+                (Some(2), Problem::NotAnObject),
+                (Some(3), Problem::DuplicateFile),
+                (Some(4), Problem::UnknownVersion),
+                (Some(5), Problem::InvalidValue),
+            ]
+        );
+        assert!(diagnostics[0].message.contains("t5_missing.ckpt"));
+        assert_eq!(diagnostics[1].file.as_deref(), Some("no_name.ckpt"));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_lint_lora_bounds() {
+        let json = CustomJsonFile::parse(
+            CustomJsonKind::Lora,
+            r#"[
+                {"name": "Ok", "file": "ok.ckpt", "weight": {"value": 0.6}},
+                {"name": "High", "file": "high.ckpt", "weight": {"value": 3.0}},
+                {"name": "Flipped", "file": "flipped.ckpt",
+                 "weight": {"value": 0.5, "lower_bound": 1, "upper_bound": 0}},
+                {"name": "Custom", "file": "custom.ckpt",
+                 "weight": {"value": 4, "lower_bound": 0, "upper_bound": 5}}
+            ]"#,
+        )
+        .unwrap();
+
+        let (loras, diagnostics) = lint_entries::<CustomLora>(&json, Path::new("/nonexistent"));
+        assert_eq!(loras.len(), 4);
+        assert_eq!(
+            problems(&diagnostics),
+            vec![
+                (Some(1), Problem::LoraBounds),
+                (Some(2), Problem::LoraBounds)
+            ]
+        );
+    }
+}
@@ -0,0 +1,763 @@
+use crate::dt_json::{CustomJsonFile, CustomJsonKind, DrawThingsConfig};
+use crate::dt_lint::ENCODER_KEYS;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_hash::{HashCache, HashMode};
+use crate::file_ops;
+use crate::file_policy::ExtensionPolicy;
+use crate::model_scan::{self, FileClass, ScannedFile};
+use crate::stash_audit::AuditManifest;
+use crate::sync_plan::Side;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One stored copy of a duplicated model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateCopy {
+    pub location: Side,
+    pub filename: String,
+    pub relative_path: String,
+    pub modified: Option<String>,
+    /// Companion files (e.g. `-tensordata`) stored with it, relative to `Models`
+    pub companions: Vec<String>,
+}
+
+/// A registry entry that points at one of the copies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRef {
+    pub location: Side,
+    pub json_file: String,
+    /// The filename it references
+    pub references: String,
+    /// `file`, or the encoder key (`autoencoder`, `clip_encoder`, `text_encoder`,
+    /// `t5_encoder`, `image_encoder`, `preprocessor`)
+    pub key: String,
+    /// The entry's own `file` and `name`
+    pub entry_file: String,
+    pub entry_name: Option<String>,
+}
+
+/// Files with identical content (model plus companions) stored under more than one filename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    /// Bytes of one copy, with its companions
+    pub size: u64,
+    pub filenames: Vec<String>,
+    pub copies: Vec<DuplicateCopy>,
+    pub references: Vec<JsonRef>,
+    /// Bytes freed by keeping one copy per location
+    pub wasted_bytes: u64,
+    /// Keeps the most referenced filename
+    pub suggested: DedupePlan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    pub wasted_bytes: u64,
+    pub files_hashed: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DedupeAction {
+    /// The keeper isn't stored at this location; one duplicate takes its name (with companions)
+    Rename {
+        location: Side,
+        from: String,
+        to: String,
+    },
+    /// Drop the entry for `file` from a registry that lists the keeper already
+    /// (or will, after an earlier repoint), so it isn't listed twice
+    RemoveEntry {
+        location: Side,
+        json_file: String,
+        file: String,
+        name: Option<String>,
+    },
+    /// Point references to `from` at `to` in one registry
+    Repoint {
+        location: Side,
+        json_file: String,
+        from: String,
+        to: String,
+    },
+    /// Delete a duplicate and its companions, once the keeper is verified identical
+    Delete {
+        location: Side,
+        relative_path: String,
+        companions: Vec<String>,
+        size: u64,
+    },
+}
+
+/// Keep one filename, repoint the registries at it and remove the other copies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupePlan {
+    pub keep: String,
+    /// Renames first, then registry edits (entry removals before repoints), then deletes
+    pub actions: Vec<DedupeAction>,
+    pub bytes_freed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupeResult {
+    pub done: Vec<DedupeAction>,
+    /// The action that failed; nothing after it was attempted
+    pub failed: Option<(DedupeAction, AppError)>,
+    pub bytes_freed: u64,
+}
+
+/// A model file with its companions, before hashing
+struct Candidate {
+    location: Side,
+    file: ScannedFile,
+    companions: Vec<ScannedFile>,
+}
+
+impl Candidate {
+    fn size(&self) -> u64 {
+        self.file.file_size + self.companions.iter().map(|c| c.file_size).sum::<u64>()
+    }
+}
+
+/// Find models stored under more than one filename across `<mac>/Models` and `<stash>/Models`.
+/// Files are grouped by size (model plus companions) first; only groups with different
+/// filenames are hashed (BLAKE3, via `cache`), so the same file on the Mac and in the stash
+/// costs nothing. Each group lists the registry entries referencing any of its filenames.
+pub fn find_duplicates<P: AsRef<Path>, Q: AsRef<Path>>(
+    mac_base_dir: P,
+    stash_base_dir: Q,
+    policy: &ExtensionPolicy,
+    cache: &HashCache,
+) -> AppResult<DuplicateReport> {
+    let mut warnings = Vec::new();
+    let mut candidates = Vec::new();
+    let mut models_dirs = HashMap::new();
+
+    for (location, base_dir) in [
+        (Side::Mac, mac_base_dir.as_ref()),
+        (Side::Stash, stash_base_dir.as_ref()),
+    ] {
+        let listing = model_scan::scan_models_dir_with(base_dir, policy)?;
+        // A reference hidden in a broken registry would let a used copy be deleted
+        DrawThingsConfig::parse_checked(&listing.models_dir)?;
+        warnings.extend(listing.errors);
+        models_dirs.insert(location, PathBuf::from(&listing.models_dir));
+
+        let mut companions: HashMap<String, Vec<ScannedFile>> = HashMap::new();
+        let mut models = Vec::new();
+        for file in listing.files {
+            match (file.class, &file.companion_of) {
+                (FileClass::Companion, Some(model)) => {
+                    let suffix = &file.ckpt_filename[model.len()..];
+                    if let Some(model_path) = file.relative_path.strip_suffix(suffix) {
+                        companions
+                            .entry(model_path.to_string())
+                            .or_default()
+                            .push(file.clone());
+                    }
+                }
+                (class, _) if class.is_model() => models.push(file),
+                _ => {}
+            }
+        }
+        for file in models {
+            let mut companions = companions.remove(&file.relative_path).unwrap_or_default();
+            companions.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+            candidates.push(Candidate {
+                location,
+                file,
+                companions,
+            });
+        }
+    }
+
+    let mut by_size: BTreeMap<u64, Vec<Candidate>> = BTreeMap::new();
+    for candidate in candidates {
+        if candidate.size() > 0 {
+            by_size.entry(candidate.size()).or_default().push(candidate);
+        }
+    }
+
+    let mut files_hashed = 0;
+    let mut by_hash: BTreeMap<(u64, String), Vec<Candidate>> = BTreeMap::new();
+    for (size, group) in by_size {
+        let names: BTreeSet<&str> = group
+            .iter()
+            .map(|c| c.file.ckpt_filename.as_str())
+            .collect();
+        if names.len() < 2 {
+            continue;
+        }
+        for candidate in group {
+            let models_dir = &models_dirs[&candidate.location];
+            match content_hash(models_dir, &candidate, policy, cache) {
+                Ok(hash) => {
+                    files_hashed += 1 + candidate.companions.len();
+                    by_hash.entry((size, hash)).or_default().push(candidate);
+                }
+                Err(e) => warnings.push(format!("{}: {}", candidate.file.relative_path, e)),
+            }
+        }
+    }
+
+    let references = json_references(&models_dirs, &mut warnings);
+
+    let mut groups = Vec::new();
+    for ((size, hash), candidates) in by_hash {
+        let filenames: BTreeSet<String> = candidates
+            .iter()
+            .map(|c| c.file.ckpt_filename.clone())
+            .collect();
+        if filenames.len() < 2 {
+            continue;
+        }
+
+        let copies: Vec<DuplicateCopy> = candidates
+            .into_iter()
+            .map(|c| DuplicateCopy {
+                location: c.location,
+                filename: c.file.ckpt_filename,
+                relative_path: c.file.relative_path,
+                modified: c.file.file_date,
+                companions: c.companions.into_iter().map(|f| f.relative_path).collect(),
+            })
+            .collect();
+        let group_refs: Vec<JsonRef> = references
+            .iter()
+            .filter(|r| filenames.contains(&r.references))
+            .cloned()
+            .collect();
+
+        let mut group = DuplicateGroup {
+            hash,
+            size,
+            filenames: filenames.into_iter().collect(),
+            copies,
+            references: group_refs,
+            wasted_bytes: 0,
+            suggested: DedupePlan {
+                keep: String::new(),
+                actions: Vec::new(),
+                bytes_freed: 0,
+            },
+        };
+        group.suggested = plan_dedupe(&group, None)?;
+        group.wasted_bytes = group.suggested.bytes_freed;
+        groups.push(group);
+    }
+
+    Ok(DuplicateReport {
+        wasted_bytes: groups.iter().map(|g| g.wasted_bytes).sum(),
+        groups,
+        files_hashed,
+        warnings,
+    })
+}
+
+/// BLAKE3 of the model, followed by each companion's
+fn content_hash(
+    models_dir: &Path,
+    candidate: &Candidate,
+    policy: &ExtensionPolicy,
+    cache: &HashCache,
+) -> std::io::Result<String> {
+    let hash = |file: &ScannedFile| {
+        let path = models_dir.join(&file.relative_path);
+        cache.hash_with_metadata(&path, &fs::metadata(&path)?, HashMode::Blake3)
+    };
+
+    let mut parts = vec![hash(&candidate.file)?];
+    for companion in &candidate.companions {
+        // Compare companions by suffix, not by their (differing) full names
+        let suffix = policy
+            .companion_of(&companion.ckpt_filename)
+            .map(|model| &companion.ckpt_filename[model.len()..])
+            .unwrap_or_default();
+        parts.push(format!("{}={}", suffix, hash(companion)?));
+    }
+    Ok(parts.join("+"))
+}
+
+/// Every `file` and encoder reference in the registries of both locations
+fn json_references(
+    models_dirs: &HashMap<Side, PathBuf>,
+    warnings: &mut Vec<String>,
+) -> Vec<JsonRef> {
+    let mut references = Vec::new();
+    for location in [Side::Mac, Side::Stash] {
+        for kind in CustomJsonKind::ALL {
+            let entries = CustomJsonFile::read(&models_dirs[&location], kind)
+                .and_then(|json| json.entries::<Value>());
+            let entries = match entries {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warnings.push(e.message);
+                    continue;
+                }
+            };
+
+            for entry in entries {
+                let text = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(String::from);
+                let Some(entry_file) = text("file") else {
+                    continue;
+                };
+                for key in std::iter::once(&"file").chain(ENCODER_KEYS) {
+                    if let Some(references_file) = text(key) {
+                        references.push(JsonRef {
+                            location,
+                            json_file: kind.file_name().to_string(),
+                            references: references_file,
+                            key: key.to_string(),
+                            entry_file: entry_file.clone(),
+                            entry_name: text("name"),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    references
+}
+
+/// Plan keeping `keep` (by default the filename with the most references, then one on the
+/// Mac, then the shortest). Per location the keeper stays - or a duplicate is renamed to it -
+/// the registries there are repointed, and the other copies are deleted. A registry keeps
+/// one entry for the keeper; the other duplicates' entries in it are removed.
+pub fn plan_dedupe(group: &DuplicateGroup, keep: Option<&str>) -> AppResult<DedupePlan> {
+    let keep = match keep {
+        Some(keep) if group.filenames.iter().any(|f| f == keep) => keep.to_string(),
+        Some(keep) => {
+            return Err(AppError::new(
+                ErrorCode::RecordNotFound,
+                format!("{} is not one of the duplicates", keep),
+            )
+            .with("keep", keep)
+            .with("filenames", &group.filenames))
+        }
+        None => suggested_keeper(group),
+    };
+
+    let mut renames = Vec::new();
+    let mut removals = Vec::new();
+    let mut repoints = Vec::new();
+    let mut deletes = Vec::new();
+    let mut bytes_freed = 0;
+
+    for location in [Side::Mac, Side::Stash] {
+        let copies: Vec<&DuplicateCopy> = group
+            .copies
+            .iter()
+            .filter(|c| c.location == location)
+            .collect();
+
+        let renamed = match copies.iter().any(|c| c.filename == keep) {
+            true => None,
+            false => copies.first().map(|c| c.filename.clone()),
+        };
+        if let Some(from) = &renamed {
+            renames.push(DedupeAction::Rename {
+                location,
+                from: from.clone(),
+                to: keep.clone(),
+            });
+        }
+
+        let references: Vec<&JsonRef> = group
+            .references
+            .iter()
+            .filter(|r| r.location == location)
+            .collect();
+        let entries: BTreeSet<(&str, &str)> = references
+            .iter()
+            .filter(|r| r.key == "file")
+            .map(|r| (r.json_file.as_str(), r.references.as_str()))
+            .collect();
+
+        // The first entry for a duplicate is repointed unless the keeper has one
+        let mut listed: BTreeSet<&str> = BTreeSet::new();
+        for &(json_file, file) in &entries {
+            if file == keep {
+                listed.insert(json_file);
+            }
+        }
+        let mut removed = BTreeSet::new();
+        for &(json_file, file) in &entries {
+            if file == keep || listed.insert(json_file) {
+                continue;
+            }
+            removed.insert((json_file, file));
+            removals.push(DedupeAction::RemoveEntry {
+                location,
+                json_file: json_file.to_string(),
+                file: file.to_string(),
+                name: references
+                    .iter()
+                    .find(|r| r.key == "file" && r.json_file == json_file && r.references == file)
+                    .and_then(|r| r.entry_name.clone()),
+            });
+        }
+
+        let mut repointed = BTreeSet::new();
+        for reference in references {
+            let in_removed =
+                removed.contains(&(reference.json_file.as_str(), reference.entry_file.as_str()));
+            if reference.references != keep && !in_removed {
+                repointed.insert((reference.json_file.clone(), reference.references.clone()));
+            }
+        }
+        repoints.extend(
+            repointed
+                .into_iter()
+                .map(|(json_file, from)| DedupeAction::Repoint {
+                    location,
+                    json_file,
+                    from,
+                    to: keep.clone(),
+                }),
+        );
+
+        for copy in copies {
+            if copy.filename == keep || Some(&copy.filename) == renamed.as_ref() {
+                continue;
+            }
+            bytes_freed += group.size;
+            deletes.push(DedupeAction::Delete {
+                location,
+                relative_path: copy.relative_path.clone(),
+                companions: copy.companions.clone(),
+                size: group.size,
+            });
+        }
+    }
+
+    Ok(DedupePlan {
+        keep,
+        actions: renames
+            .into_iter()
+            .chain(removals)
+            .chain(repoints)
+            .chain(deletes)
+            .collect(),
+        bytes_freed,
+    })
+}
+
+fn suggested_keeper(group: &DuplicateGroup) -> String {
+    let score = |filename: &String| {
+        let references = group
+            .references
+            .iter()
+            .filter(|r| &r.references == filename)
+            .count();
+        let on_mac = group
+            .copies
+            .iter()
+            .any(|c| &c.filename == filename && c.location == Side::Mac);
+        (references, on_mac, std::cmp::Reverse(filename.len()))
+    };
+    group
+        .filenames
+        .iter()
+        .max_by_key(|f| score(f))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Carry out a plan in order, stopping at the first failure. Before anything is deleted the
+/// keeper at that location is re-checked to be identical (size + BLAKE3). Registry edits are
+/// backed up under `<app_dir>/backups`. Refuses (41) to start while any registry has errors.
+pub fn apply_dedupe<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(
+    plan: &DedupePlan,
+    mac_base_dir: P,
+    stash_base_dir: Q,
+    app_dir: R,
+    policy: &ExtensionPolicy,
+) -> AppResult<DedupeResult> {
+    let models_dir = |location: Side| match location {
+        Side::Mac => mac_base_dir.as_ref().join("Models"),
+        Side::Stash => stash_base_dir.as_ref().join("Models"),
+    };
+    for location in [Side::Mac, Side::Stash] {
+        DrawThingsConfig::parse_checked(models_dir(location))?;
+    }
+    let mut result = DedupeResult {
+        done: Vec::new(),
+        failed: None,
+        bytes_freed: 0,
+    };
+
+    for action in &plan.actions {
+        let outcome = match action {
+            DedupeAction::Rename { location, from, to } => {
+                rename_with_companions(&models_dir(*location), from, to, policy).map(|moved| {
+                    // Best effort: at worst the next audit reports the old names missing
+                    if *location == Side::Stash {
+                        let _ = AuditManifest::rename(stash_base_dir.as_ref(), &moved);
+                    }
+                })
+            }
+            DedupeAction::RemoveEntry {
+                location,
+                json_file,
+                file,
+                ..
+            } => edit_json(
+                &models_dir(*location),
+                json_file,
+                app_dir.as_ref(),
+                |json| json.remove_entry(file),
+            ),
+            DedupeAction::Repoint {
+                location,
+                json_file,
+                from,
+                to,
+            } => edit_json(
+                &models_dir(*location),
+                json_file,
+                app_dir.as_ref(),
+                |json| json.repoint(from, to),
+            ),
+            DedupeAction::Delete {
+                location,
+                relative_path,
+                companions,
+                size,
+            } => {
+                let models = models_dir(*location);
+                delete_duplicate(&models, &plan.keep, relative_path, companions, policy).map(|()| {
+                    result.bytes_freed += size;
+                    if *location == Side::Stash {
+                        let mut deleted = companions.clone();
+                        deleted.push(relative_path.clone());
+                        let _ = AuditManifest::forget(stash_base_dir.as_ref(), &deleted);
+                    }
+                })
+            }
+        };
+
+        match outcome {
+            Ok(()) => result.done.push(action.clone()),
+            Err(error) => {
+                result.failed = Some((action.clone(), error));
+                break;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn rename_with_companions(
+    models_dir: &Path,
+    from: &str,
+    to: &str,
+    policy: &ExtensionPolicy,
+) -> AppResult<Vec<(String, String)>> {
+    let companions = policy
+        .companion_names(from)
+        .into_iter()
+        .zip(policy.companion_names(to))
+        .filter(|(from, _)| models_dir.join(from).is_file());
+
+    let mut moved = Vec::new();
+    for (from, to) in std::iter::once((from.to_string(), to.to_string())).chain(companions) {
+        let destination = models_dir.join(&to);
+        if destination.exists() {
+            return Err(AppError::from(ErrorCode::AlreadyExists).with("path", &destination));
+        }
+        file_ops::move_file(models_dir.join(&from), &destination)?;
+        moved.push((from, to));
+    }
+    Ok(moved)
+}
+
+/// Apply one edit to a registry, writing it (after a backup) only if anything changed
+fn edit_json<F>(models_dir: &Path, json_file: &str, app_dir: &Path, edit: F) -> AppResult<()>
+where
+    F: FnOnce(&mut CustomJsonFile) -> AppResult<usize>,
+{
+    let kind = CustomJsonKind::ALL
+        .into_iter()
+        .find(|k| k.file_name() == json_file)
+        .ok_or_else(|| AppError::from(ErrorCode::JsonNotFound).with("json_file", json_file))?;
+
+    let mut json = CustomJsonFile::read(models_dir, kind)?;
+    if edit(&mut json)? > 0 {
+        json.write(models_dir, app_dir)?;
+    }
+    Ok(())
+}
+
+fn delete_duplicate(
+    models_dir: &Path,
+    keep: &str,
+    relative_path: &str,
+    companions: &[String],
+    policy: &ExtensionPolicy,
+) -> AppResult<()> {
+    let duplicate = models_dir.join(relative_path);
+    let keeper = duplicate.with_file_name(keep);
+
+    let mut pairs = vec![(keeper.clone(), duplicate.clone())];
+    for companion in companions {
+        let path = models_dir.join(companion);
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+        let suffix = name.as_deref().and_then(|n| {
+            policy
+                .companion_of(n)
+                .map(|model| n[model.len()..].to_string())
+        });
+        if let Some(suffix) = suffix {
+            pairs.push((keeper.with_file_name(format!("{}{}", keep, suffix)), path));
+        }
+    }
+
+    for (keeper, duplicate) in &pairs {
+        if !file_ops::verify_copy(keeper, duplicate)? {
+            return Err(AppError::new(
+                ErrorCode::FileDelete,
+                "Not deleted: no longer identical to the kept copy",
+            )
+            .with("path", duplicate)
+            .with("kept", keeper));
+        }
+    }
+    for (_, duplicate) in pairs {
+        file_ops::delete_file(duplicate)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same SDXL model as `sdxl_f16.ckpt` (listed on the Mac, with a LoRA entry pointing
+    /// at it as well) and `sdxl_reimport_f16.ckpt`; the stash only has the reimport
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let root = tempfile::tempdir().unwrap();
+        let (mac, stash) = (root.path().join("mac"), root.path().join("stash"));
+        let (mac_models, stash_models) = (mac.join("Models"), stash.join("Models"));
+        fs::create_dir_all(&mac_models).unwrap();
+        fs::create_dir_all(&stash_models).unwrap();
+
+        for dir in [&mac_models, &stash_models] {
+            fs::write(dir.join("sdxl_reimport_f16.ckpt"), b"sdxl weights").unwrap();
+        }
+        fs::write(mac_models.join("sdxl_f16.ckpt"), b"sdxl weights").unwrap();
+        // Same size, different content
+        fs::write(mac_models.join("other_f16.ckpt"), b"other weight").unwrap();
+        fs::write(
+            mac_models.join("custom.json"),
+            r#"[{"name":"SDXL","file":"sdxl_f16.ckpt"},{"name":"SDXL again","file":"sdxl_reimport_f16.ckpt"},{"name":"Refiner","file":"refiner_f16.ckpt","autoencoder":"sdxl_reimport_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            stash_models.join("custom.json"),
+            r#"[{"name":"SDXL","file":"sdxl_reimport_f16.ckpt"}]"#,
+        )
+        .unwrap();
+
+        (root, mac, stash)
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let (_root, mac, stash) = setup();
+        let report =
+            find_duplicates(&mac, &stash, &ExtensionPolicy::default(), &HashCache::new()).unwrap();
+
+        assert_eq!(report.groups.len(), 1);
+        let group = &report.groups[0];
+        assert_eq!(
+            group.filenames,
+            vec!["sdxl_f16.ckpt", "sdxl_reimport_f16.ckpt"]
+        );
+        assert_eq!(group.copies.len(), 3);
+        assert_eq!(group.references.len(), 4);
+        assert_eq!(report.wasted_bytes, 12);
+
+        // The reimport has three references, so it is kept
+        let plan = &group.suggested;
+        assert_eq!(plan.keep, "sdxl_reimport_f16.ckpt");
+        assert_eq!(
+            plan.actions,
+            vec![
+                // custom.json lists both; the keeper's entry stays
+                DedupeAction::RemoveEntry {
+                    location: Side::Mac,
+                    json_file: "custom.json".into(),
+                    file: "sdxl_f16.ckpt".into(),
+                    name: Some("SDXL".into()),
+                },
+                DedupeAction::Delete {
+                    location: Side::Mac,
+                    relative_path: "sdxl_f16.ckpt".into(),
+                    companions: Vec::new(),
+                    size: 12,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_dedupe() {
+        let (root, mac, stash) = setup();
+        let policy = ExtensionPolicy::default();
+        let report = find_duplicates(&mac, &stash, &policy, &HashCache::new()).unwrap();
+
+        // Keep the original name instead: the stash copy is renamed to it
+        let plan = plan_dedupe(&report.groups[0], Some("sdxl_f16.ckpt")).unwrap();
+        assert!(matches!(
+            plan.actions[0],
+            DedupeAction::Rename {
+                location: Side::Stash,
+                ..
+            }
+        ));
+        assert!(plan.actions.contains(&DedupeAction::RemoveEntry {
+            location: Side::Mac,
+            json_file: "custom.json".into(),
+            file: "sdxl_reimport_f16.ckpt".into(),
+            name: Some("SDXL again".into()),
+        }));
+        assert!(plan_dedupe(&report.groups[0], Some("nope.ckpt")).is_err());
+
+        // A registry that doesn't parse may still use a copy: nothing is touched
+        let controlnets = mac.join("Models/custom_controlnet.json");
+        fs::write(
+            &controlnets,
+            r#"[{"name":"Tile","file":"tile_f16.ckpt","autoencoder":"sdxl_reimport_f16.ckpt"},]"#,
+        )
+        .unwrap();
+        let error = find_duplicates(&mac, &stash, &policy, &HashCache::new()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidJsonStructure);
+        let error =
+            apply_dedupe(&plan, &mac, &stash, root.path().join("app"), &policy).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidJsonStructure);
+        assert!(mac.join("Models/sdxl_reimport_f16.ckpt").exists());
+        fs::remove_file(&controlnets).unwrap();
+
+        let result = apply_dedupe(&plan, &mac, &stash, root.path().join("app"), &policy).unwrap();
+        assert!(result.failed.is_none(), "{:?}", result.failed);
+        assert_eq!(result.bytes_freed, 12);
+
+        assert!(!mac.join("Models/sdxl_reimport_f16.ckpt").exists());
+        assert!(stash.join("Models/sdxl_f16.ckpt").exists());
+        assert!(!stash.join("Models/sdxl_reimport_f16.ckpt").exists());
+
+        let json = CustomJsonFile::read(mac.join("Models"), CustomJsonKind::Model).unwrap();
+        let entries = json.entries::<Value>().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["autoencoder"], "sdxl_f16.ckpt");
+        let json = CustomJsonFile::read(stash.join("Models"), CustomJsonKind::Model).unwrap();
+        assert_eq!(json.entries::<Value>().unwrap()[0]["file"], "sdxl_f16.ckpt");
+    }
+}
@@ -0,0 +1,358 @@
+use crate::model_graph::FileInUse;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::io;
+
+/// Numbered errors from error_codes.md. Serialized as the bare number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
+pub enum ErrorCode {
+    // File Operations
+    DuplicateFilename = 2,
+    InsufficientSpace = 3,
+    PermissionDenied = 4,
+    FileNotFound = 5,
+    AlreadyExists = 6,
+    FileRead = 7,
+    FileWrite = 8,
+    FileDelete = 9,
+    FileCopy = 10,
+    DirectoryNotFound = 11,
+    DirectoryCreate = 12,
+    DirectoryNotWritable = 13,
+    DirectoryNotReadable = 14,
+    // Settings & Configuration
+    SettingsMissing = 15,
+    SettingsInvalidJson = 16,
+    SettingsCorrupt = 17,
+    DtBaseDirNotConfigured = 18,
+    StashDirNotConfigured = 19,
+    DtBaseDirInvalid = 20,
+    StashDirInvalid = 21,
+    DtBaseDirNotAccessible = 22,
+    StashDirNotAccessible = 23,
+    // Database
+    DatabaseConnection = 24,
+    DatabaseSchema = 25,
+    DatabaseQuery = 26,
+    DatabaseWrite = 27,
+    RecordNotFound = 28,
+    // Model Operations
+    ModelTypeUnknown = 29,
+    ModelNotInJson = 30,
+    InvalidModelType = 31,
+    ModelHasDependencies = 32,
+    ModelIsOrphan = 33,
+    ModelAlreadyStashed = 34,
+    ModelNotStashed = 35,
+    ParentModelsExist = 36,
+    ChildFilesMissing = 37,
+    // JSON Operations
+    JsonNotFound = 38,
+    JsonParse = 39,
+    JsonWrite = 40,
+    InvalidJsonStructure = 41,
+    JsonLocked = 42,
+    // Copy/Move/Delete Safety
+    OnlyCopy = 43,
+    MultipleReferences = 44,
+    DtBaseDirReadOnly = 45,
+    SameSourceAndDestination = 46,
+    SourceMissing = 47,
+    InvalidDestination = 48,
+    // Network/Updates
+    NetworkFailed = 49,
+    UpdateCheckFailed = 50,
+    DownloadFailed = 51,
+    InvalidDownloadUrl = 52,
+    ParquetUnavailable = 53,
+    // Initialization
+    NotInitialized = 54,
+    SetupIncomplete = 55,
+    DatabaseInit = 56,
+    // General
+    Unknown = 100,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 56] = [
+        Self::DuplicateFilename,
+        Self::InsufficientSpace,
+        Self::PermissionDenied,
+        Self::FileNotFound,
+        Self::AlreadyExists,
+        Self::FileRead,
+        Self::FileWrite,
+        Self::FileDelete,
+        Self::FileCopy,
+        Self::DirectoryNotFound,
+        Self::DirectoryCreate,
+        Self::DirectoryNotWritable,
+        Self::DirectoryNotReadable,
+        Self::SettingsMissing,
+        Self::SettingsInvalidJson,
+        Self::SettingsCorrupt,
+        Self::DtBaseDirNotConfigured,
+        Self::StashDirNotConfigured,
+        Self::DtBaseDirInvalid,
+        Self::StashDirInvalid,
+        Self::DtBaseDirNotAccessible,
+        Self::StashDirNotAccessible,
+        Self::DatabaseConnection,
+        Self::DatabaseSchema,
+        Self::DatabaseQuery,
+        Self::DatabaseWrite,
+        Self::RecordNotFound,
+        Self::ModelTypeUnknown,
+        Self::ModelNotInJson,
+        Self::InvalidModelType,
+        Self::ModelHasDependencies,
+        Self::ModelIsOrphan,
+        Self::ModelAlreadyStashed,
+        Self::ModelNotStashed,
+        Self::ParentModelsExist,
+        Self::ChildFilesMissing,
+        Self::JsonNotFound,
+        Self::JsonParse,
+        Self::JsonWrite,
+        Self::InvalidJsonStructure,
+        Self::JsonLocked,
+        Self::OnlyCopy,
+        Self::MultipleReferences,
+        Self::DtBaseDirReadOnly,
+        Self::SameSourceAndDestination,
+        Self::SourceMissing,
+        Self::InvalidDestination,
+        Self::NetworkFailed,
+        Self::UpdateCheckFailed,
+        Self::DownloadFailed,
+        Self::InvalidDownloadUrl,
+        Self::ParquetUnavailable,
+        Self::NotInitialized,
+        Self::SetupIncomplete,
+        Self::DatabaseInit,
+        Self::Unknown,
+    ];
+
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    pub fn from_code(code: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.code() == code)
+    }
+
+    /// Wording from error_codes.md
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::DuplicateFilename => "Duplicate filename",
+            Self::InsufficientSpace => "Insufficient disk space",
+            Self::PermissionDenied => "Insufficient permissions",
+            Self::FileNotFound => "File not found",
+            Self::AlreadyExists => "File already exists at destination",
+            Self::FileRead => "File read error",
+            Self::FileWrite => "File write error",
+            Self::FileDelete => "File delete error",
+            Self::FileCopy => "File copy error",
+            Self::DirectoryNotFound => "Directory not found",
+            Self::DirectoryCreate => "Directory creation failed",
+            Self::DirectoryNotWritable => "Directory not writable",
+            Self::DirectoryNotReadable => "Directory not readable",
+            Self::SettingsMissing => "Settings file missing",
+            Self::SettingsInvalidJson => "Settings file invalid JSON",
+            Self::SettingsCorrupt => "Settings file corrupt",
+            Self::DtBaseDirNotConfigured => "DT_BASE_DIR not configured",
+            Self::StashDirNotConfigured => "STASH_DIR not configured",
+            Self::DtBaseDirInvalid => "DT_BASE_DIR path invalid",
+            Self::StashDirInvalid => "STASH_DIR path invalid",
+            Self::DtBaseDirNotAccessible => "DT_BASE_DIR not accessible",
+            Self::StashDirNotAccessible => "STASH_DIR not accessible",
+            Self::DatabaseConnection => "Database connection failed",
+            Self::DatabaseSchema => "Database schema invalid",
+            Self::DatabaseQuery => "Database query error",
+            Self::DatabaseWrite => "Database write error",
+            Self::RecordNotFound => "Record not found",
+            Self::ModelTypeUnknown => "Model type unknown",
+            Self::ModelNotInJson => "Model not in JSON file",
+            Self::InvalidModelType => "Invalid model type",
+            Self::ModelHasDependencies => "Model has dependencies (cannot delete)",
+            Self::ModelIsOrphan => "Model is orphan",
+            Self::ModelAlreadyStashed => "Model already stashed",
+            Self::ModelNotStashed => "Model not stashed",
+            Self::ParentModelsExist => "Parent models exist (file in use)",
+            Self::ChildFilesMissing => "Child files missing",
+            Self::JsonNotFound => "JSON file not found",
+            Self::JsonParse => "JSON parse error",
+            Self::JsonWrite => "JSON write error",
+            Self::InvalidJsonStructure => "Invalid JSON structure",
+            Self::JsonLocked => "JSON file locked",
+            Self::OnlyCopy => "Only copy exists (cannot delete)",
+            Self::MultipleReferences => "File referenced by multiple models",
+            Self::DtBaseDirReadOnly => "Attempting to write to DT_BASE_DIR (read-only)",
+            Self::SameSourceAndDestination => "Source and destination same",
+            Self::SourceMissing => "Source file missing",
+            Self::InvalidDestination => "Destination path invalid",
+            Self::NetworkFailed => "Network connection failed",
+            Self::UpdateCheckFailed => "Update check failed",
+            Self::DownloadFailed => "Download failed",
+            Self::InvalidDownloadUrl => "Invalid download URL",
+            Self::ParquetUnavailable => "Parquet file unavailable",
+            Self::NotInitialized => "App not initialized",
+            Self::SetupIncomplete => "First-time setup incomplete",
+            Self::DatabaseInit => "Database initialization failed",
+            Self::Unknown => "Unknown error",
+        }
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for ErrorCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = u16::deserialize(deserializer)?;
+        Ok(Self::from_code(code).unwrap_or(Self::Unknown))
+    }
+}
+
+/// Error returned by every command. Serializes to the same `{ code, message, details }`
+/// shape the frontend puts in its `error` arrays, e.g.
+/// `{ "code": 3, "message": "Insufficient disk space", "details": { "required_bytes": 42 } }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Structured context: paths, byte counts, the underlying OS error...
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub details: Map<String, Value>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: Map::new(),
+        }
+    }
+
+    /// An I/O failure while doing `code`. Missing files, permissions and full disks
+    /// get their own codes whatever the operation was.
+    pub fn io(code: ErrorCode, error: &io::Error) -> Self {
+        let code = match error.kind() {
+            io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+            io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            io::ErrorKind::AlreadyExists => ErrorCode::AlreadyExists,
+            io::ErrorKind::StorageFull => ErrorCode::InsufficientSpace,
+            _ => code,
+        };
+        Self::new(code, format!("{}: {}", code.description(), error))
+            .with("os_error", error.to_string())
+    }
+
+    /// Add a `details` entry
+    pub fn with(mut self, key: &str, value: impl Serialize) -> Self {
+        self.details.insert(
+            key.to_string(),
+            serde_json::to_value(value).unwrap_or(Value::Null),
+        );
+        self
+    }
+}
+
+impl From<ErrorCode> for AppError {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code, code.description())
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(error: io::Error) -> Self {
+        Self::io(ErrorCode::Unknown, &error)
+    }
+}
+
+impl From<FileInUse> for AppError {
+    fn from(in_use: FileInUse) -> Self {
+        Self::new(in_use.code(), in_use.to_string())
+            .with("file", &in_use.file)
+            .with("parents", &in_use.parents)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_codes_match_error_codes_md() {
+        let doc = include_str!("../../error_codes.md");
+        let mut documented = 0;
+
+        for line in doc.lines() {
+            let Some((number, text)) = line.split_once(". ") else {
+                continue;
+            };
+            let Ok(number) = number.trim().parse::<u16>() else {
+                continue;
+            };
+            let code = ErrorCode::from_code(number).unwrap();
+            assert_eq!(code.description(), text.trim(), "code {}", number);
+            documented += 1;
+        }
+
+        assert_eq!(documented, ErrorCode::ALL.len());
+    }
+
+    #[test]
+    fn test_serializes_like_frontend_errors() {
+        let error = AppError::from(ErrorCode::InsufficientSpace)
+            .with("path", "/Volumes/Stash/Models")
+            .with("required_bytes", 42u64);
+
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "code": 3,
+                "details": {"path": "/Volumes/Stash/Models", "required_bytes": 42},
+                "message": "Insufficient disk space"
+            })
+        );
+        assert_eq!(serde_json::from_value::<AppError>(value).unwrap(), error);
+
+        // No context, no `details` key
+        let bare = serde_json::to_value(AppError::from(ErrorCode::Unknown)).unwrap();
+        assert_eq!(bare, json!({"code": 100, "message": "Unknown error"}));
+    }
+
+    #[test]
+    fn test_io_errors_keep_specific_codes() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        assert_eq!(
+            AppError::io(ErrorCode::FileCopy, &missing).code,
+            ErrorCode::FileNotFound
+        );
+
+        let other = io::Error::other("bad sector");
+        let error = AppError::io(ErrorCode::FileCopy, &other);
+        assert_eq!(error.code, ErrorCode::FileCopy);
+        assert_eq!(error.message, "File copy error: bad sector");
+        assert_eq!(error.details["os_error"], "bad sector");
+    }
+}
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Bytes read from each end of a file for a partial fingerprint
+const FINGERPRINT_CHUNK: u64 = 1024 * 1024;
+
+/// Read buffer size for full-file hashing
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Hashing modes supported by `meta` / `meta_many`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+    /// Full SHA-256 of the file contents (slow, widely comparable)
+    Sha256,
+    /// Full BLAKE3 of the file contents (fast)
+    Blake3,
+    /// BLAKE3 of the file size plus the first and last 1 MiB - cheap, but only
+    /// good for telling files apart, not for verifying them
+    Fingerprint,
+}
+
+/// Calculate SHA256 checksum of a file
+pub fn calculate_checksum<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Calculate BLAKE3 hash of a file
+pub fn calculate_blake3<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Calculate a partial fingerprint: BLAKE3 over the file size, the first 1 MiB
+/// and the last 1 MiB. Small files are hashed in full.
+pub fn calculate_fingerprint<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&size.to_le_bytes());
+
+    if size <= FINGERPRINT_CHUNK * 2 {
+        io::copy(&mut file, &mut hasher)?;
+    } else {
+        let mut buffer = vec![0; FINGERPRINT_CHUNK as usize];
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+
+        file.seek(SeekFrom::End(-(FINGERPRINT_CHUNK as i64)))?;
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Hash a file with the given mode, bypassing the cache
+pub fn hash_file<P: AsRef<Path>>(path: P, mode: HashMode) -> io::Result<String> {
+    match mode {
+        HashMode::Sha256 => calculate_checksum(path),
+        HashMode::Blake3 => calculate_blake3(path),
+        HashMode::Fingerprint => calculate_fingerprint(path),
+    }
+}
+
+// ################################################################################
+// # Hash Cache
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    path: PathBuf,
+    size: u64,
+    modified: Option<SystemTime>,
+    mode: HashMode,
+}
+
+/// In-memory cache of file hashes keyed by (path, size, mtime, mode).
+/// A file that changes size or mtime simply misses the cache and is re-hashed.
+pub struct HashCache {
+    entries: Mutex<HashMap<CacheKey, String>>,
+}
+
+impl HashCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hash a file, reusing a previous result if the file's size and mtime are unchanged
+    pub fn hash_with_metadata(
+        &self,
+        path: &Path,
+        metadata: &fs::Metadata,
+        mode: HashMode,
+    ) -> io::Result<String> {
+        let key = CacheKey {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+            mode,
+        };
+
+        if let Ok(entries) = self.entries.lock() {
+            if let Some(hash) = entries.get(&key) {
+                return Ok(hash.clone());
+            }
+        }
+
+        // Hash outside the lock so parallel callers don't serialize on one big file
+        let hash = hash_file(path, mode)?;
+
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, hash.clone());
+        }
+
+        Ok(hash)
+    }
+}
+
+impl Default for HashCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.ckpt");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            calculate_checksum(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            calculate_blake3(&path).unwrap(),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_middle_of_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let size = (FINGERPRINT_CHUNK * 3) as usize;
+
+        let mut a = vec![1u8; size];
+        let path_a = dir.path().join("a.ckpt");
+        fs::write(&path_a, &a).unwrap();
+
+        // Change a byte in the middle: fingerprint is blind to it, full hash is not
+        a[size / 2] = 2;
+        let path_b = dir.path().join("b.ckpt");
+        fs::write(&path_b, &a).unwrap();
+
+        assert_eq!(
+            calculate_fingerprint(&path_a).unwrap(),
+            calculate_fingerprint(&path_b).unwrap()
+        );
+        assert_ne!(
+            calculate_blake3(&path_a).unwrap(),
+            calculate_blake3(&path_b).unwrap()
+        );
+
+        // Change the tail: fingerprint notices
+        a[size - 1] = 3;
+        fs::write(&path_b, &a).unwrap();
+        assert_ne!(
+            calculate_fingerprint(&path_a).unwrap(),
+            calculate_fingerprint(&path_b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cache_is_keyed_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.ckpt");
+        fs::write(&path, b"first").unwrap();
+
+        let cache = HashCache::new();
+        let hash = |cache: &HashCache| {
+            let metadata = fs::metadata(&path).unwrap();
+            cache
+                .hash_with_metadata(&path, &metadata, HashMode::Blake3)
+                .unwrap()
+        };
+
+        let first = hash(&cache);
+        assert_eq!(hash(&cache), first);
+        assert_eq!(cache.entries.lock().unwrap().len(), 1);
+
+        fs::write(&path, b"second version").unwrap();
+        let second = hash(&cache);
+        assert_ne!(first, second);
+        assert_eq!(cache.entries.lock().unwrap().len(), 2);
+    }
+}
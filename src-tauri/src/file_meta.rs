@@ -0,0 +1,211 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_hash::{HashCache, HashMode};
+use glob::Pattern;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+// ################################################################################
+// # File Metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub exists: bool,
+    pub size: Option<u64>,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub hash: Option<String>,
+    pub hash_mode: Option<HashMode>,
+    pub error: Option<String>,
+}
+
+/// Format a filesystem timestamp the way the frontend expects it
+fn format_timestamp(time: std::io::Result<SystemTime>) -> Option<String> {
+    time.ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .map(|secs| {
+            let datetime = chrono::DateTime::from_timestamp(secs as i64, 0);
+            datetime
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| "Invalid timestamp".to_string())
+        })
+}
+
+/// Read size and created/modified times for a single path, optionally hashing
+/// the contents. Hashes are served from `cache` while size and mtime are unchanged.
+pub fn read_metadata<P: AsRef<Path>>(
+    path: P,
+    hash_mode: Option<HashMode>,
+    cache: &HashCache,
+) -> FileMetadata {
+    let path = path.as_ref();
+
+    match fs::metadata(path) {
+        Ok(metadata) => {
+            let mut result = FileMetadata {
+                exists: true,
+                size: Some(metadata.len()),
+                created: format_timestamp(metadata.created()),
+                modified: format_timestamp(metadata.modified()),
+                hash: None,
+                hash_mode: None,
+                error: None,
+            };
+
+            if let Some(mode) = hash_mode {
+                match cache.hash_with_metadata(path, &metadata, mode) {
+                    Ok(hash) => {
+                        result.hash = Some(hash);
+                        result.hash_mode = Some(mode);
+                    }
+                    Err(e) => result.error = Some(format!("Failed to hash file: {}", e)),
+                }
+            }
+
+            result
+        }
+        Err(e) => FileMetadata {
+            exists: false,
+            size: None,
+            created: None,
+            modified: None,
+            hash: None,
+            hash_mode: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Read metadata for many paths in parallel, keyed by the path as given.
+/// A failure on one path is reported in its own entry and never aborts the batch.
+pub fn read_metadata_many(
+    paths: &[String],
+    hash_mode: Option<HashMode>,
+    cache: &HashCache,
+) -> HashMap<String, FileMetadata> {
+    paths
+        .par_iter()
+        .map(|path| (path.clone(), read_metadata(path, hash_mode, cache)))
+        .collect()
+}
+
+/// List the files directly inside `dir` whose file name matches any of the glob
+/// `patterns` (e.g. `*.ckpt`). An empty pattern list matches every file.
+pub fn list_matching_files<P: AsRef<Path>>(dir: P, patterns: &[String]) -> AppResult<Vec<String>> {
+    let dir = dir.as_ref();
+
+    let compiled = patterns
+        .iter()
+        .map(|p| {
+            Pattern::new(p).map_err(|e| {
+                AppError::new(
+                    ErrorCode::Unknown,
+                    format!("Invalid glob pattern '{}': {}", p, e),
+                )
+                .with("pattern", p)
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let unreadable = |e: std::io::Error| {
+        if dir.is_dir() {
+            AppError::io(ErrorCode::DirectoryNotReadable, &e).with("path", dir)
+        } else {
+            AppError::from(ErrorCode::DirectoryNotFound).with("path", dir)
+        }
+    };
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(unreadable)? {
+        let entry = entry.map_err(unreadable)?;
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        if compiled.is_empty() || compiled.iter().any(|p| p.matches(&name)) {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_metadata_many_reports_per_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let present = dir.path().join("model.ckpt");
+        fs::write(&present, b"12345").unwrap();
+        let missing = dir.path().join("missing.ckpt");
+
+        let paths = vec![
+            present.to_string_lossy().to_string(),
+            missing.to_string_lossy().to_string(),
+        ];
+        let results = read_metadata_many(&paths, None, &HashCache::new());
+
+        let found = &results[&paths[0]];
+        assert!(found.exists);
+        assert_eq!(found.size, Some(5));
+        assert!(found.error.is_none());
+
+        let not_found = &results[&paths[1]];
+        assert!(!not_found.exists);
+        assert!(not_found.error.is_some());
+    }
+
+    #[test]
+    fn test_read_metadata_with_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.ckpt");
+        fs::write(&path, b"abc").unwrap();
+
+        let cache = HashCache::new();
+        let plain = read_metadata(&path, None, &cache);
+        assert!(plain.hash.is_none());
+
+        let hashed = read_metadata(&path, Some(HashMode::Sha256), &cache);
+        assert_eq!(hashed.hash_mode, Some(HashMode::Sha256));
+        assert_eq!(
+            hashed.hash.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert!(hashed.error.is_none());
+    }
+
+    #[test]
+    fn test_list_matching_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.ckpt"), b"").unwrap();
+        fs::write(dir.path().join("b.safetensors"), b"").unwrap();
+        fs::write(dir.path().join("custom.json"), b"[]").unwrap();
+        fs::create_dir(dir.path().join("sub.ckpt")).unwrap();
+
+        let patterns = vec!["*.ckpt".to_string(), "*.safetensors".to_string()];
+        let files = list_matching_files(dir.path(), &patterns).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|f| {
+                Path::new(f)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(names, vec!["a.ckpt", "b.safetensors"]);
+
+        assert_eq!(list_matching_files(dir.path(), &[]).unwrap().len(), 3);
+        assert!(list_matching_files(dir.path(), &["[".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use crate::copy_engine::{copy_with_progress, partial_path, CopyOptions, CopyProgress};
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_hash::calculate_blake3;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+/// Get file metadata
+pub fn get_file_size<P: AsRef<Path>>(path: P) -> AppResult<u64> {
+    let path = path.as_ref();
+    let metadata =
+        fs::metadata(path).map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", path))?;
+    Ok(metadata.len())
+}
+
+/// Source must exist and differ from the destination
+fn check_source_destination(source: &Path, destination: &Path) -> AppResult<()> {
+    if !source.is_file() {
+        return Err(AppError::from(ErrorCode::SourceMissing).with("source", source));
+    }
+    if source == destination || fs::canonicalize(source).ok() == fs::canonicalize(destination).ok()
+    {
+        return Err(AppError::from(ErrorCode::SameSourceAndDestination)
+            .with("source", source)
+            .with("destination", destination));
+    }
+    Ok(())
+}
+
+/// Copy file in chunks via `<destination>.partial`, resuming an interrupted copy.
+/// Creates the destination directory if needed.
+pub fn copy_file<P: AsRef<Path>, Q: AsRef<Path>>(source: P, destination: Q) -> AppResult<u64> {
+    let (source, destination) = (source.as_ref(), destination.as_ref());
+    check_source_destination(source, destination)?;
+
+    copy_with_progress(source, destination, &CopyOptions::default(), None, |_| {}).map_err(|e| {
+        AppError::io(ErrorCode::FileCopy, &e)
+            .with("source", source)
+            .with("destination", destination)
+    })
+}
+
+/// Move file
+pub fn move_file<P: AsRef<Path>, Q: AsRef<Path>>(source: P, destination: Q) -> AppResult<()> {
+    let (source, destination) = (source.as_ref(), destination.as_ref());
+    check_source_destination(source, destination)?;
+
+    // Ensure destination directory exists
+    if let Some(parent) = destination.parent() {
+        ensure_directory(parent)?;
+    }
+
+    fs::rename(source, destination).map_err(|e| {
+        AppError::io(ErrorCode::FileWrite, &e)
+            .with("source", source)
+            .with("destination", destination)
+    })
+}
+
+/// Delete file
+pub fn delete_file<P: AsRef<Path>>(path: P) -> AppResult<()> {
+    let path = path.as_ref();
+    fs::remove_file(path).map_err(|e| AppError::io(ErrorCode::FileDelete, &e).with("path", path))
+}
+
+/// Ensure directory exists
+pub fn ensure_directory<P: AsRef<Path>>(path: P) -> AppResult<()> {
+    let path = path.as_ref();
+    fs::create_dir_all(path)
+        .map_err(|e| AppError::io(ErrorCode::DirectoryCreate, &e).with("path", path))
+}
+
+/// Capacity and identity of the volume holding a path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskSpace {
+    /// Path that was queried: the one asked for, or its nearest existing ancestor
+    pub path: String,
+    pub mount_point: Option<String>,
+    /// e.g. "apfs", "ext4", "exfat", "smbfs"
+    pub fs_type: Option<String>,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub warning: Option<VolumeWarning>,
+}
+
+/// Volumes that work, but are a poor home for a stash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeWarning {
+    Exfat,
+    NetworkShare,
+}
+
+impl VolumeWarning {
+    pub fn for_fs_type(fs_type: &str) -> Option<Self> {
+        match fs_type.to_ascii_lowercase().as_str() {
+            "exfat" | "fuse.exfat" => Some(Self::Exfat),
+            "nfs" | "nfs4" | "cifs" | "smb3" | "smbfs" | "afpfs" | "webdav" | "davfs"
+            | "fuse.sshfs" | "9p" => Some(Self::NetworkShare),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Exfat => {
+                "exFAT has no journal: unplugging the drive during a copy can corrupt it"
+            }
+            Self::NetworkShare => "Network share: copies are slow and fail if the connection drops",
+        }
+    }
+}
+
+/// Capacity, mount point and file system of the volume holding `path`.
+/// A path that doesn't exist yet is looked up through its nearest existing ancestor.
+pub fn disk_space<P: AsRef<Path>>(path: P) -> AppResult<DiskSpace> {
+    let path = path.as_ref();
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| AppError::from(ErrorCode::DirectoryNotFound).with("path", path))?;
+
+    let mut space = volume_stats(existing)
+        .map_err(|e| AppError::io(ErrorCode::DirectoryNotReadable, &e).with("path", existing))?;
+    space.warning = space
+        .fs_type
+        .as_deref()
+        .and_then(VolumeWarning::for_fs_type);
+    Ok(space)
+}
+
+/// Get available disk space for a given path (in bytes)
+pub fn get_available_space<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    volume_stats(path.as_ref()).map(|space| space.available_bytes)
+}
+
+#[cfg(unix)]
+fn path_cstring(path: &Path) -> io::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+    std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid path"))
+}
+
+#[cfg(target_os = "macos")]
+fn volume_stats(path: &Path) -> io::Result<DiskSpace> {
+    use std::ffi::CStr;
+    use std::mem;
+
+    let path_cstring = path_cstring(path)?;
+    let stat = unsafe {
+        let mut stat: libc::statfs = mem::zeroed();
+        if libc::statfs(path_cstring.as_ptr(), &mut stat) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat
+    };
+    let name = |chars: &[libc::c_char]| {
+        unsafe { CStr::from_ptr(chars.as_ptr()) }
+            .to_string_lossy()
+            .to_string()
+    };
+
+    // Blocks * block size = bytes
+    let block_size = stat.f_bsize as u64;
+    Ok(DiskSpace {
+        path: path.to_string_lossy().to_string(),
+        mount_point: Some(name(&stat.f_mntonname)),
+        fs_type: Some(name(&stat.f_fstypename)),
+        total_bytes: stat.f_blocks * block_size,
+        used_bytes: (stat.f_blocks - stat.f_bfree) * block_size,
+        available_bytes: stat.f_bavail * block_size,
+        warning: None,
+    })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+// statvfs field types differ between platforms and word sizes
+#[allow(clippy::useless_conversion)]
+fn volume_stats(path: &Path) -> io::Result<DiskSpace> {
+    let path_cstring = path_cstring(path)?;
+    let stat = unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(path_cstring.as_ptr(), &mut stat) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat
+    };
+
+    // Block counts are in units of the fragment size
+    let block_size = match u64::from(stat.f_frsize) {
+        0 => u64::from(stat.f_bsize),
+        size => size,
+    };
+    let blocks = u64::from(stat.f_blocks);
+    let free = u64::from(stat.f_bfree);
+
+    #[cfg(target_os = "linux")]
+    let mount = fs::read_to_string("/proc/self/mountinfo")
+        .ok()
+        .zip(fs::canonicalize(path).ok())
+        .and_then(|(mountinfo, path)| find_mount(&mountinfo, &path));
+    #[cfg(not(target_os = "linux"))]
+    let mount: Option<(String, String)> = None;
+    let (mount_point, fs_type) = mount.unzip();
+
+    Ok(DiskSpace {
+        path: path.to_string_lossy().to_string(),
+        mount_point,
+        fs_type,
+        total_bytes: blocks * block_size,
+        used_bytes: blocks.saturating_sub(free) * block_size,
+        available_bytes: u64::from(stat.f_bavail) * block_size,
+        warning: None,
+    })
+}
+
+#[cfg(not(unix))]
+fn volume_stats(path: &Path) -> io::Result<DiskSpace> {
+    // Fallback for other platforms - report unlimited space
+    Ok(DiskSpace {
+        path: path.to_string_lossy().to_string(),
+        mount_point: None,
+        fs_type: None,
+        total_bytes: u64::MAX,
+        used_bytes: 0,
+        available_bytes: u64::MAX,
+        warning: None,
+    })
+}
+
+/// The `/proc/self/mountinfo` entry whose mount point is the longest prefix of `path`,
+/// as (mount point, fs type). The last of several mounts on one point is the visible one.
+#[cfg(any(target_os = "linux", test))]
+fn find_mount(mountinfo: &str, path: &Path) -> Option<(String, String)> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            // id parent major:minor root mount_point options [optional...] - fs_type source super_options
+            let fields: Vec<&str> = line.split(' ').collect();
+            let mount_point = unescape_mount_field(fields.get(4)?);
+            let separator = fields.iter().position(|f| *f == "-")?;
+            let fs_type = fields.get(separator + 1)?.to_string();
+            Some((mount_point, fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.len())
+}
+
+/// mountinfo escapes space, tab, newline and backslash as `\ooo` octal
+#[cfg(any(target_os = "linux", test))]
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            if let Some(byte) = field
+                .get(i + 1..i + 4)
+                .and_then(|octal| u8::from_str_radix(octal, 8).ok())
+            {
+                out.push(byte);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Check if there's enough space for a file copy operation
+pub fn has_enough_space<P: AsRef<Path>>(destination: P, required_bytes: u64) -> io::Result<bool> {
+    let available = get_available_space(destination)?;
+    Ok(available >= with_buffer(required_bytes))
+}
+
+/// Add 10% buffer for safety
+fn with_buffer(required_bytes: u64) -> u64 {
+    required_bytes.saturating_add(required_bytes / 10)
+}
+
+/// Like `has_enough_space`, but fails with `InsufficientSpace` (3) carrying the byte counts
+pub fn require_space<P: AsRef<Path>>(destination: P, required_bytes: u64) -> AppResult<()> {
+    let destination = destination.as_ref();
+    let available = get_available_space(destination)
+        .map_err(|e| AppError::io(ErrorCode::DirectoryNotReadable, &e).with("path", destination))?;
+
+    if available >= with_buffer(required_bytes) {
+        Ok(())
+    } else {
+        Err(AppError::from(ErrorCode::InsufficientSpace)
+            .with("path", destination)
+            .with("required_bytes", required_bytes)
+            .with("available_bytes", available))
+    }
+}
+
+/// Check that `destination` is a faithful copy of `source`: same size, then same BLAKE3 hash
+pub fn verify_copy<P: AsRef<Path>, Q: AsRef<Path>>(source: P, destination: Q) -> AppResult<bool> {
+    let (source, destination) = (source.as_ref(), destination.as_ref());
+    if get_file_size(source)? != get_file_size(destination)? {
+        return Ok(false);
+    }
+
+    let hash = |path: &Path| {
+        calculate_blake3(path).map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", path))
+    };
+    Ok(hash(source)? == hash(destination)?)
+}
+
+/// Copy `source` to `destination` via `copy_engine` and verify it by size and hash; a copy
+/// that fails verification is removed. An identical file already at the destination counts
+/// as copied; a different one is an error unless `replace` is set. After an I/O error the
+/// `.partial` stays so a retry resumes; it is removed when cancelled or the source changed.
+pub fn copy_verified(
+    source: &Path,
+    destination: &Path,
+    replace: bool,
+    cancel: Option<&AtomicBool>,
+    on_progress: &mut dyn FnMut(&CopyProgress),
+) -> AppResult<()> {
+    if destination.exists() && !replace {
+        if !verify_copy(source, destination)? {
+            return Err(AppError::new(
+                ErrorCode::AlreadyExists,
+                format!(
+                    "File already exists at destination: {}",
+                    destination.display()
+                ),
+            )
+            .with("source", source)
+            .with("destination", destination));
+        }
+    } else {
+        let parent = destination.parent().unwrap_or(Path::new("."));
+        ensure_directory(parent)?;
+
+        // The copy is written to `.partial` and only renamed into place when complete
+        let options = CopyOptions::default();
+        if let Err(e) = copy_with_progress(source, destination, &options, cancel, on_progress) {
+            if matches!(
+                e.kind(),
+                io::ErrorKind::Interrupted | io::ErrorKind::UnexpectedEof
+            ) {
+                let _ = fs::remove_file(partial_path(destination));
+            }
+            return Err(AppError::io(ErrorCode::FileCopy, &e)
+                .with("source", source)
+                .with("destination", destination));
+        }
+
+        let verified = match verify_copy(source, destination) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(AppError::new(
+                ErrorCode::FileCopy,
+                "Copy verification failed: size or hash mismatch",
+            )
+            .with("source", source)
+            .with("destination", destination)),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = verified {
+            let _ = fs::remove_file(destination);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_mount() {
+        let mountinfo = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+40 22 8:17 / /media/stash rw,nosuid shared:20 - exfat /dev/sdb1 rw
+41 22 0:50 / /mnt/nas\\040share rw - cifs //nas/models rw
+42 40 0:51 / /media/stash rw - fuse.exfat /dev/sdb1 rw
+";
+
+        let mount = |path: &str| find_mount(mountinfo, Path::new(path)).unwrap();
+        assert_eq!(mount("/home/me/Models"), ("/".into(), "ext4".into()));
+        assert_eq!(
+            mount("/media/stash/Models"),
+            ("/media/stash".into(), "fuse.exfat".into())
+        );
+        assert_eq!(
+            mount("/mnt/nas share/Models"),
+            ("/mnt/nas share".into(), "cifs".into())
+        );
+        // Prefix match is by path component, not by string
+        assert_eq!(mount("/media/stash2").0, "/");
+
+        assert_eq!(
+            VolumeWarning::for_fs_type("fuse.exfat"),
+            Some(VolumeWarning::Exfat)
+        );
+        assert_eq!(
+            VolumeWarning::for_fs_type("cifs"),
+            Some(VolumeWarning::NetworkShare)
+        );
+        assert_eq!(VolumeWarning::for_fs_type("apfs"), None);
+    }
+
+    #[test]
+    fn test_disk_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let space = disk_space(dir.path().join("not/created/yet")).unwrap();
+
+        assert_eq!(space.path, dir.path().to_string_lossy());
+        assert!(space.total_bytes > 0);
+        assert!(space.available_bytes <= space.total_bytes);
+        assert!(space.used_bytes <= space.total_bytes);
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        assert!(space.fs_type.is_some() && space.mount_point.is_some());
+
+        assert_eq!(
+            get_available_space(dir.path()).unwrap(),
+            space.available_bytes
+        );
+        assert!(require_space(dir.path(), u64::MAX / 2).is_err());
+    }
+
+    #[test]
+    fn test_copy_verified_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("model.ckpt");
+        fs::write(&source, vec![7u8; 10_000]).unwrap();
+
+        // An I/O error (here: a directory in the way) keeps the `.partial` for a retry
+        let blocked = dir.path().join("blocked.ckpt");
+        fs::create_dir_all(blocked.join("inside")).unwrap();
+        let error = copy_verified(&source, &blocked, true, None, &mut |_| {}).unwrap_err();
+        assert_eq!(error.code, ErrorCode::FileCopy);
+        assert!(partial_path(&blocked).exists());
+
+        // A cancelled copy is abandoned
+        let destination = dir.path().join("copy.ckpt");
+        let cancel = AtomicBool::new(true);
+        assert!(copy_verified(&source, &destination, false, Some(&cancel), &mut |_| {}).is_err());
+        assert!(!partial_path(&destination).exists());
+
+        copy_verified(&source, &destination, false, None, &mut |_| {}).unwrap();
+        assert_eq!(fs::read(&destination).unwrap().len(), 10_000);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::model_scan::FileClass;
+use serde::{Deserialize, Serialize};
+
+/// Which files in a `Models` directory are models, which travel with a model, and
+/// which are leftovers. Every scan, prune and sync goes through one policy.
+/// Configured from settings.json (`model_extensions`, `companion_suffixes`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtensionPolicy {
+    /// Model file extensions, without the dot
+    pub model_extensions: Vec<String>,
+    /// Appended to a model's filename for files that belong to it, e.g.
+    /// `flux_1_dev_q8p.ckpt-tensordata` holds the weights of `flux_1_dev_q8p.ckpt`
+    pub companion_suffixes: Vec<String>,
+    /// Extensions left behind by interrupted downloads and copies
+    pub partial_extensions: Vec<String>,
+}
+
+impl Default for ExtensionPolicy {
+    fn default() -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            model_extensions: strings(&["ckpt", "safetensors", "pth", "pt"]),
+            companion_suffixes: strings(&["-tensordata"]),
+            partial_extensions: strings(&[
+                "part",
+                "partial",
+                "tmp",
+                "temp",
+                "download",
+                "crdownload",
+            ]),
+        }
+    }
+}
+
+impl ExtensionPolicy {
+    /// Classify a file in a `Models` directory by its name
+    pub fn classify(&self, filename: &str) -> FileClass {
+        let lower = filename.to_lowercase();
+
+        if lower.starts_with('.') || lower.ends_with('~') {
+            return FileClass::Partial;
+        }
+
+        let extension = match lower.rsplit_once('.') {
+            Some((_, ext)) => ext,
+            None => return FileClass::Other,
+        };
+
+        if contains(&self.partial_extensions, extension) {
+            return FileClass::Partial;
+        }
+        if self.companion_of(filename).is_some() {
+            return FileClass::Companion;
+        }
+
+        match extension {
+            "json" => FileClass::SidecarJson,
+            ext if !contains(&self.model_extensions, ext) => FileClass::Other,
+            "ckpt" => FileClass::Ckpt,
+            "safetensors" => FileClass::Safetensors,
+            _ => FileClass::OtherModel,
+        }
+    }
+
+    pub fn is_model(&self, filename: &str) -> bool {
+        self.classify(filename).is_model()
+    }
+
+    /// The model filename a companion file belongs to
+    pub fn companion_of<'a>(&self, filename: &'a str) -> Option<&'a str> {
+        self.companion_suffixes.iter().find_map(|suffix| {
+            let split = filename.len().checked_sub(suffix.len())?;
+            let (model, tail) = (filename.get(..split)?, filename.get(split..)?);
+            let is_model = model
+                .rsplit_once('.')
+                .map(|(_, ext)| contains(&self.model_extensions, &ext.to_lowercase()))
+                .unwrap_or(false);
+            (tail.eq_ignore_ascii_case(suffix) && is_model).then_some(model)
+        })
+    }
+
+    /// Names a companion of `model_filename` would have (they may not exist)
+    pub fn companion_names(&self, model_filename: &str) -> Vec<String> {
+        self.companion_suffixes
+            .iter()
+            .map(|suffix| format!("{}{}", model_filename, suffix))
+            .collect()
+    }
+}
+
+fn contains(list: &[String], extension: &str) -> bool {
+    list.iter().any(|e| e.eq_ignore_ascii_case(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_companions() {
+        let policy = ExtensionPolicy::default();
+
+        assert_eq!(
+            policy.classify("flux_1_dev_q8p.ckpt-tensordata"),
+            FileClass::Companion
+        );
+        assert_eq!(
+            policy.companion_of("flux_1_dev_q8p.ckpt-tensordata"),
+            Some("flux_1_dev_q8p.ckpt")
+        );
+        assert_eq!(policy.companion_of("notes-tensordata"), None);
+        assert_eq!(
+            policy.classify("flux.ckpt-tensordata.partial"),
+            FileClass::Partial
+        );
+        assert_eq!(
+            policy.companion_names("flux.ckpt"),
+            vec!["flux.ckpt-tensordata"]
+        );
+    }
+
+    #[test]
+    fn test_configured_extensions() {
+        let mut policy = ExtensionPolicy::default();
+        assert_eq!(policy.classify("upscaler.pth"), FileClass::OtherModel);
+        assert!(policy.is_model("lora.PT"));
+
+        policy.model_extensions = vec!["ckpt".to_string(), "gguf".to_string()];
+        assert!(policy.is_model("t5.gguf"));
+        assert!(!policy.is_model("upscaler.pth"));
+        assert_eq!(policy.classify("style.safetensors"), FileClass::Other);
+
+        // Missing keys fall back to the defaults
+        let policy: ExtensionPolicy =
+            serde_json::from_str(r#"{"companion_suffixes": ["-tensordata", "-wal"]}"#).unwrap();
+        assert!(policy.is_model("style.safetensors"));
+        assert_eq!(policy.classify("flux.ckpt-wal"), FileClass::Companion);
+    }
+}
@@ -0,0 +1,497 @@
+use crate::copy_engine::CopyProgress;
+use crate::error::{AppError, AppResult, ErrorCode};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Tauri event carrying `JobInfo` payloads whenever a job changes
+pub const JOB_EVENT: &str = "job-update";
+
+pub type JobId = u64;
+
+/// Finished jobs kept for `list_jobs` / `get_job`; older ones are dropped on the next submit
+pub const DEFAULT_MAX_FINISHED: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// Status of one background job, as returned by `list_jobs` / `get_job`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    /// e.g. "copy", "prune"
+    pub kind: String,
+    pub description: String,
+    /// Jobs with the same volume run one at a time, in submission order
+    pub volume: String,
+    pub status: JobStatus,
+    pub progress: Option<CopyProgress>,
+    pub result: Option<Value>,
+    pub error: Option<AppError>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+/// Handed to a running job so it can report progress and notice cancellation
+pub struct JobContext {
+    id: JobId,
+    cancel: Arc<AtomicBool>,
+    inner: Arc<Inner>,
+}
+
+impl JobContext {
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Set by `cancel_job`; long-running work should check it between steps
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn report_progress(&self, progress: &CopyProgress) {
+        self.inner.update(self.id, |info| {
+            info.progress = Some(progress.clone());
+        });
+    }
+}
+
+type Work = Box<dyn FnOnce(&JobContext) -> AppResult<Value> + Send>;
+type Listener = Box<dyn Fn(&JobInfo) + Send + Sync>;
+
+struct JobEntry {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+struct QueuedJob {
+    id: JobId,
+    work: Work,
+}
+
+struct Inner {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<JobId, JobEntry>>,
+    /// One worker thread per volume, fed through its channel
+    queues: Mutex<HashMap<String, Sender<QueuedJob>>>,
+    listener: Mutex<Option<Listener>>,
+    max_finished: usize,
+}
+
+impl Inner {
+    /// Apply `change` to a job and notify the listener
+    fn update(&self, id: JobId, change: impl FnOnce(&mut JobInfo)) {
+        let info = match self.jobs.lock() {
+            Ok(mut jobs) => match jobs.get_mut(&id) {
+                Some(entry) => {
+                    change(&mut entry.info);
+                    entry.info.clone()
+                }
+                None => return,
+            },
+            Err(_) => return,
+        };
+
+        if let Ok(listener) = self.listener.lock() {
+            if let Some(listener) = listener.as_ref() {
+                listener(&info);
+            }
+        }
+    }
+
+    fn cancel_flag(&self, id: JobId) -> Option<Arc<AtomicBool>> {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.get(&id).map(|e| e.cancel.clone()))
+    }
+
+    fn status(&self, id: JobId) -> Option<JobStatus> {
+        self.jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.get(&id).map(|e| e.info.status))
+    }
+
+    /// Drop the oldest finished jobs beyond `max_finished`
+    fn prune_finished(&self) {
+        let Ok(mut jobs) = self.jobs.lock() else {
+            return;
+        };
+        let finished: Vec<JobId> = jobs
+            .iter()
+            .filter(|(_, e)| e.info.status.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        let excess = finished.len().saturating_sub(self.max_finished);
+        for id in &finished[..excess] {
+            jobs.remove(id);
+        }
+    }
+}
+
+// ################################################################################
+// # Job Manager
+/// Background job queue held in the Tauri app state. Jobs get an ID, run on a
+/// worker thread per destination volume (so two copies never compete for one
+/// disk), and can be listed, inspected and cancelled.
+pub struct JobManager {
+    inner: Arc<Inner>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::with_history(DEFAULT_MAX_FINISHED)
+    }
+
+    /// Keep at most `max_finished` completed, failed or cancelled jobs
+    pub fn with_history(max_finished: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                next_id: AtomicU64::new(1),
+                jobs: Mutex::new(BTreeMap::new()),
+                queues: Mutex::new(HashMap::new()),
+                listener: Mutex::new(None),
+                max_finished,
+            }),
+        }
+    }
+
+    /// Called with the new state every time a job changes (status or progress)
+    pub fn set_listener<F: Fn(&JobInfo) + Send + Sync + 'static>(&self, listener: F) {
+        if let Ok(mut slot) = self.inner.listener.lock() {
+            *slot = Some(Box::new(listener));
+        }
+    }
+
+    /// Queue `work` behind any other jobs on `volume` and return its ID
+    pub fn submit<F>(&self, kind: &str, description: &str, volume: &str, work: F) -> JobId
+    where
+        F: FnOnce(&JobContext) -> AppResult<Value> + Send + 'static,
+    {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let info = JobInfo {
+            id,
+            kind: kind.to_string(),
+            description: description.to_string(),
+            volume: volume.to_string(),
+            status: JobStatus::Queued,
+            progress: None,
+            result: None,
+            error: None,
+            created_at: now(),
+            started_at: None,
+            finished_at: None,
+        };
+
+        self.inner.prune_finished();
+        if let Ok(mut jobs) = self.inner.jobs.lock() {
+            jobs.insert(
+                id,
+                JobEntry {
+                    info,
+                    cancel: Arc::new(AtomicBool::new(false)),
+                },
+            );
+        }
+        self.inner.update(id, |_| {});
+
+        let job = QueuedJob {
+            id,
+            work: Box::new(work),
+        };
+
+        let mut queues = match self.inner.queues.lock() {
+            Ok(queues) => queues,
+            Err(_) => {
+                self.inner.update(id, |info| {
+                    info.status = JobStatus::Failed;
+                    info.error = Some(AppError::new(ErrorCode::Unknown, "Job queue unavailable"));
+                });
+                return id;
+            }
+        };
+
+        // Reuse the volume's worker; start one if there is none (or it has gone)
+        let job = match queues.get(volume) {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => return id,
+                Err(mpsc::SendError(job)) => job,
+            },
+            None => job,
+        };
+
+        let (sender, receiver) = mpsc::channel::<QueuedJob>();
+        let inner = self.inner.clone();
+        thread::spawn(move || {
+            for job in receiver {
+                run_job(&inner, job);
+            }
+        });
+        let _ = sender.send(job);
+        queues.insert(volume.to_string(), sender);
+
+        id
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.inner
+            .jobs
+            .lock()
+            .map(|jobs| jobs.values().map(|e| e.info.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, id: JobId) -> Option<JobInfo> {
+        self.inner
+            .jobs
+            .lock()
+            .ok()
+            .and_then(|jobs| jobs.get(&id).map(|e| e.info.clone()))
+    }
+
+    /// Cancel a job. A queued job never starts; a running one is asked to stop and
+    /// cleans up after itself. Returns false if the job is unknown or already finished.
+    pub fn cancel(&self, id: JobId) -> bool {
+        let status = match self.inner.status(id) {
+            Some(status) if !status.is_finished() => status,
+            _ => return false,
+        };
+
+        if let Some(flag) = self.inner.cancel_flag(id) {
+            flag.store(true, Ordering::Relaxed);
+        }
+
+        if status == JobStatus::Queued {
+            self.inner.update(id, |info| {
+                info.status = JobStatus::Cancelled;
+                info.finished_at = Some(now());
+            });
+        }
+        true
+    }
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run_job(inner: &Arc<Inner>, job: QueuedJob) {
+    let cancel = match inner.cancel_flag(job.id) {
+        Some(cancel) => cancel,
+        None => return,
+    };
+
+    // Cancelled while waiting in the queue
+    if cancel.load(Ordering::Relaxed) {
+        return;
+    }
+
+    inner.update(job.id, |info| {
+        info.status = JobStatus::Running;
+        info.started_at = Some(now());
+    });
+
+    let context = JobContext {
+        id: job.id,
+        cancel: cancel.clone(),
+        inner: inner.clone(),
+    };
+    // A panic fails the job instead of taking the volume's worker (and queue) with it
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| (job.work)(&context)))
+        .unwrap_or_else(|payload| Err(panic_error(payload.as_ref())));
+    let cancelled = cancel.load(Ordering::Relaxed);
+
+    inner.update(job.id, |info| {
+        info.finished_at = Some(now());
+        match outcome {
+            // Work that stops early on cancel may still return a partial result
+            Ok(result) => {
+                info.status = match cancelled {
+                    true => JobStatus::Cancelled,
+                    false => JobStatus::Completed,
+                };
+                info.result = Some(result);
+            }
+            Err(_) if cancelled => info.status = JobStatus::Cancelled,
+            Err(error) => {
+                info.status = JobStatus::Failed;
+                info.error = Some(error);
+            }
+        }
+    });
+}
+
+fn panic_error(payload: &(dyn std::any::Any + Send)) -> AppError {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    AppError::new(ErrorCode::Unknown, format!("Job panicked: {}", message))
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Identify the volume holding `path` (or its nearest existing ancestor), so
+/// jobs writing to the same disk can be queued behind each other
+pub fn volume_key<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref();
+    let existing = path.ancestors().find(|p| p.exists()).unwrap_or(path);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if let Ok(metadata) = std::fs::metadata(existing) {
+            return format!("dev:{}", metadata.dev());
+        }
+    }
+
+    existing
+        .components()
+        .next()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    fn wait_finished(jobs: &JobManager, id: JobId) -> JobInfo {
+        for _ in 0..500 {
+            let info = jobs.get(id).unwrap();
+            if info.status.is_finished() {
+                return info;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("job {} did not finish", id);
+    }
+
+    /// A job that blocks until released, so tests can control ordering
+    fn gated(jobs: &JobManager, volume: &str) -> (JobId, Sender<()>) {
+        let (release, gate): (Sender<()>, Receiver<()>) = mpsc::channel();
+        let id = jobs.submit("test", "gated", volume, move |ctx| {
+            while gate.recv_timeout(Duration::from_millis(5)).is_err() {
+                if ctx.is_cancelled() {
+                    return Err(AppError::new(ErrorCode::Unknown, "cancelled"));
+                }
+            }
+            Ok(Value::Null)
+        });
+        (id, release)
+    }
+
+    #[test]
+    fn test_jobs_run_and_report() {
+        let jobs = JobManager::new();
+        let ok = jobs.submit("test", "ok", "a", |_| Ok(Value::from(42)));
+        let failed = jobs.submit("test", "fails", "a", |_| {
+            Err(AppError::from(ErrorCode::FileCopy))
+        });
+
+        let info = wait_finished(&jobs, ok);
+        assert_eq!(info.status, JobStatus::Completed);
+        assert_eq!(info.result, Some(Value::from(42)));
+        assert!(info.started_at.is_some());
+
+        let info = wait_finished(&jobs, failed);
+        assert_eq!(info.status, JobStatus::Failed);
+        assert_eq!(info.error.unwrap().code, ErrorCode::FileCopy);
+
+        assert_eq!(jobs.list().len(), 2);
+        assert!(jobs.get(999).is_none());
+    }
+
+    #[test]
+    fn test_panic_fails_job_and_history_is_capped() {
+        let jobs = JobManager::with_history(2);
+        let panicked = jobs.submit("test", "panics", "disk", |_| panic!("boom"));
+        let after = jobs.submit("test", "after", "disk", |_| Ok(Value::Null));
+
+        let info = wait_finished(&jobs, panicked);
+        assert_eq!(info.status, JobStatus::Failed);
+        assert!(info.error.unwrap().message.contains("boom"));
+        // The volume's worker survived
+        assert_eq!(wait_finished(&jobs, after).status, JobStatus::Completed);
+
+        let third = jobs.submit("test", "third", "disk", |_| Ok(Value::Null));
+        wait_finished(&jobs, third);
+        let latest = jobs.submit("test", "latest", "disk", |_| Ok(Value::Null));
+        wait_finished(&jobs, latest);
+        let ids: Vec<JobId> = jobs.list().iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![after, third, latest]);
+        assert!(jobs.get(panicked).is_none());
+    }
+
+    #[test]
+    fn test_same_volume_is_serialized() {
+        let jobs = JobManager::new();
+        let (first, release) = gated(&jobs, "disk");
+        let second = jobs.submit("test", "second", "disk", |_| Ok(Value::Null));
+        let other = jobs.submit("test", "other disk", "other", |_| Ok(Value::Null));
+
+        // A different volume isn't held up
+        assert_eq!(wait_finished(&jobs, other).status, JobStatus::Completed);
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(jobs.get(second).unwrap().status, JobStatus::Queued);
+
+        release.send(()).unwrap();
+        assert_eq!(wait_finished(&jobs, first).status, JobStatus::Completed);
+        assert_eq!(wait_finished(&jobs, second).status, JobStatus::Completed);
+    }
+
+    #[test]
+    fn test_cancel_queued_and_running() {
+        let jobs = JobManager::new();
+        let (running, _release) = gated(&jobs, "disk");
+        let queued = jobs.submit("test", "queued", "disk", |_| Ok(Value::Null));
+
+        assert!(jobs.cancel(queued));
+        assert_eq!(jobs.get(queued).unwrap().status, JobStatus::Cancelled);
+
+        assert!(jobs.cancel(running));
+        assert_eq!(wait_finished(&jobs, running).status, JobStatus::Cancelled);
+        assert!(!jobs.cancel(running));
+
+        // The queued job never ran
+        thread::sleep(Duration::from_millis(30));
+        let info = jobs.get(queued).unwrap();
+        assert!(info.started_at.is_none());
+    }
+}
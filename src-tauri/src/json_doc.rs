@@ -0,0 +1,692 @@
+//! Format-preserving editor for JSON documents whose top level is an array of
+//! objects - the shape of every Draw Things registry (custom.json etc.).
+//!
+//! The original text is kept verbatim and edits are spliced into it, so unknown
+//! keys, key order, whitespace and number formatting all survive a round trip
+//! and a saved file differs from the original only where it was edited.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// An array-of-objects JSON document that can be edited in place
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonArrayDoc {
+    /// Text up to the first element (or up to and including `[` when empty)
+    prefix: String,
+    /// Raw text of each array element
+    elements: Vec<String>,
+    /// Raw text between consecutive elements, comma included
+    separators: Vec<String>,
+    /// Text after the last element
+    suffix: String,
+}
+
+/// Location of one `"key": value` member inside an object's text
+#[derive(Debug, Clone)]
+struct Member {
+    key: String,
+    key_start: usize,
+    key_end: usize,
+    value_start: usize,
+    value_end: usize,
+}
+
+/// Location of an object's braces and members inside some text
+#[derive(Debug, Clone)]
+struct ObjectLayout {
+    open: usize,
+    close: usize,
+    members: Vec<Member>,
+}
+
+impl JsonArrayDoc {
+    /// Parse a document. The text must be valid JSON with an array at the top level.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let parsed: Value =
+            serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+        if !parsed.is_array() {
+            return Err("Invalid JSON structure: expected an array".to_string());
+        }
+
+        let bytes = text.as_bytes();
+        let open = skip_ws(bytes, 0);
+        let mut pos = skip_ws(bytes, open + 1);
+
+        if bytes[pos] == b']' {
+            return Ok(Self {
+                prefix: text[..open + 1].to_string(),
+                elements: Vec::new(),
+                separators: Vec::new(),
+                suffix: text[open + 1..].to_string(),
+            });
+        }
+
+        let prefix = text[..pos].to_string();
+        let mut elements = Vec::new();
+        let mut separators = Vec::new();
+
+        loop {
+            let end = value_end(bytes, pos)?;
+            elements.push(text[pos..end].to_string());
+
+            let after = skip_ws(bytes, end);
+            if bytes[after] == b',' {
+                let next = skip_ws(bytes, after + 1);
+                separators.push(text[end..next].to_string());
+                pos = next;
+            } else {
+                return Ok(Self {
+                    prefix,
+                    elements,
+                    separators,
+                    suffix: text[end..].to_string(),
+                });
+            }
+        }
+    }
+
+    /// An empty document, `[]`
+    pub fn empty() -> Self {
+        Self {
+            prefix: "[".to_string(),
+            elements: Vec::new(),
+            separators: Vec::new(),
+            suffix: "]".to_string(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Raw text of an element, exactly as it appears in the document
+    pub fn raw(&self, index: usize) -> Option<&str> {
+        self.elements.get(index).map(|s| s.as_str())
+    }
+
+    /// Parsed value of an element
+    pub fn get(&self, index: usize) -> Option<Value> {
+        self.elements
+            .get(index)
+            .and_then(|raw| serde_json::from_str(raw).ok())
+    }
+
+    /// Deserialize an element into a typed struct
+    pub fn entry<T: DeserializeOwned>(&self, index: usize) -> Result<T, String> {
+        let raw = self
+            .elements
+            .get(index)
+            .ok_or_else(|| format!("No entry at index {}", index))?;
+        serde_json::from_str(raw).map_err(|e| format!("Entry {}: {}", index, e))
+    }
+
+    /// Index of the first object whose string member `key` equals `value`
+    pub fn position_where(&self, key: &str, value: &str) -> Option<usize> {
+        self.elements.iter().position(|raw| {
+            serde_json::from_str::<Value>(raw)
+                .ok()
+                .and_then(|v| v.get(key).and_then(|s| s.as_str()).map(|s| s == value))
+                .unwrap_or(false)
+        })
+    }
+
+    /// Move an element to a new position. Separators keep their places, so only
+    /// the moved elements change in the output.
+    pub fn move_element(&mut self, from: usize, to: usize) -> Result<(), String> {
+        if from >= self.len() || to >= self.len() {
+            return Err(format!(
+                "Position out of range: {} -> {} (length {})",
+                from,
+                to,
+                self.len()
+            ));
+        }
+        let element = self.elements.remove(from);
+        self.elements.insert(to, element);
+        Ok(())
+    }
+
+    /// Remove an element along with one neighbouring separator
+    pub fn remove(&mut self, index: usize) -> Result<Value, String> {
+        if index >= self.len() {
+            return Err(format!("No entry at index {}", index));
+        }
+        let value = self.get(index).unwrap_or(Value::Null);
+        self.elements.remove(index);
+
+        if !self.separators.is_empty() {
+            let sep = if index < self.separators.len() {
+                index
+            } else {
+                index - 1
+            };
+            self.separators.remove(sep);
+        }
+
+        if self.elements.is_empty() {
+            // Collapse to `[` + closing text without the old element indentation
+            let trimmed = self.prefix.trim_end().to_string();
+            self.prefix = trimmed;
+            self.suffix = self.suffix.trim_start().to_string();
+        }
+
+        Ok(value)
+    }
+
+    /// Append an element, formatted like the existing elements
+    pub fn push(&mut self, value: &Value) {
+        let style = self.style();
+        let rendered = render(value, &style, &style.element_indent);
+
+        if self.elements.is_empty() {
+            if style.pretty {
+                self.prefix = format!("{}\n{}", self.prefix.trim_end(), style.element_indent);
+                self.suffix = format!("\n{}", self.suffix.trim_start());
+            }
+        } else {
+            let separator = self
+                .separators
+                .last()
+                .cloned()
+                .unwrap_or_else(|| match style.pretty {
+                    true => format!(",\n{}", style.element_indent),
+                    false => ",".to_string(),
+                });
+            self.separators.push(separator);
+        }
+
+        self.elements.push(rendered);
+    }
+
+    /// Set `path` (e.g. `["weight", "value"]`) in the element at `index`.
+    /// Existing values are replaced in place; missing keys are appended to their object.
+    pub fn set(&mut self, index: usize, path: &[&str], value: &Value) -> Result<(), String> {
+        if path.is_empty() {
+            return Err("Empty key path".to_string());
+        }
+        let style = self.style();
+        let element = self
+            .elements
+            .get_mut(index)
+            .ok_or_else(|| format!("No entry at index {}", index))?;
+
+        let mut base = 0;
+        let mut layout = object_layout(element, 0)?;
+
+        for (depth, key) in path.iter().enumerate() {
+            let is_last = depth == path.len() - 1;
+
+            match layout.members.iter().find(|m| m.key == *key).cloned() {
+                Some(member) if is_last => {
+                    let indent = line_indent(element, base + member.key_start);
+                    let rendered = render(value, &style, &indent);
+                    element.replace_range(
+                        base + member.value_start..base + member.value_end,
+                        &rendered,
+                    );
+                    return Ok(());
+                }
+                Some(member) => {
+                    // Descend; offsets in `layout` are relative to `base`
+                    base += member.value_start;
+                    layout = object_layout(&element[base..], 0)
+                        .map_err(|_| format!("'{}' is not an object", key))?;
+                }
+                None => {
+                    // Build the rest of the path as nested objects and append it
+                    let mut nested = value.clone();
+                    for k in path[depth + 1..].iter().rev() {
+                        let mut map = serde_json::Map::new();
+                        map.insert(k.to_string(), nested);
+                        nested = Value::Object(map);
+                    }
+                    let (offset, insertion) =
+                        member_insertion(element, base, &layout, key, &nested, &style);
+                    element.insert_str(base + offset, &insertion);
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove `path` from the element at `index`. Returns false if it wasn't there.
+    pub fn remove_key(&mut self, index: usize, path: &[&str]) -> Result<bool, String> {
+        let element = self
+            .elements
+            .get_mut(index)
+            .ok_or_else(|| format!("No entry at index {}", index))?;
+
+        let mut base = 0;
+        let mut layout = object_layout(element, 0)?;
+
+        for (depth, key) in path.iter().enumerate() {
+            let position = match layout.members.iter().position(|m| m.key == *key) {
+                Some(position) => position,
+                None => return Ok(false),
+            };
+            let member = layout.members[position].clone();
+
+            if depth < path.len() - 1 {
+                base += member.value_start;
+                layout = object_layout(&element[base..], 0)
+                    .map_err(|_| format!("'{}' is not an object", key))?;
+                continue;
+            }
+
+            // Take the member plus the comma on one side of it
+            let (start, end) = if position + 1 < layout.members.len() {
+                (member.key_start, layout.members[position + 1].key_start)
+            } else if position > 0 {
+                (layout.members[position - 1].value_end, member.value_end)
+            } else {
+                (member.key_start, member.value_end)
+            };
+            element.replace_range(base + start..base + end, "");
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// Replace the whole element at `index`
+    pub fn replace(&mut self, index: usize, value: &Value) -> Result<(), String> {
+        let style = self.style();
+        let element = self
+            .elements
+            .get_mut(index)
+            .ok_or_else(|| format!("No entry at index {}", index))?;
+        *element = render(value, &style, &style.element_indent);
+        Ok(())
+    }
+
+    /// Replace every element with `values`. Elements equal to an existing one keep
+    /// their original text, and an edited element (matched by `file`, else by position)
+    /// is patched key by key, so rewriting a whole list only changes what really changed.
+    pub fn assign(&mut self, values: &[Value]) {
+        let style = self.style();
+        let existing: Vec<(String, Option<Value>)> = self
+            .elements
+            .drain(..)
+            .map(|raw| {
+                let parsed = serde_json::from_str(&raw).ok();
+                (raw, parsed)
+            })
+            .collect();
+        let mut used = vec![false; existing.len()];
+        let mut bases: Vec<Option<usize>> = vec![None; values.len()];
+
+        let file_of = |value: &Value| value.get("file").and_then(|f| f.as_str()).map(String::from);
+        // Unchanged elements first, then the same `file`, then the same position
+        for pass in 0..3 {
+            for (i, value) in values.iter().enumerate() {
+                if bases[i].is_some() {
+                    continue;
+                }
+                let found = existing.iter().enumerate().position(|(j, (_, old))| {
+                    let Some(old) = old.as_ref().filter(|_| !used[j]) else {
+                        return false;
+                    };
+                    match pass {
+                        0 => old == value,
+                        1 => file_of(value).is_some() && file_of(value) == file_of(old),
+                        _ => i == j && value.is_object() && old.is_object(),
+                    }
+                });
+                if let Some(j) = found {
+                    used[j] = true;
+                    bases[i] = Some(j);
+                }
+            }
+        }
+
+        let elements: Vec<String> = values
+            .iter()
+            .zip(&bases)
+            .map(|(value, base)| match base {
+                Some(j) => existing[*j].0.clone(),
+                None => render(value, &style, &style.element_indent),
+            })
+            .collect();
+
+        if elements.is_empty() {
+            self.prefix = self.prefix.trim_end().to_string();
+            self.suffix = self.suffix.trim_start().to_string();
+            self.separators.clear();
+            return;
+        }
+
+        let default_separator =
+            self.separators
+                .last()
+                .cloned()
+                .unwrap_or_else(|| match style.pretty {
+                    true => format!(",\n{}", style.element_indent),
+                    false => ",".to_string(),
+                });
+        self.separators
+            .resize(elements.len() - 1, default_separator);
+        self.elements = elements;
+
+        for (index, (value, base)) in values.iter().zip(&bases).enumerate() {
+            let Some(old) = base.and_then(|j| existing[j].1.as_ref()) else {
+                continue;
+            };
+            if old != value && self.patch(index, &[], old, value).is_err() {
+                self.elements[index] = render(value, &style, &style.element_indent);
+            }
+        }
+    }
+
+    /// Turn `old` (the parsed element, or the value at `path` in it) into `new` with
+    /// `set` and `remove_key`, leaving unchanged members as they are
+    fn patch(
+        &mut self,
+        index: usize,
+        path: &[&str],
+        old: &Value,
+        new: &Value,
+    ) -> Result<(), String> {
+        match (old, new) {
+            (Value::Object(old), Value::Object(new)) => {
+                for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                    let mut key_path = path.to_vec();
+                    key_path.push(key);
+                    self.remove_key(index, &key_path)?;
+                }
+                for (key, value) in new {
+                    let mut key_path = path.to_vec();
+                    key_path.push(key);
+                    match old.get(key) {
+                        Some(old) if old == value => {}
+                        Some(old) => self.patch(index, &key_path, old, value)?,
+                        None => self.set(index, &key_path, value)?,
+                    }
+                }
+                Ok(())
+            }
+            _ if path.is_empty() => Err("Not an object".to_string()),
+            _ => self.set(index, path, new),
+        }
+    }
+
+    /// Work out how new elements should be formatted from the existing text
+    fn style(&self) -> Style {
+        let sample = self.elements.iter().find(|e| e.contains('\n'));
+
+        // Whitespace on the line before an element, e.g. the "  " in ",\n  "
+        let lead_in = self.separators.first().unwrap_or(&self.prefix);
+        let element_indent: String = match lead_in.rfind('\n') {
+            Some(i) => lead_in[i + 1..]
+                .chars()
+                .filter(|c| *c == ' ' || *c == '\t')
+                .collect(),
+            None => String::new(),
+        };
+
+        let pretty = sample.is_some();
+
+        // Indent unit: member indentation minus element indentation
+        let indent_unit = sample
+            .and_then(|e| e.lines().nth(1))
+            .map(|line| {
+                let ws: String = line
+                    .chars()
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .collect();
+                ws.strip_prefix(element_indent.as_str())
+                    .map(|s| s.to_string())
+                    .unwrap_or(ws)
+            })
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "  ".to_string());
+
+        let colon_space = self
+            .elements
+            .first()
+            .and_then(|e| {
+                let layout = object_layout(e, 0).ok()?;
+                let member = layout.members.first()?;
+                Some(e[member.key_end..member.value_start].contains(' '))
+            })
+            .unwrap_or(pretty);
+
+        Style {
+            pretty,
+            indent_unit,
+            element_indent,
+            colon_space,
+        }
+    }
+}
+
+impl std::fmt::Display for JsonArrayDoc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.prefix)?;
+        for (i, element) in self.elements.iter().enumerate() {
+            if i > 0 {
+                f.write_str(&self.separators[i - 1])?;
+            }
+            f.write_str(element)?;
+        }
+        f.write_str(&self.suffix)
+    }
+}
+
+/// Formatting conventions detected from a document
+#[derive(Debug, Clone)]
+struct Style {
+    pretty: bool,
+    indent_unit: String,
+    element_indent: String,
+    colon_space: bool,
+}
+
+/// Render a value as JSON text. Objects and arrays are laid out like the rest of
+/// the document; `indent` is the indentation of the line the value starts on.
+fn render(value: &Value, style: &Style, indent: &str) -> String {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let colon = if style.colon_space { ": " } else { ":" };
+            let inner = format!("{}{}", indent, style.indent_unit);
+            let members: Vec<String> = map
+                .iter()
+                .map(|(k, v)| {
+                    let key = serde_json::to_string(k).unwrap_or_default();
+                    format!("{}{}{}", key, colon, render(v, style, &inner))
+                })
+                .collect();
+            if style.pretty {
+                format!(
+                    "{{\n{}{}\n{}}}",
+                    inner,
+                    members.join(&format!(",\n{}", inner)),
+                    indent
+                )
+            } else {
+                format!("{{{}}}", members.join(","))
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            let inner = format!("{}{}", indent, style.indent_unit);
+            let items: Vec<String> = items.iter().map(|v| render(v, style, &inner)).collect();
+            if style.pretty {
+                format!(
+                    "[\n{}{}\n{}]",
+                    inner,
+                    items.join(&format!(",\n{}", inner)),
+                    indent
+                )
+            } else {
+                format!("[{}]", items.join(","))
+            }
+        }
+        _ => serde_json::to_string(value).unwrap_or_else(|_| "null".to_string()),
+    }
+}
+
+/// Offset (relative to `base`) and text to insert to add `"key": value` to an object
+fn member_insertion(
+    text: &str,
+    base: usize,
+    layout: &ObjectLayout,
+    key: &str,
+    value: &Value,
+    style: &Style,
+) -> (usize, String) {
+    let key_json = serde_json::to_string(key).unwrap_or_default();
+
+    match layout.members.last() {
+        Some(last) => {
+            // Copy the separator and colon spacing already used in this object
+            let separator = match layout.members.len() {
+                1 => format!(",{}", &text[base + layout.open + 1..base + last.key_start]),
+                n => {
+                    let prev = &layout.members[n - 2];
+                    text[base + prev.value_end..base + last.key_start].to_string()
+                }
+            };
+            let colon = &text[base + last.key_end..base + last.value_start];
+            let indent = line_indent(text, base + last.key_start);
+            let rendered = render(value, style, &indent);
+
+            // Goes straight after the last value, so any whitespace before `}` stays put
+            (
+                last.value_end,
+                format!("{}{}{}{}", separator, key_json, colon, rendered),
+            )
+        }
+        None => {
+            let colon = if style.colon_space { ": " } else { ":" };
+            (
+                layout.close,
+                format!("{}{}{}", key_json, colon, render(value, style, "")),
+            )
+        }
+    }
+}
+
+/// Leading whitespace of the line containing byte offset `pos`
+fn line_indent(text: &str, pos: usize) -> String {
+    let line_start = text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    text[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect()
+}
+
+fn skip_ws(bytes: &[u8], mut pos: usize) -> usize {
+    while pos < bytes.len() && matches!(bytes[pos], b' ' | b'\t' | b'\n' | b'\r') {
+        pos += 1;
+    }
+    pos
+}
+
+/// End offset (exclusive) of the JSON value starting at `pos`. Input is known-valid JSON.
+fn value_end(bytes: &[u8], pos: usize) -> Result<usize, String> {
+    match bytes.get(pos) {
+        Some(b'"') => string_end(bytes, pos),
+        Some(b'{') | Some(b'[') => {
+            let mut depth = 0usize;
+            let mut i = pos;
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        i = string_end(bytes, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            Err("Unterminated object or array".to_string())
+        }
+        Some(_) => {
+            let mut i = pos;
+            while i < bytes.len()
+                && !matches!(bytes[i], b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r')
+            {
+                i += 1;
+            }
+            Ok(i)
+        }
+        None => Err("Unexpected end of input".to_string()),
+    }
+}
+
+/// End offset (exclusive) of the string starting at `pos`
+fn string_end(bytes: &[u8], pos: usize) -> Result<usize, String> {
+    let mut i = pos + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+    Err("Unterminated string".to_string())
+}
+
+/// Locate the members of the object starting at (or after whitespace from) `pos`
+fn object_layout(text: &str, pos: usize) -> Result<ObjectLayout, String> {
+    let bytes = text.as_bytes();
+    let open = skip_ws(bytes, pos);
+    if bytes.get(open) != Some(&b'{') {
+        return Err("Expected an object".to_string());
+    }
+
+    let mut members = Vec::new();
+    let mut i = skip_ws(bytes, open + 1);
+
+    if bytes.get(i) == Some(&b'}') {
+        return Ok(ObjectLayout {
+            open,
+            close: i,
+            members,
+        });
+    }
+
+    loop {
+        let key_start = i;
+        let key_end = string_end(bytes, key_start)?;
+        let key: String = serde_json::from_str(&text[key_start..key_end])
+            .map_err(|e| format!("Invalid key: {}", e))?;
+
+        let colon = skip_ws(bytes, key_end);
+        let value_start = skip_ws(bytes, colon + 1);
+        let value_end = value_end(bytes, value_start)?;
+
+        members.push(Member {
+            key,
+            key_start,
+            key_end,
+            value_start,
+            value_end,
+        });
+
+        let after = skip_ws(bytes, value_end);
+        match bytes.get(after) {
+            Some(b',') => i = skip_ws(bytes, after + 1),
+            Some(b'}') => {
+                return Ok(ObjectLayout {
+                    open,
+                    close: after,
+                    members,
+                })
+            }
+            _ => return Err("Malformed object".to_string()),
+        }
+    }
+}
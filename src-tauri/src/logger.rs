@@ -0,0 +1,267 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Tauri event carrying each `LogEvent` as it is written
+pub const LOG_EVENT: &str = "log-event";
+
+/// `<DTC_APP_DIR>/z_logs`, shared with `log_json_file` snapshots
+pub const LOG_DIR: &str = "z_logs";
+pub const LOG_FILE: &str = "logger.log";
+
+/// Rotate once `logger.log` would grow past this
+pub const DEFAULT_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// `logger.log` plus `logger.log.1` .. `logger.log.4`
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// One line of `logger.log`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEvent {
+    pub id: u64,
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// Filter and page for `FileLogger::read`; pages run newest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogQuery {
+    /// Only these levels; all when empty
+    pub levels: Vec<LogLevel>,
+    pub offset: usize,
+    /// Page size; 0 means everything from `offset`
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogPage {
+    pub events: Vec<LogEvent>,
+    /// Events matching the filter, across every page
+    pub total: usize,
+    pub offset: usize,
+    /// Lines that weren't log events (hand edits, a torn final write)
+    pub skipped: usize,
+}
+
+/// Append-only JSON-lines log under `<app_dir>/z_logs`, rotated by size. Ids carry on
+/// from the last event on disk, so they stay unique across restarts.
+pub struct FileLogger {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    next_id: AtomicU64,
+    write_lock: Mutex<()>,
+}
+
+impl FileLogger {
+    pub fn new<P: AsRef<Path>>(app_dir: P) -> Self {
+        Self::with_limits(app_dir, DEFAULT_MAX_BYTES, DEFAULT_MAX_FILES)
+    }
+
+    pub fn with_limits<P: AsRef<Path>>(app_dir: P, max_bytes: u64, max_files: usize) -> Self {
+        let dir = app_dir.as_ref().join(LOG_DIR);
+        let last_id = fs::read_to_string(dir.join(LOG_FILE))
+            .ok()
+            .and_then(|content| {
+                content
+                    .lines()
+                    .rev()
+                    .find_map(|line| serde_json::from_str::<LogEvent>(line).ok())
+            })
+            .map_or(0, |event| event.id);
+
+        Self {
+            dir,
+            max_bytes,
+            max_files: max_files.max(1),
+            next_id: AtomicU64::new(last_id + 1),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// The `DTC_APP_DIR` the log lives in
+    pub fn app_dir(&self) -> &Path {
+        self.dir.parent().unwrap_or(&self.dir)
+    }
+
+    /// `logger.log`, then `logger.log.1` (newest rotated) onwards
+    pub fn files(&self) -> Vec<PathBuf> {
+        (0..self.max_files).map(|n| self.file(n)).collect()
+    }
+
+    fn file(&self, n: usize) -> PathBuf {
+        match n {
+            0 => self.dir.join(LOG_FILE),
+            n => self.dir.join(format!("{}.{}", LOG_FILE, n)),
+        }
+    }
+
+    /// Append one event, rotating first if it would take `logger.log` past the size limit
+    pub fn log(&self, level: LogLevel, message: &str) -> AppResult<LogEvent> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let event = LogEvent {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level,
+            message: message.to_string(),
+        };
+        let mut line = serde_json::to_string(&event)
+            .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?;
+        line.push('\n');
+
+        let path = self.file(0);
+        let write_error =
+            |e: std::io::Error| AppError::io(ErrorCode::FileWrite, &e).with("path", &path);
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| AppError::io(ErrorCode::DirectoryCreate, &e).with("path", &self.dir))?;
+
+        let size = fs::metadata(&path).map_or(0, |m| m.len());
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            self.rotate().map_err(write_error)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(write_error)?;
+        file.write_all(line.as_bytes()).map_err(write_error)?;
+
+        Ok(event)
+    }
+
+    /// Drop the oldest file and shift the rest up: `logger.log` becomes `logger.log.1`
+    fn rotate(&self) -> std::io::Result<()> {
+        let oldest = self.file(self.max_files - 1);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for n in (0..self.max_files - 1).rev() {
+            let from = self.file(n);
+            if from.exists() {
+                fs::rename(&from, self.file(n + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read back history from every log file, newest first, filtered by level and paged
+    pub fn read(&self, query: &LogQuery) -> AppResult<LogPage> {
+        let mut events = Vec::new();
+        let mut skipped = 0;
+
+        for path in self.files() {
+            let content = match fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(AppError::io(ErrorCode::FileRead, &e).with("path", &path)),
+            };
+            for line in content.lines().rev().filter(|l| !l.trim().is_empty()) {
+                match serde_json::from_str::<LogEvent>(line) {
+                    Ok(event) if query.levels.is_empty() || query.levels.contains(&event.level) => {
+                        events.push(event)
+                    }
+                    Ok(_) => {}
+                    Err(_) => skipped += 1,
+                }
+            }
+        }
+
+        let total = events.len();
+        let limit = match query.limit {
+            0 => usize::MAX,
+            limit => limit,
+        };
+        Ok(LogPage {
+            events: events.into_iter().skip(query.offset).take(limit).collect(),
+            total,
+            offset: query.offset,
+            skipped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_and_read_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let logger = FileLogger::new(dir.path());
+        logger.log(LogLevel::Info, "scan started").unwrap();
+        logger.log(LogLevel::Error, "copy failed").unwrap();
+        logger.log(LogLevel::Info, "scan done").unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("z_logs/logger.log"))
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let page = logger
+            .read(&LogQuery {
+                levels: Vec::new(),
+                offset: 1,
+                limit: 1,
+            })
+            .unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.skipped, 1);
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].message, "copy failed");
+
+        let errors = logger
+            .read(&LogQuery {
+                levels: vec![LogLevel::Error],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(errors.total, 1);
+
+        // Ids continue after a restart
+        let logger = FileLogger::new(dir.path());
+        assert_eq!(logger.log(LogLevel::Debug, "again").unwrap().id, 4);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let logger = FileLogger::with_limits(dir.path(), 200, 3);
+        for n in 0..20 {
+            logger
+                .log(LogLevel::Info, &format!("event {:02}", n))
+                .unwrap();
+        }
+
+        let logs = dir.path().join(LOG_DIR);
+        assert!(logs.join("logger.log.2").exists());
+        assert!(!logs.join("logger.log.3").exists());
+        for path in logger.files() {
+            assert!(fs::metadata(path).unwrap().len() <= 200);
+        }
+
+        // The oldest events were dropped with the oldest file; the newest come first
+        let page = logger.read(&LogQuery::default()).unwrap();
+        assert!(page.total < 20);
+        assert_eq!(page.events[0].message, "event 19");
+        assert_eq!(page.events[0].id, 20);
+    }
+}
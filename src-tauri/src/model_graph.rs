@@ -0,0 +1,317 @@
+use crate::dt_json::DrawThingsConfig;
+use crate::error::ErrorCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// One parent -> child link, e.g. a main model and the VAE it uses.
+/// Same shape as a `ckpt_x_ckpt` row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelEdge {
+    pub parent: String,
+    pub child: String,
+    /// "vae" | "clip" | "text" | "image_encoder" | "preprocessor"
+    pub role: String,
+}
+
+/// Which models use which encoder files, in both directions
+#[derive(Debug, Clone, Default)]
+pub struct ModelGraph {
+    edges: Vec<ModelEdge>,
+    parents: HashMap<String, Vec<String>>,
+    children: HashMap<String, Vec<String>>,
+}
+
+/// Serializable summary of a `ModelGraph`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphReport {
+    pub edges: Vec<ModelEdge>,
+    /// Number of models using each encoder file
+    pub ref_counts: BTreeMap<String, usize>,
+    /// Encoder files used by more than one model
+    pub shared: Vec<String>,
+}
+
+/// A file can't be deleted because other models still need it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInUse {
+    pub file: String,
+    pub parents: Vec<String>,
+}
+
+impl FileInUse {
+    /// Error code from error_codes.md: 36 for one parent, 44 when shared by several
+    pub fn code(&self) -> ErrorCode {
+        if self.parents.len() > 1 {
+            ErrorCode::MultipleReferences
+        } else {
+            ErrorCode::ParentModelsExist
+        }
+    }
+}
+
+impl fmt::Display for FileInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = if self.parents.len() > 1 {
+            "File referenced by multiple models"
+        } else {
+            "Parent models exist (file in use)"
+        };
+        write!(
+            f,
+            "{}: {} is used by {}",
+            message,
+            self.file,
+            self.parents.join(", ")
+        )
+    }
+}
+
+impl ModelGraph {
+    /// Build the graph from the encoder references in custom.json and custom_controlnet.json
+    pub fn from_config(config: &DrawThingsConfig) -> Self {
+        let mut graph = ModelGraph::default();
+
+        for model in &config.models {
+            for (role, child) in model.encoders() {
+                graph.add_edge(&model.file, child, role);
+            }
+        }
+        for controlnet in &config.controlnets {
+            for (role, child) in controlnet.encoders() {
+                graph.add_edge(&controlnet.file, child, role);
+            }
+        }
+
+        graph
+    }
+
+    fn add_edge(&mut self, parent: &str, child: &str, role: &str) {
+        let parents = self.parents.entry(child.to_string()).or_default();
+        if parents.iter().any(|p| p == parent) {
+            return;
+        }
+        parents.push(parent.to_string());
+
+        self.children
+            .entry(parent.to_string())
+            .or_default()
+            .push(child.to_string());
+        self.edges.push(ModelEdge {
+            parent: parent.to_string(),
+            child: child.to_string(),
+            role: role.to_string(),
+        });
+    }
+
+    pub fn edges(&self) -> &[ModelEdge] {
+        &self.edges
+    }
+
+    /// Models that use `file`
+    pub fn parents_of(&self, file: &str) -> &[String] {
+        self.parents.get(file).map(|p| p.as_slice()).unwrap_or(&[])
+    }
+
+    /// Encoder files used by `file`
+    pub fn children_of(&self, file: &str) -> &[String] {
+        self.children.get(file).map(|c| c.as_slice()).unwrap_or(&[])
+    }
+
+    /// Number of models using `file`
+    pub fn reference_count(&self, file: &str) -> usize {
+        self.parents_of(file).len()
+    }
+
+    /// Reference count of every encoder file
+    pub fn ref_counts(&self) -> BTreeMap<String, usize> {
+        self.parents
+            .iter()
+            .map(|(child, parents)| (child.clone(), parents.len()))
+            .collect()
+    }
+
+    /// Encoder files shared by more than one model, sorted
+    pub fn shared_files(&self) -> Vec<String> {
+        let mut shared: Vec<String> = self
+            .parents
+            .iter()
+            .filter(|(_, parents)| parents.len() > 1)
+            .map(|(child, _)| child.clone())
+            .collect();
+        shared.sort();
+        shared
+    }
+
+    /// Refuse to delete `file` while any model still uses it
+    pub fn check_deletable(&self, file: &str) -> Result<(), FileInUse> {
+        self.check_deletable_with(file, &HashSet::new())
+    }
+
+    /// Like `check_deletable`, but parents in `also_deleting` don't count -
+    /// for batch operations that remove a model together with its encoders
+    pub fn check_deletable_with(
+        &self,
+        file: &str,
+        also_deleting: &HashSet<String>,
+    ) -> Result<(), FileInUse> {
+        let remaining: Vec<String> = self
+            .parents_of(file)
+            .iter()
+            .filter(|p| !also_deleting.contains(*p))
+            .cloned()
+            .collect();
+
+        if remaining.is_empty() {
+            Ok(())
+        } else {
+            Err(FileInUse {
+                file: file.to_string(),
+                parents: remaining,
+            })
+        }
+    }
+
+    pub fn report(&self) -> GraphReport {
+        GraphReport {
+            edges: self.edges.clone(),
+            ref_counts: self.ref_counts(),
+            shared: self.shared_files(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn graph() -> ModelGraph {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("custom.json"),
+            r#"[
+                {"name": "FLUX dev", "file": "flux_1_dev_q8p.ckpt", "autoencoder": "flux_1_vae_f16.ckpt",
+                 "text_encoder": "t5_xxl_encoder_q6p.ckpt", "clip_encoder": "clip_vit_l14_f16.ckpt"},
+                {"name": "FLUX schnell", "file": "flux_1_schnell_q8p.ckpt", "autoencoder": "flux_1_vae_f16.ckpt",
+                 "text_encoder": "t5_xxl_encoder_q6p.ckpt"},
+                {"name": "SD3", "file": "sd3_medium_f16.ckpt", "text_encoder": "t5_xxl_encoder_q6p.ckpt"},
+                {"name": "SD 1.5", "file": "sd_v1.5_f16.ckpt"}
+            ]"#,
+        )
+        .unwrap();
+        ModelGraph::from_config(&DrawThingsConfig::parse_from_directory(dir.path()).unwrap())
+    }
+
+    #[test]
+    fn test_parents_and_children() {
+        let graph = graph();
+
+        assert_eq!(
+            graph.children_of("flux_1_dev_q8p.ckpt"),
+            [
+                "flux_1_vae_f16.ckpt",
+                "clip_vit_l14_f16.ckpt",
+                "t5_xxl_encoder_q6p.ckpt"
+            ]
+        );
+        assert_eq!(
+            graph.parents_of("flux_1_vae_f16.ckpt"),
+            ["flux_1_dev_q8p.ckpt", "flux_1_schnell_q8p.ckpt"]
+        );
+        assert!(graph.parents_of("sd_v1.5_f16.ckpt").is_empty());
+        assert!(graph.children_of("sd_v1.5_f16.ckpt").is_empty());
+        assert_eq!(graph.edges().len(), 6);
+        assert_eq!(graph.edges()[1].role, "clip");
+    }
+
+    #[test]
+    fn test_shared_encoders() {
+        let graph = graph();
+
+        assert_eq!(graph.reference_count("t5_xxl_encoder_q6p.ckpt"), 3);
+        assert_eq!(graph.reference_count("clip_vit_l14_f16.ckpt"), 1);
+        assert_eq!(graph.reference_count("sd_v1.5_f16.ckpt"), 0);
+        assert_eq!(
+            graph.shared_files(),
+            vec!["flux_1_vae_f16.ckpt", "t5_xxl_encoder_q6p.ckpt"]
+        );
+        assert_eq!(graph.report().ref_counts.len(), 3);
+    }
+
+    #[test]
+    fn test_check_deletable() {
+        let graph = graph();
+
+        assert!(graph.check_deletable("sd_v1.5_f16.ckpt").is_ok());
+        assert!(graph.check_deletable("flux_1_dev_q8p.ckpt").is_ok());
+
+        let single = graph.check_deletable("clip_vit_l14_f16.ckpt").unwrap_err();
+        assert_eq!(single.code(), ErrorCode::ParentModelsExist);
+
+        let shared = graph
+            .check_deletable("t5_xxl_encoder_q6p.ckpt")
+            .unwrap_err();
+        assert_eq!(shared.code(), ErrorCode::MultipleReferences);
+        assert_eq!(shared.parents.len(), 3);
+
+        // Deleting the only parent along with the encoder is fine
+        let deleting: HashSet<String> = ["flux_1_dev_q8p.ckpt".to_string()].into();
+        assert!(graph
+            .check_deletable_with("clip_vit_l14_f16.ckpt", &deleting)
+            .is_ok());
+        let vae = graph
+            .check_deletable_with("flux_1_vae_f16.ckpt", &deleting)
+            .unwrap_err();
+        assert_eq!(vae.parents, vec!["flux_1_schnell_q8p.ckpt"]);
+        assert_eq!(vae.code(), ErrorCode::ParentModelsExist);
+    }
+
+    #[test]
+    fn test_t5_image_encoder_and_preprocessor() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("custom.json"),
+            r#"[{"name": "SD3", "file": "sd3_large_q8p.ckpt", "t5_encoder": "t5_xxl_encoder_q6p.ckpt",
+                 "image_encoder": "clip_vit_h14_vision_model_f16.ckpt", "preprocessor": "depth_anything_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        let graph =
+            ModelGraph::from_config(&DrawThingsConfig::parse_from_directory(dir.path()).unwrap());
+
+        let roles: Vec<&str> = graph.edges().iter().map(|e| e.role.as_str()).collect();
+        assert_eq!(roles, vec!["text", "image_encoder", "preprocessor"]);
+        assert!(graph.check_deletable("depth_anything_f16.ckpt").is_err());
+    }
+
+    #[test]
+    fn test_controlnet_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("custom_controlnet.json"),
+            r#"[
+                {"name": "PuLID", "file": "pulid_0.9.1_eva02_clip_l14_336_f16.ckpt", "version": "flux1",
+                 "image_encoder": "eva02_clip_l14_336_f16.ckpt", "preprocessor": "arcface_f16.ckpt"},
+                {"name": "FaceID", "file": "ip_adapter_faceid_plus_f16.ckpt", "version": "sdxl_base_v0.9",
+                 "image_encoder": "clip_vit_l14_336_vision_model_f16.ckpt", "preprocessor": "arcface_f16.ckpt"}
+            ]"#,
+        )
+        .unwrap();
+        let config = DrawThingsConfig::parse_from_directory(dir.path()).unwrap();
+        let graph = ModelGraph::from_config(&config);
+
+        assert_eq!(
+            graph.children_of("pulid_0.9.1_eva02_clip_l14_336_f16.ckpt"),
+            ["eva02_clip_l14_336_f16.ckpt", "arcface_f16.ckpt"]
+        );
+        let shared = graph.check_deletable("arcface_f16.ckpt").unwrap_err();
+        assert_eq!(shared.code(), ErrorCode::MultipleReferences);
+        assert_eq!(
+            config.get_model_type("arcface_f16.ckpt").as_deref(),
+            Some("preprocessor")
+        );
+        assert!(config
+            .get_model_encoders("ip_adapter_faceid_plus_f16.ckpt")
+            .is_some());
+    }
+}
@@ -0,0 +1,680 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Architecture a model file belongs to, inferred from its tensor shapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelFamily {
+    Sd15,
+    Sd2,
+    Sdxl,
+    Sd3,
+    Flux,
+    Unknown,
+}
+
+/// What a model file is for, inferred from its tensor names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRole {
+    /// The denoiser (UNet, or the DiT of SD3/Flux), possibly with its VAE and encoders
+    Unet,
+    Vae,
+    TextEncoder,
+    Lora,
+    ControlNet,
+    Unknown,
+}
+
+impl ModelRole {
+    /// The model type Draw Things' registries use for it
+    pub fn model_type(&self) -> &'static str {
+        match self {
+            Self::Unet => "model",
+            Self::Vae => "vae",
+            Self::TextEncoder => "text",
+            Self::Lora => "lora",
+            Self::ControlNet => "control",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TensorInfo {
+    pub name: String,
+    pub dtype: String,
+    pub shape: Vec<u64>,
+    pub params: u64,
+}
+
+impl TensorInfo {
+    pub fn new(name: String, dtype: String, shape: Vec<u64>) -> Self {
+        let params = shape.iter().product();
+        Self {
+            name,
+            dtype,
+            shape,
+            params,
+        }
+    }
+}
+
+/// The `__metadata__` block of a safetensors file (kohya-ss `ss_*` and modelspec keys)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SafetensorsMetadata {
+    /// `ss_base_model_version`, e.g. `sd_v1`, `sdxl_base_v1-0`, `flux1`
+    pub base_model_version: Option<String>,
+    /// `ss_network_dim`: a LoRA's rank
+    pub network_dim: Option<u32>,
+    pub network_alpha: Option<f64>,
+    /// `modelspec.trigger_phrase` / `trigger_words`, else the most frequent training tags
+    pub trigger_words: Vec<String>,
+    /// The remaining entries, minus long values (tag frequencies, dataset configs)
+    pub other: BTreeMap<String, String>,
+}
+
+/// Contents of a model file, read without loading any weights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelInspection {
+    pub path: String,
+    pub tensor_count: usize,
+    pub total_params: u64,
+    /// Number of tensors of each dtype, e.g. `{"f16": 1131}`
+    pub dtypes: BTreeMap<String, usize>,
+    pub family: ModelFamily,
+    pub role: ModelRole,
+    /// What the family/role were inferred from
+    pub hints: Vec<String>,
+    /// safetensors only
+    pub metadata: Option<SafetensorsMetadata>,
+    pub tensors: Vec<TensorInfo>,
+}
+
+impl ModelInspection {
+    pub fn new(
+        path: &Path,
+        tensors: Vec<TensorInfo>,
+        metadata: Option<SafetensorsMetadata>,
+    ) -> Self {
+        let mut dtypes = BTreeMap::new();
+        for tensor in &tensors {
+            *dtypes.entry(tensor.dtype.clone()).or_insert(0) += 1;
+        }
+        let mut hints = Vec::new();
+        let role = infer_role(&tensors, metadata.as_ref(), &mut hints);
+        let family = infer_family(&tensors, metadata.as_ref(), &mut hints);
+
+        Self {
+            path: path.to_string_lossy().to_string(),
+            tensor_count: tensors.len(),
+            total_params: tensors.iter().map(|t| t.params).sum(),
+            dtypes,
+            family,
+            role,
+            hints,
+            metadata,
+            tensors,
+        }
+    }
+}
+
+/// What the scanner keeps on each model record: the inspection without its tensor list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelSummary {
+    pub tensor_count: usize,
+    pub total_params: u64,
+    pub dtypes: BTreeMap<String, usize>,
+    pub family: ModelFamily,
+    pub role: ModelRole,
+    pub metadata: Option<SafetensorsMetadata>,
+}
+
+impl From<ModelInspection> for ModelSummary {
+    fn from(inspection: ModelInspection) -> Self {
+        Self {
+            tensor_count: inspection.tensor_count,
+            total_params: inspection.total_params,
+            dtypes: inspection.dtypes,
+            family: inspection.family,
+            role: inspection.role,
+            metadata: inspection.metadata,
+        }
+    }
+}
+
+/// Inspect a `.safetensors` by its header, anything else as a Draw Things `.ckpt`
+pub fn inspect_model<P: AsRef<Path>>(path: P) -> AppResult<ModelInspection> {
+    let path = path.as_ref();
+    let is_safetensors = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("safetensors"));
+    match is_safetensors {
+        true => inspect_safetensors(path),
+        false => inspect_ckpt(path),
+    }
+}
+
+/// List the tensors in a Draw Things `.ckpt`: an SQLite database with a
+/// `tensors (name, type, format, datatype, dim, data)` table. The database is opened
+/// read-only and `data` is never read, so even multi-GB files inspect instantly.
+pub fn inspect_ckpt<P: AsRef<Path>>(path: P) -> AppResult<ModelInspection> {
+    let path = path.as_ref();
+    if !path.is_file() {
+        return Err(AppError::from(ErrorCode::FileNotFound).with("path", path));
+    }
+
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let conn = Connection::open_with_flags(path, flags).map_err(|e| {
+        AppError::new(ErrorCode::DatabaseConnection, e.to_string()).with("path", path)
+    })?;
+    let mut statement = conn
+        .prepare("SELECT name, datatype, dim FROM tensors ORDER BY rowid")
+        .map_err(|e| {
+            AppError::new(ErrorCode::DatabaseSchema, "Not a Draw Things checkpoint")
+                .with("path", path)
+                .with("error", e.to_string())
+        })?;
+
+    let rows = statement
+        .query_map([], |row| {
+            let dim: Vec<u8> = row.get(2)?;
+            let shape = dim
+                .chunks_exact(4)
+                .map(|d| i32::from_le_bytes([d[0], d[1], d[2], d[3]]).max(0) as u64)
+                .collect();
+            Ok(TensorInfo::new(row.get(0)?, dtype_name(row.get(1)?), shape))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| AppError::new(ErrorCode::DatabaseQuery, e.to_string()).with("path", path))?;
+
+    Ok(ModelInspection::new(path, rows, None))
+}
+
+/// ccv/s4nnc datatype codes; the low bits hold the channel count
+fn dtype_name(datatype: i64) -> String {
+    match datatype & 0xFF000 {
+        0x01000 => "u8".into(),
+        0x02000 => "i32".into(),
+        0x04000 => "f32".into(),
+        0x08000 => "i64".into(),
+        0x10000 => "f64".into(),
+        0x20000 => "f16".into(),
+        0x40000 => "quantized".into(),
+        0x80000 => "bf16".into(),
+        _ => format!("unknown({:#x})", datatype),
+    }
+}
+
+/// Headers are a few hundred KB at most; anything bigger isn't a safetensors file
+const MAX_HEADER_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Metadata values longer than this are left out of `SafetensorsMetadata::other`
+const MAX_METADATA_VALUE: usize = 256;
+
+#[derive(Deserialize)]
+struct HeaderEntry {
+    dtype: String,
+    shape: Vec<u64>,
+    data_offsets: [u64; 2],
+}
+
+/// Read a `.safetensors` header: an 8-byte little-endian length followed by a JSON object
+/// of `name: { dtype, shape, data_offsets }` plus an optional `__metadata__` string map.
+/// Only the header is read, never the tensor data.
+pub fn inspect_safetensors<P: AsRef<Path>>(path: P) -> AppResult<ModelInspection> {
+    let path = path.as_ref();
+    let not_safetensors = |reason: &str| {
+        AppError::new(ErrorCode::InvalidJsonStructure, "Not a safetensors file")
+            .with("path", path)
+            .with("reason", reason)
+    };
+
+    let mut file = File::open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => AppError::from(ErrorCode::FileNotFound).with("path", path),
+        _ => AppError::io(ErrorCode::FileRead, &e).with("path", path),
+    })?;
+    let file_size = file
+        .metadata()
+        .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", path))?
+        .len();
+
+    let mut length = [0u8; 8];
+    file.read_exact(&mut length)
+        .map_err(|_| not_safetensors("shorter than the header length"))?;
+    let length = u64::from_le_bytes(length);
+    if length > MAX_HEADER_BYTES || length > file_size - 8 {
+        return Err(not_safetensors("header length out of range"));
+    }
+
+    let mut header = vec![0u8; length as usize];
+    file.read_exact(&mut header)
+        .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", path))?;
+    let header: BTreeMap<String, Value> = serde_json::from_slice(&header)
+        .map_err(|e| AppError::new(ErrorCode::JsonParse, e.to_string()).with("path", path))?;
+
+    let mut metadata = None;
+    let mut entries = Vec::new();
+    for (name, value) in header {
+        if name == "__metadata__" {
+            let values = serde_json::from_value(value)
+                .map_err(|_| not_safetensors("__metadata__ is not a string map"))?;
+            metadata = Some(parse_metadata(values));
+            continue;
+        }
+        let entry: HeaderEntry = serde_json::from_value(value)
+            .map_err(|_| not_safetensors("tensor entry without dtype/shape/data_offsets"))?;
+        entries.push((name, entry));
+    }
+
+    // In file order, as `.ckpt` tensors are listed
+    entries.sort_by_key(|(_, entry)| entry.data_offsets[0]);
+    let tensors = entries
+        .into_iter()
+        .map(|(name, entry)| TensorInfo::new(name, entry.dtype.to_lowercase(), entry.shape))
+        .collect();
+
+    Ok(ModelInspection::new(path, tensors, metadata))
+}
+
+fn parse_metadata(mut values: BTreeMap<String, String>) -> SafetensorsMetadata {
+    let words = |text: &str| -> Vec<String> {
+        text.split(',')
+            .map(|w| w.trim().to_string())
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+
+    let mut trigger_words = Vec::new();
+    for key in [
+        "modelspec.trigger_phrase",
+        "ss_trigger_words",
+        "trigger_words",
+        "trigger_phrase",
+    ] {
+        if let Some(text) = values.remove(key) {
+            trigger_words.extend(words(&text));
+        }
+    }
+    // kohya's ss_tag_frequency: {"<dataset dir>": {"<tag>": count}}; the top tags are
+    // usually the trigger words when none were recorded
+    let tag_frequency = values.remove("ss_tag_frequency").and_then(|text| {
+        serde_json::from_str::<BTreeMap<String, BTreeMap<String, u64>>>(&text).ok()
+    });
+    if let (true, Some(datasets)) = (trigger_words.is_empty(), tag_frequency) {
+        let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+        for (tag, count) in datasets.into_values().flatten() {
+            *counts.entry(tag.trim().to_string()).or_insert(0) += count;
+        }
+        let mut counts: Vec<(String, u64)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        trigger_words = counts.into_iter().take(5).map(|(tag, _)| tag).collect();
+    }
+
+    SafetensorsMetadata {
+        base_model_version: values.remove("ss_base_model_version"),
+        network_dim: values.remove("ss_network_dim").and_then(|v| v.parse().ok()),
+        network_alpha: values
+            .remove("ss_network_alpha")
+            .and_then(|v| v.parse().ok()),
+        trigger_words,
+        other: values
+            .into_iter()
+            .filter(|(_, value)| value.len() <= MAX_METADATA_VALUE)
+            .collect(),
+    }
+}
+
+/// Role from tensor names. Covers Draw Things names (`__unet__[t-..]`, `__dit__`,
+/// `__text_model__`, `..__up__`) as well as original/diffusers/kohya ones. A full
+/// checkpoint that bundles a VAE and text encoders counts as `Unet`. Training metadata
+/// with a network dim/module marks a LoRA whatever its tensors are called.
+fn infer_role(
+    tensors: &[TensorInfo],
+    metadata: Option<&SafetensorsMetadata>,
+    hints: &mut Vec<String>,
+) -> ModelRole {
+    if let Some(metadata) = metadata {
+        if let Some(dim) = metadata.network_dim {
+            hints.push(format!("ss_network_dim {}", dim));
+            return ModelRole::Lora;
+        }
+        if metadata.other.contains_key("ss_network_module") {
+            hints.push("ss_network_module".into());
+            return ModelRole::Lora;
+        }
+    }
+
+    const RULES: [(ModelRole, &[&str]); 5] = [
+        (
+            ModelRole::Lora,
+            &[
+                "lora_up",
+                "lora_down",
+                "lora_a",
+                "lora_b",
+                "__up__",
+                "__down__",
+                "lora.",
+            ],
+        ),
+        (ModelRole::ControlNet, &["control"]),
+        (
+            ModelRole::Unet,
+            &[
+                "__unet__",
+                "__dit__",
+                "diffusion_model",
+                "double_blocks",
+                "single_blocks",
+                "joint_blocks",
+                "input_blocks",
+                "time_embed",
+            ],
+        ),
+        (
+            ModelRole::Vae,
+            &[
+                "first_stage_model",
+                "__encoder__",
+                "__decoder__",
+                "post_quant_conv",
+                "decoder.up",
+            ],
+        ),
+        (
+            ModelRole::TextEncoder,
+            &[
+                "text_model",
+                "__te2__",
+                "token_embedding",
+                "encoder.block",
+                "shared.weight",
+            ],
+        ),
+    ];
+
+    for (role, patterns) in RULES {
+        let matched = tensors.iter().find_map(|t| {
+            let name = t.name.to_lowercase();
+            patterns.iter().find(|p| name.contains(*p))
+        });
+        if let Some(pattern) = matched {
+            hints.push(format!("tensor names contain \"{}\"", pattern));
+            return role;
+        }
+    }
+
+    // Unrecognised names: a CLIP or T5 token embedding is still a text encoder
+    if tensors
+        .iter()
+        .any(|t| t.shape.len() == 2 && matches!(t.shape[0], 49408 | 32128))
+    {
+        hints.push("token embedding shape".into());
+        return ModelRole::TextEncoder;
+    }
+    ModelRole::Unknown
+}
+
+/// Family from characteristic tensor widths, checked most specific first: Flux's fused
+/// qkv/MLP (9216, 12288, 21504), SD3's (4608, 6144; 7296, 9728 for 3.5 Large), SDXL's
+/// 2048-wide cross-attention context and 2816 ADM input, SD2's 1024 and SD1.5's 768 (CLIP-L).
+/// LoRAs carry the same widths on one side of each pair. CLIP-L alone reports SD1.5;
+/// VAEs and T5 are shared between families and report `Unknown`. A base model named in
+/// the metadata (`ss_base_model_version`, `modelspec.architecture`) takes precedence.
+fn infer_family(
+    tensors: &[TensorInfo],
+    metadata: Option<&SafetensorsMetadata>,
+    hints: &mut Vec<String>,
+) -> ModelFamily {
+    let labels = metadata.into_iter().flat_map(|m| {
+        [
+            m.base_model_version.as_deref(),
+            m.other.get("modelspec.architecture").map(String::as_str),
+        ]
+    });
+    for label in labels.flatten() {
+        if let Some(family) = family_from_label(label) {
+            hints.push(format!("base model {}", label));
+            return family;
+        }
+    }
+
+    const WIDTHS: [(ModelFamily, &[u64]); 5] = [
+        (ModelFamily::Flux, &[9216, 12288, 21504]),
+        (ModelFamily::Sd3, &[4608, 6144, 7296, 9728]),
+        (ModelFamily::Sdxl, &[2048, 2816]),
+        (ModelFamily::Sd2, &[1024]),
+        (ModelFamily::Sd15, &[768]),
+    ];
+
+    let t5 = tensors.iter().any(|t| t.shape.contains(&32128));
+    for (family, widths) in WIDTHS {
+        // T5 (32128 vocab) is shared between families; T5-Base is 768 wide, which would
+        // otherwise read as SD1.5
+        if t5 && matches!(family, ModelFamily::Sd2 | ModelFamily::Sd15) {
+            break;
+        }
+        let matched = tensors
+            .iter()
+            .filter(|t| t.shape.len() >= 2)
+            .find_map(|t| widths.iter().find(|w| t.shape.contains(w)));
+        if let Some(width) = matched {
+            hints.push(format!("{:?} width {}", family, width).to_lowercase());
+            return family;
+        }
+    }
+
+    if let Some(channels) = latent_channels(tensors) {
+        hints.push(format!("vae with {} latent channels", channels));
+    }
+    ModelFamily::Unknown
+}
+
+/// `sd_v1`, `sd_v2_768_v`, `sdxl_base_v1-0`, `sd3`, `flux1`, or modelspec's
+/// `stable-diffusion-xl-v1-base/lora`, `flux-1-dev/lora`, ...
+fn family_from_label(label: &str) -> Option<ModelFamily> {
+    let label = label.to_lowercase();
+    let family = if label.contains("flux") {
+        ModelFamily::Flux
+    } else if label.contains("sdxl") || label.contains("stable-diffusion-xl") {
+        ModelFamily::Sdxl
+    } else if label.contains("sd3") || label.contains("stable-diffusion-3") {
+        ModelFamily::Sd3
+    } else if label.contains("sd_v2") || label.contains("stable-diffusion-v2") {
+        ModelFamily::Sd2
+    } else if label.contains("sd_v1") || label.contains("stable-diffusion-v1") {
+        ModelFamily::Sd15
+    } else {
+        return None;
+    };
+    Some(family)
+}
+
+/// Latent channels of a VAE: the input channels of the decoder's first convolution
+fn latent_channels(tensors: &[TensorInfo]) -> Option<u64> {
+    tensors
+        .iter()
+        .find(|t| {
+            let name = t.name.to_lowercase();
+            name.contains("decoder") && name.contains("conv_in") && t.shape.len() == 4
+        })
+        .map(|t| t.shape[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Draw Things style tensor store; `data` stays empty
+    fn write_ckpt(path: &Path, tensors: &[(&str, i64, &[i32])]) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute(
+            "CREATE TABLE tensors (name TEXT PRIMARY KEY, type INTEGER, format INTEGER, datatype INTEGER, dim BLOB, data BLOB)",
+            [],
+        )
+        .unwrap();
+        for (name, datatype, dim) in tensors {
+            let dim: Vec<u8> = dim.iter().flat_map(|d| d.to_le_bytes()).collect();
+            conn.execute(
+                "INSERT INTO tensors VALUES (?1, 0, 0, ?2, ?3, x'')",
+                rusqlite::params![name, datatype, dim],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_inspect_sdxl_unet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sdxl_base_v1.0_f16.ckpt");
+        write_ckpt(
+            &path,
+            &[
+                ("__unet__[t-0-0]", 0x20001, &[320, 4, 3, 3]),
+                ("__unet__[t-400-0]", 0x20001, &[640, 2048]),
+                ("__unet__[t-401-0]", 0x04001, &[1280]),
+            ],
+        );
+
+        let info = inspect_ckpt(&path).unwrap();
+        assert_eq!(info.tensor_count, 3);
+        assert_eq!(info.total_params, 320 * 4 * 9 + 640 * 2048 + 1280);
+        assert_eq!(info.dtypes.get("f16"), Some(&2));
+        assert_eq!(info.dtypes.get("f32"), Some(&1));
+        assert_eq!(info.tensors[1].shape, vec![640, 2048]);
+        assert_eq!(info.role, ModelRole::Unet);
+        assert_eq!(info.family, ModelFamily::Sdxl);
+        assert_eq!(info.role.model_type(), "model");
+    }
+
+    #[test]
+    fn test_infer_lora_and_text_encoder() {
+        let dir = tempfile::tempdir().unwrap();
+        let lora = dir.path().join("flux_style_lora_f16.ckpt");
+        write_ckpt(
+            &lora,
+            &[
+                ("__dit__[t-10-0]__down__", 0x20001, &[16, 3072]),
+                ("__dit__[t-10-0]__up__", 0x20001, &[9216, 16]),
+            ],
+        );
+        let info = inspect_ckpt(&lora).unwrap();
+        assert_eq!(
+            (info.role, info.family),
+            (ModelRole::Lora, ModelFamily::Flux)
+        );
+
+        let clip = dir.path().join("clip_vit_l14_f16.ckpt");
+        write_ckpt(&clip, &[("__text_model__[t-0-0]", 0x20001, &[49408, 768])]);
+        let info = inspect_ckpt(&clip).unwrap();
+        assert_eq!(
+            (info.role, info.family),
+            (ModelRole::TextEncoder, ModelFamily::Sd15)
+        );
+
+        let t5 = dir.path().join("t5_xxl_encoder_q6p.ckpt");
+        write_ckpt(&t5, &[("t-0-0", 0x20001, &[32128, 4096])]);
+        let info = inspect_ckpt(&t5).unwrap();
+        assert_eq!(
+            (info.role, info.family),
+            (ModelRole::TextEncoder, ModelFamily::Unknown)
+        );
+    }
+
+    fn write_safetensors(path: &Path, header: &serde_json::Value) {
+        let header = header.to_string();
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend(header.as_bytes());
+        bytes.extend([0u8; 16]);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_inspect_safetensors_lora() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pixel_art.safetensors");
+        let tags = r#"{"10_pxart": {"pxart style": 40, "1girl": 12, "outdoors": 3}}"#;
+        write_safetensors(
+            &path,
+            &serde_json::json!({
+                "lora_unet_mid_block_attentions_0_proj_in.lora_up.weight":
+                    {"dtype": "F16", "shape": [1280, 8], "data_offsets": [8, 16]},
+                "lora_unet_mid_block_attentions_0_proj_in.lora_down.weight":
+                    {"dtype": "F16", "shape": [8, 1280], "data_offsets": [0, 8]},
+                "__metadata__": {
+                    "ss_base_model_version": "sdxl_base_v1-0",
+                    "ss_network_dim": "8",
+                    "ss_network_alpha": "4.0",
+                    "ss_tag_frequency": tags,
+                    "ss_output_name": "pixel_art"
+                }
+            }),
+        );
+
+        let info = inspect_model(&path).unwrap();
+        assert_eq!(info.tensor_count, 2);
+        assert_eq!(info.total_params, 2 * 8 * 1280);
+        assert!(info.tensors[0].name.contains("lora_down"));
+        assert_eq!(info.dtypes.get("f16"), Some(&2));
+        assert_eq!(
+            (info.role, info.family),
+            (ModelRole::Lora, ModelFamily::Sdxl)
+        );
+
+        let metadata = info.metadata.unwrap();
+        assert_eq!(metadata.network_dim, Some(8));
+        assert_eq!(metadata.network_alpha, Some(4.0));
+        assert_eq!(
+            metadata.trigger_words,
+            vec!["pxart style", "1girl", "outdoors"]
+        );
+        assert_eq!(metadata.other.get("ss_output_name").unwrap(), "pixel_art");
+
+        // Without metadata the shapes still give it away
+        write_safetensors(
+            &path,
+            &serde_json::json!({
+                "transformer.single_blocks.0.linear1.lora_A.weight":
+                    {"dtype": "BF16", "shape": [16, 3072], "data_offsets": [0, 8]},
+                "transformer.single_blocks.0.linear1.lora_B.weight":
+                    {"dtype": "BF16", "shape": [21504, 16], "data_offsets": [8, 16]}
+            }),
+        );
+        let info = inspect_model(&path).unwrap();
+        assert_eq!(
+            (info.role, info.family),
+            (ModelRole::Lora, ModelFamily::Flux)
+        );
+        assert!(info.metadata.is_none());
+
+        std::fs::write(&path, u64::MAX.to_le_bytes()).unwrap();
+        assert_eq!(
+            inspect_model(&path).unwrap_err().code,
+            ErrorCode::InvalidJsonStructure
+        );
+    }
+
+    #[test]
+    fn test_inspect_not_a_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.ckpt");
+        std::fs::write(&path, b"not an sqlite database, just some bytes").unwrap();
+        assert_eq!(
+            inspect_ckpt(&path).unwrap_err().code,
+            ErrorCode::DatabaseSchema
+        );
+        assert_eq!(
+            inspect_ckpt(dir.path().join("missing.ckpt"))
+                .unwrap_err()
+                .code,
+            ErrorCode::FileNotFound
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use crate::community_models::CommunityModels;
+use crate::dt_json::DrawThingsConfig;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_policy::ExtensionPolicy;
+use crate::model_inspect::{self, ModelRole, ModelSummary};
+use crate::model_types::ModelTypeRegistry;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// What kind of file was found in a `Models` directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileClass {
+    Ckpt,
+    Safetensors,
+    /// Model with another configured extension, e.g. `.pth`
+    OtherModel,
+    /// Travels with a model, e.g. `<model>.ckpt-tensordata`
+    Companion,
+    SidecarJson,
+    /// Temp or partially written file (interrupted download/copy)
+    Partial,
+    Other,
+}
+
+impl FileClass {
+    pub fn is_model(&self) -> bool {
+        matches!(self, Self::Ckpt | Self::Safetensors | Self::OtherModel)
+    }
+
+    /// Models and their companions: what gets copied between the Mac and the stash
+    pub fn is_synced(&self) -> bool {
+        self.is_model() || *self == Self::Companion
+    }
+}
+
+/// Draw Things quantized variant, taken from the filename suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantization {
+    F16,
+    Q6p,
+    Q8p,
+}
+
+/// One file found under a `Models` directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedFile {
+    pub ckpt_filename: String,
+    pub relative_path: String,
+    pub file_size: u64,
+    pub file_date: Option<String>,
+    pub class: FileClass,
+    pub quantization: Option<Quantization>,
+    /// Filename stem with any quantization suffix removed, so variants of one model group together
+    pub base_name: String,
+    /// For a `Companion`, the model filename it belongs to
+    pub companion_of: Option<String>,
+    /// For `.safetensors`, what the header says: tensors, params, family/role and training metadata
+    pub header: Option<ModelSummary>,
+}
+
+/// Typed listing of a `Models` directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelsListing {
+    pub models_dir: String,
+    pub files: Vec<ScannedFile>,
+    pub total_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+impl ModelsListing {
+    /// Type of every model file: from the Draw Things registries, else the cached model
+    /// type lists, else the community models dataset, else (for unlisted safetensors) the
+    /// role read from its header, else "unknown"
+    pub fn model_types(
+        &self,
+        config: &DrawThingsConfig,
+        registry: &ModelTypeRegistry,
+        community: &CommunityModels,
+    ) -> HashMap<String, String> {
+        self.files
+            .iter()
+            .filter(|f| f.class.is_model())
+            .map(|f| {
+                let model_type = config.get_model_type(&f.ckpt_filename).unwrap_or_else(|| {
+                    let role = f.header.as_ref().map_or(ModelRole::Unknown, |h| h.role);
+                    registry
+                        .get_model_type(&f.ckpt_filename)
+                        .or_else(|| community.get_model_type(&f.ckpt_filename))
+                        .unwrap_or(role.model_type())
+                        .to_string()
+                });
+                (f.ckpt_filename.clone(), model_type)
+            })
+            .collect()
+    }
+}
+
+/// Scan `<base_dir>/Models` (DT_BASE_DIR or STASH_DIR) recursively and classify every file
+pub fn scan_models_dir<P: AsRef<Path>>(base_dir: P) -> AppResult<ModelsListing> {
+    scan_models_dir_with(base_dir, &ExtensionPolicy::default())
+}
+
+/// `scan_models_dir` with a configured extension policy
+pub fn scan_models_dir_with<P: AsRef<Path>>(
+    base_dir: P,
+    policy: &ExtensionPolicy,
+) -> AppResult<ModelsListing> {
+    let models_dir = base_dir.as_ref().join("Models");
+
+    if !models_dir.is_dir() {
+        return Err(AppError::new(
+            ErrorCode::DirectoryNotFound,
+            format!("Directory not found: {}", models_dir.display()),
+        )
+        .with("path", &models_dir));
+    }
+
+    let mut listing = ModelsListing {
+        models_dir: models_dir.to_string_lossy().to_string(),
+        files: Vec::new(),
+        total_bytes: 0,
+        errors: Vec::new(),
+    };
+
+    walk(&models_dir, &models_dir, policy, &mut listing)?;
+
+    listing
+        .files
+        .sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    listing.total_bytes = listing.files.iter().map(|f| f.file_size).sum();
+
+    Ok(listing)
+}
+
+fn walk(
+    root: &Path,
+    dir: &Path,
+    policy: &ExtensionPolicy,
+    listing: &mut ModelsListing,
+) -> AppResult<()> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        AppError::new(
+            ErrorCode::DirectoryNotReadable,
+            format!("Failed to read directory {}: {}", dir.display(), e),
+        )
+        .with("path", dir)
+    })?;
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                listing
+                    .errors
+                    .push(format!("{}: failed to read entry: {}", dir.display(), e));
+                continue;
+            }
+        };
+        let path = entry.path();
+        let filename = entry.file_name().to_string_lossy().to_string();
+
+        // Finder / exFAT noise, never a model
+        if filename == ".DS_Store" || filename.starts_with("._") {
+            continue;
+        }
+
+        // Don't follow symlinked directories - avoids loops
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(e) => {
+                listing.errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+
+        if file_type.is_dir() {
+            if let Err(e) = walk(root, &path, policy, listing) {
+                listing.errors.push(e.message);
+            }
+            continue;
+        }
+
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                listing.errors.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let relative_path = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let file_date = metadata
+            .modified()
+            .ok()
+            .map(|time| DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Millis, true));
+
+        let class = policy.classify(&filename);
+        let companion_of = policy.companion_of(&filename).map(str::to_string);
+        let (base_name, quantization) =
+            split_quantization(companion_of.as_deref().unwrap_or(&filename));
+        // Just the JSON header, so this stays cheap
+        let header = match class {
+            FileClass::Safetensors => match model_inspect::inspect_safetensors(&path) {
+                Ok(inspection) => Some(inspection.into()),
+                Err(e) => {
+                    listing
+                        .errors
+                        .push(format!("{}: {}", path.display(), e.message));
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        listing.files.push(ScannedFile {
+            ckpt_filename: filename,
+            relative_path,
+            file_size: metadata.len(),
+            file_date,
+            class,
+            quantization,
+            base_name,
+            companion_of,
+            header,
+        });
+    }
+
+    Ok(())
+}
+
+/// Classify a file in a `Models` directory by its name, with the default extension policy
+pub fn classify_file(filename: &str) -> FileClass {
+    ExtensionPolicy::default().classify(filename)
+}
+
+/// Split a filename into its base name and quantization suffix,
+/// e.g. `flux_1_dev_q8p.ckpt` -> (`flux_1_dev`, Some(Q8p))
+pub fn split_quantization(filename: &str) -> (String, Option<Quantization>) {
+    let stem = match filename.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => filename,
+    };
+
+    for (suffix, quantization) in [
+        ("_f16", Quantization::F16),
+        ("_q6p", Quantization::Q6p),
+        ("_q8p", Quantization::Q8p),
+    ] {
+        if stem.len() <= suffix.len() {
+            continue;
+        }
+        let split = stem.len() - suffix.len();
+        if let (Some(base), Some(tail)) = (stem.get(..split), stem.get(split..)) {
+            if tail.eq_ignore_ascii_case(suffix) {
+                return (base.to_string(), Some(quantization));
+            }
+        }
+    }
+
+    (stem.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_file() {
+        assert_eq!(classify_file("sd_v1.5_f16.ckpt"), FileClass::Ckpt);
+        assert_eq!(classify_file("Detail.SAFETENSORS"), FileClass::Safetensors);
+        assert_eq!(classify_file("custom.json"), FileClass::SidecarJson);
+        assert_eq!(classify_file("flux.ckpt.part"), FileClass::Partial);
+        assert_eq!(classify_file("flux.ckpt.crdownload"), FileClass::Partial);
+        assert_eq!(classify_file(".flux.ckpt.swp"), FileClass::Partial);
+        assert_eq!(classify_file("model.pth"), FileClass::OtherModel);
+        assert_eq!(classify_file("flux.ckpt-tensordata"), FileClass::Companion);
+        assert_eq!(classify_file("README"), FileClass::Other);
+    }
+
+    #[test]
+    fn test_split_quantization() {
+        assert_eq!(
+            split_quantization("flux_1_dev_q8p.ckpt"),
+            ("flux_1_dev".to_string(), Some(Quantization::Q8p))
+        );
+        assert_eq!(
+            split_quantization("sd_xl_base_1.0_F16.ckpt"),
+            ("sd_xl_base_1.0".to_string(), Some(Quantization::F16))
+        );
+        assert_eq!(
+            split_quantization("t5_xxl_encoder_q6p.ckpt"),
+            ("t5_xxl_encoder".to_string(), Some(Quantization::Q6p))
+        );
+        assert_eq!(
+            split_quantization("my_lora.safetensors"),
+            ("my_lora".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_scan_models_dir_recurses() {
+        let base = tempfile::tempdir().unwrap();
+        let models = base.path().join("Models");
+        fs::create_dir_all(models.join("imported")).unwrap();
+        fs::write(models.join("flux_1_dev_q8p.ckpt"), b"1234").unwrap();
+        fs::write(models.join("flux_1_dev_q8p.ckpt-tensordata"), b"567").unwrap();
+        fs::write(models.join("custom.json"), b"[]").unwrap();
+        fs::write(models.join(".DS_Store"), b"").unwrap();
+        // Header length 2, then `{}`
+        let mut safetensors = 2u64.to_le_bytes().to_vec();
+        safetensors.extend(b"{}");
+        fs::write(
+            models.join("imported").join("style.safetensors"),
+            safetensors,
+        )
+        .unwrap();
+        fs::write(models.join("imported").join("big.ckpt.partial"), b"1").unwrap();
+
+        let listing = scan_models_dir(base.path()).unwrap();
+        let paths: Vec<_> = listing
+            .files
+            .iter()
+            .map(|f| f.relative_path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "custom.json",
+                "flux_1_dev_q8p.ckpt",
+                "flux_1_dev_q8p.ckpt-tensordata",
+                "imported/big.ckpt.partial",
+                "imported/style.safetensors",
+            ]
+        );
+        assert_eq!(listing.total_bytes, 20);
+        let companion = &listing.files[2];
+        assert_eq!(companion.class, FileClass::Companion);
+        assert_eq!(
+            companion.companion_of.as_deref(),
+            Some("flux_1_dev_q8p.ckpt")
+        );
+        assert_eq!(companion.base_name, "flux_1_dev");
+        let partial = listing
+            .files
+            .iter()
+            .find(|f| f.class == FileClass::Partial)
+            .unwrap();
+        assert_eq!(partial.ckpt_filename, "big.ckpt.partial");
+        let header = listing.files[4].header.as_ref().unwrap();
+        assert_eq!(header.tensor_count, 0);
+        assert!(listing.files[1].header.is_none());
+        assert!(listing.errors.is_empty());
+
+        assert!(scan_models_dir(models.join("missing")).is_err());
+    }
+}
@@ -0,0 +1,427 @@
+use crate::atomic_write::write_atomic;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::settings::Settings;
+use chrono::{SecondsFormat, Utc};
+use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// `<DTC_APP_DIR>/model_types_cache.json`
+pub const CACHE_FILE: &str = "model_types_cache.json";
+
+/// settings.json key holding `GithubModelTypes`
+pub const SETTINGS_KEY: &str = "github_model_types";
+
+/// Overrides `github_model_types.base_url` (dev builds, tests against a local server)
+pub const BASE_URL_ENV: &str = "MODEL_TYPES_BASE_URL";
+
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The model type lists from settings.json. Each list is a URL, or a path resolved
+/// against `base_url`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GithubModelTypes {
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub models: Option<String>,
+    #[serde(default)]
+    pub loras: Option<String>,
+    #[serde(default)]
+    pub controlnets: Option<String>,
+    #[serde(default)]
+    pub embeddings: Option<String>,
+}
+
+impl GithubModelTypes {
+    /// `github_model_types` from settings.json (empty when missing), `MODEL_TYPES_BASE_URL` applied
+    pub fn from_settings(settings: &Settings) -> AppResult<Self> {
+        let mut config: Self = match settings.get(SETTINGS_KEY) {
+            Some(value) => serde_json::from_value(value.clone()).map_err(|e| {
+                AppError::new(ErrorCode::SettingsCorrupt, e.to_string()).with("key", SETTINGS_KEY)
+            })?,
+            None => Self::default(),
+        };
+        if let Some(base_url) = env::var(BASE_URL_ENV).ok().filter(|v| !v.is_empty()) {
+            config.base_url = Some(base_url);
+        }
+        Ok(config)
+    }
+
+    /// The resolved URL of each configured list
+    pub fn urls(&self) -> AppResult<BTreeMap<ListKind, String>> {
+        let lists = [
+            (ListKind::Models, &self.models),
+            (ListKind::Loras, &self.loras),
+            (ListKind::Controlnets, &self.controlnets),
+            (ListKind::Embeddings, &self.embeddings),
+        ];
+        let mut urls = BTreeMap::new();
+        for (kind, list) in lists {
+            let Some(list) = list.as_deref().filter(|l| !l.is_empty()) else {
+                continue;
+            };
+            let url = if list.contains("://") {
+                list.to_string()
+            } else {
+                let base_url = self.base_url.as_deref().ok_or_else(|| {
+                    AppError::new(
+                        ErrorCode::InvalidDownloadUrl,
+                        format!("{} is relative but no base_url is set", list),
+                    )
+                    .with("list", list)
+                })?;
+                format!(
+                    "{}/{}",
+                    base_url.trim_end_matches('/'),
+                    list.trim_start_matches('/')
+                )
+            };
+            urls.insert(kind, url);
+        }
+        Ok(urls)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListKind {
+    Models,
+    Loras,
+    Controlnets,
+    Embeddings,
+}
+
+impl ListKind {
+    /// The type `get_model_type` reports for files on this list
+    pub fn model_type(self) -> &'static str {
+        match self {
+            ListKind::Models => "model",
+            ListKind::Loras => "lora",
+            ListKind::Controlnets => "control",
+            ListKind::Embeddings => "embedding",
+        }
+    }
+}
+
+/// One downloaded list with what's needed to revalidate it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedList {
+    pub url: String,
+    pub filenames: BTreeSet<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Last time the server answered (200 or 304)
+    pub fetched_at: Option<String>,
+    /// Last time the content changed
+    pub updated_at: Option<String>,
+    /// Why the last refresh failed; cleared by the next answer
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshStatus {
+    Updated,
+    NotModified,
+    /// Unreachable or an error status; the cached list (if any) is still served
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOutcome {
+    pub kind: ListKind,
+    pub url: String,
+    pub status: RefreshStatus,
+    /// Filenames now in the list
+    pub count: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshReport {
+    pub registry: ModelTypeRegistry,
+    pub outcomes: Vec<ListOutcome>,
+}
+
+/// Filenames of known models, LoRAs, ControlNets and embeddings, persisted in
+/// `<DTC_APP_DIR>/model_types_cache.json` so it works offline
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelTypeRegistry {
+    pub lists: BTreeMap<ListKind, CachedList>,
+    /// Why an unparsable cache file was ignored; the next `save` replaces it
+    #[serde(skip)]
+    pub discarded: Option<String>,
+}
+
+impl ModelTypeRegistry {
+    /// The cached registry; empty before the first refresh, and when the file doesn't
+    /// parse (with the reason in `discarded`) so a refresh can rebuild it
+    pub fn load<P: AsRef<Path>>(app_dir: P) -> AppResult<Self> {
+        let path = app_dir.as_ref().join(CACHE_FILE);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(AppError::io(ErrorCode::FileRead, &e).with("path", &path)),
+        };
+        Ok(serde_json::from_str(&content).unwrap_or_else(|e| Self {
+            discarded: Some(format!("Ignored unreadable {}: {}", path.display(), e)),
+            ..Self::default()
+        }))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, app_dir: P) -> AppResult<PathBuf> {
+        let app_dir = app_dir.as_ref();
+        fs::create_dir_all(app_dir)
+            .map_err(|e| AppError::io(ErrorCode::DirectoryCreate, &e).with("path", app_dir))?;
+        let path = app_dir.join(CACHE_FILE);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?;
+        write_atomic(&path, content.as_bytes())
+            .map_err(|e| AppError::io(ErrorCode::FileWrite, &e).with("path", &path))?;
+        Ok(path)
+    }
+
+    /// "model", "lora", "control" or "embedding" for a listed filename
+    pub fn get_model_type(&self, filename: &str) -> Option<&'static str> {
+        self.lists
+            .iter()
+            .find(|(_, list)| list.filenames.contains(filename))
+            .map(|(kind, _)| kind.model_type())
+    }
+
+    /// Revalidate every configured list with a conditional GET (ETag / Last-Modified).
+    /// A list that can't be fetched keeps its cached content; lists no longer configured
+    /// are dropped. Blocks on the network.
+    pub fn refresh(&mut self, config: &GithubModelTypes) -> AppResult<Vec<ListOutcome>> {
+        let urls = config.urls()?;
+        let client = Client::builder()
+            .timeout(TIMEOUT)
+            .user_agent(concat!("draw-things-companion/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| AppError::new(ErrorCode::NetworkFailed, e.to_string()))?;
+
+        self.lists.retain(|kind, _| urls.contains_key(kind));
+        let mut outcomes = Vec::new();
+        for (kind, url) in urls {
+            let list = self.lists.entry(kind).or_default();
+            if list.url != url {
+                // Validators belong to the old URL
+                *list = CachedList {
+                    url: url.clone(),
+                    ..Default::default()
+                };
+            }
+
+            let (status, error) = match fetch(&client, list) {
+                Ok(status) => (status, None),
+                Err(e) => {
+                    list.error = Some(e.clone());
+                    (RefreshStatus::Failed, Some(e))
+                }
+            };
+            outcomes.push(ListOutcome {
+                kind,
+                url,
+                status,
+                count: list.filenames.len(),
+                error,
+            });
+        }
+        Ok(outcomes)
+    }
+}
+
+/// `load`, `refresh` and `save` in one go; the cache is saved even if every list failed,
+/// so the errors are on record
+pub fn refresh_registry<P: AsRef<Path>>(
+    app_dir: P,
+    config: &GithubModelTypes,
+) -> AppResult<RefreshReport> {
+    let mut registry = ModelTypeRegistry::load(&app_dir)?;
+    let outcomes = registry.refresh(config)?;
+    registry.save(&app_dir)?;
+    Ok(RefreshReport { registry, outcomes })
+}
+
+fn fetch(client: &Client, list: &mut CachedList) -> Result<RefreshStatus, String> {
+    let mut request = client.get(&list.url);
+    if let Some(etag) = &list.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &list.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().map_err(|e| e.to_string())?;
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    if response.status() == StatusCode::NOT_MODIFIED {
+        list.fetched_at = Some(now);
+        list.error = None;
+        return Ok(RefreshStatus::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let text = response.text().map_err(|e| e.to_string())?;
+
+    list.filenames = parse_list(&text);
+    list.etag = etag;
+    list.last_modified = last_modified;
+    list.fetched_at = Some(now.clone());
+    list.updated_at = Some(now);
+    list.error = None;
+    Ok(RefreshStatus::Updated)
+}
+
+/// One filename per line; blank lines and `#` comments skipped
+fn parse_list(text: &str) -> BTreeSet<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Serves `body` with ETag "v1" and answers 304 when it is sent back
+    fn stub_server(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let not_modified = Arc::new(AtomicUsize::new(0));
+        let counter = not_modified.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut revalidated = false;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap() == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                    let line = line.to_lowercase();
+                    revalidated |= line.starts_with("if-none-match:") && line.contains("\"v1\"");
+                }
+                let response = if revalidated {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (base_url, not_modified)
+    }
+
+    #[test]
+    fn test_refresh_revalidates_with_etag() {
+        let dir = tempfile::tempdir().unwrap();
+        let (base_url, not_modified) = stub_server("# models\nflux_1_dev.ckpt\n\nsd_v1.5.ckpt\n");
+        let config = GithubModelTypes {
+            base_url: Some(base_url),
+            models: Some("models.txt".into()),
+            ..Default::default()
+        };
+
+        let report = refresh_registry(dir.path(), &config).unwrap();
+        assert_eq!(report.outcomes[0].status, RefreshStatus::Updated);
+        assert_eq!(report.outcomes[0].count, 2);
+        assert_eq!(
+            report.registry.get_model_type("flux_1_dev.ckpt"),
+            Some("model")
+        );
+
+        let report = refresh_registry(dir.path(), &config).unwrap();
+        assert_eq!(report.outcomes[0].status, RefreshStatus::NotModified);
+        assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+        let list = &report.registry.lists[&ListKind::Models];
+        assert_eq!(list.filenames.len(), 2);
+        assert_eq!(list.etag.as_deref(), Some("\"v1\""));
+        assert!(list.fetched_at.is_some());
+    }
+
+    #[test]
+    fn test_offline_serves_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = ModelTypeRegistry::default();
+        registry.lists.insert(
+            ListKind::Loras,
+            CachedList {
+                url: "http://127.0.0.1:1/loras.txt".into(),
+                filenames: BTreeSet::from(["my_lora.ckpt".to_string()]),
+                ..Default::default()
+            },
+        );
+        registry.save(dir.path()).unwrap();
+
+        // Nothing listens on port 1
+        let config = GithubModelTypes {
+            base_url: Some("http://127.0.0.1:1".into()),
+            loras: Some("/loras.txt".into()),
+            ..Default::default()
+        };
+        let report = refresh_registry(dir.path(), &config).unwrap();
+        assert_eq!(report.outcomes[0].status, RefreshStatus::Failed);
+        assert!(report.outcomes[0].error.is_some());
+
+        let registry = ModelTypeRegistry::load(dir.path()).unwrap();
+        assert!(registry.discarded.is_none());
+        assert_eq!(registry.get_model_type("my_lora.ckpt"), Some("lora"));
+        assert_eq!(registry.get_model_type("unknown.ckpt"), None);
+        assert!(registry.lists[&ListKind::Loras].error.is_some());
+
+        let config = GithubModelTypes {
+            models: Some("models.txt".into()),
+            ..Default::default()
+        };
+        let error = config.urls().unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidDownloadUrl);
+
+        // A corrupt cache reads as empty and the next refresh rewrites it
+        fs::write(dir.path().join(CACHE_FILE), r#"{"lists":{"loras":{"url""#).unwrap();
+        let registry = ModelTypeRegistry::load(dir.path()).unwrap();
+        assert!(registry.lists.is_empty());
+        assert!(registry.discarded.is_some());
+        let config = GithubModelTypes {
+            base_url: Some("http://127.0.0.1:1".into()),
+            loras: Some("/loras.txt".into()),
+            ..Default::default()
+        };
+        let report = refresh_registry(dir.path(), &config).unwrap();
+        assert!(report.registry.discarded.is_some());
+        let registry = ModelTypeRegistry::load(dir.path()).unwrap();
+        assert!(registry.discarded.is_none());
+        assert!(registry.lists[&ListKind::Loras].error.is_some());
+    }
+}
@@ -0,0 +1,408 @@
+use crate::copy_engine::CopyProgress;
+use crate::dt_json::DrawThingsConfig;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_ops;
+use crate::file_policy::ExtensionPolicy;
+use crate::model_graph::ModelGraph;
+use crate::model_scan::{self, FileClass};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// An orphaned file on the Mac that can be moved to the stash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PruneItem {
+    pub filename: String,
+    /// Path relative to `Models`, kept the same in the stash
+    pub relative_path: String,
+    /// Bytes of the model plus its companions
+    pub size: u64,
+    /// Companion files (e.g. `-tensordata`) moved along with the model, relative to `Models`
+    pub companions: Vec<String>,
+}
+
+/// A file that isn't listed in any registry but is still used by a model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub filename: String,
+    pub parents: Vec<String>,
+}
+
+/// What a prune would do
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrunePlan {
+    pub items: Vec<PruneItem>,
+    pub total_bytes: u64,
+    pub skipped_referenced: Vec<SkippedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PruneFailure {
+    pub filename: String,
+    pub error: AppError,
+}
+
+/// Result of `prune_mac`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PruneSummary {
+    pub dry_run: bool,
+    pub planned: Vec<PruneItem>,
+    pub planned_bytes: u64,
+    pub moved: Vec<String>,
+    pub bytes_freed: u64,
+    pub skipped_referenced: Vec<SkippedFile>,
+    pub failures: Vec<PruneFailure>,
+    /// Stopped early by `cancel`; files not yet reached are left on the Mac
+    pub cancelled: bool,
+}
+
+/// Find orphans in `<mac_base_dir>/Models`: model files that no registry lists and
+/// no model uses as an encoder. Encoders still in use are reported as skipped.
+/// Refuses (41) while any registry has errors, as its files would all look orphaned.
+pub fn plan_prune<P: AsRef<Path>>(
+    mac_base_dir: P,
+    policy: &ExtensionPolicy,
+) -> AppResult<PrunePlan> {
+    let listing = model_scan::scan_models_dir_with(mac_base_dir, policy)?;
+    let config = DrawThingsConfig::parse_checked(&listing.models_dir)?;
+    let graph = ModelGraph::from_config(&config);
+
+    let mut plan = PrunePlan {
+        items: Vec::new(),
+        total_bytes: 0,
+        skipped_referenced: Vec::new(),
+    };
+
+    // Model relative path -> its companions (relative path, size)
+    let mut companions: HashMap<String, Vec<(String, u64)>> = HashMap::new();
+    for file in &listing.files {
+        if let (FileClass::Companion, Some(model)) = (file.class, &file.companion_of) {
+            let suffix = &file.ckpt_filename[model.len()..];
+            if let Some(model_path) = file.relative_path.strip_suffix(suffix) {
+                companions
+                    .entry(model_path.to_string())
+                    .or_default()
+                    .push((file.relative_path.clone(), file.file_size));
+            }
+        }
+    }
+
+    for file in listing.files {
+        if !file.class.is_model() {
+            continue;
+        }
+        if config.is_listed(&file.ckpt_filename) {
+            continue;
+        }
+
+        if let Err(in_use) = graph.check_deletable(&file.ckpt_filename) {
+            plan.skipped_referenced.push(SkippedFile {
+                filename: file.ckpt_filename,
+                parents: in_use.parents,
+            });
+            continue;
+        }
+
+        let companions = companions.remove(&file.relative_path).unwrap_or_default();
+        let size = file.file_size + companions.iter().map(|(_, size)| size).sum::<u64>();
+
+        plan.total_bytes += size;
+        plan.items.push(PruneItem {
+            filename: file.ckpt_filename,
+            relative_path: file.relative_path,
+            size,
+            companions: companions.into_iter().map(|(path, _)| path).collect(),
+        });
+    }
+
+    Ok(plan)
+}
+
+/// Move every orphan, with its companions, from the Mac to the stash. Each file is copied
+/// and verified by size and hash, and only then deleted from the Mac. With `dry_run`
+/// nothing is touched. `cancel` stops between files or mid-copy (the partial copy is removed).
+pub fn prune_mac<P, Q, F>(
+    mac_base_dir: P,
+    stash_base_dir: Q,
+    policy: &ExtensionPolicy,
+    dry_run: bool,
+    cancel: Option<&AtomicBool>,
+    mut on_progress: F,
+) -> AppResult<PruneSummary>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    F: FnMut(&CopyProgress),
+{
+    let mac_models = mac_base_dir.as_ref().join("Models");
+    let stash_models = stash_base_dir.as_ref().join("Models");
+    let plan = plan_prune(&mac_base_dir, policy)?;
+
+    let mut summary = PruneSummary {
+        dry_run,
+        planned: plan.items.clone(),
+        planned_bytes: plan.total_bytes,
+        moved: Vec::new(),
+        bytes_freed: 0,
+        skipped_referenced: plan.skipped_referenced,
+        failures: Vec::new(),
+        cancelled: false,
+    };
+
+    if dry_run {
+        return Ok(summary);
+    }
+
+    if !stash_models.is_dir() {
+        return Err(AppError::new(
+            ErrorCode::StashDirInvalid,
+            format!("Directory not found: {}", stash_models.display()),
+        )
+        .with("path", &stash_models));
+    }
+
+    let is_cancelled = || cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false);
+
+    for item in plan.items {
+        if is_cancelled() {
+            summary.cancelled = true;
+            break;
+        }
+
+        let paths: Vec<&String> = std::iter::once(&item.relative_path)
+            .chain(&item.companions)
+            .collect();
+
+        match move_verified(
+            &mac_models,
+            &stash_models,
+            &paths,
+            item.size,
+            cancel,
+            &mut on_progress,
+        ) {
+            Ok(()) => {
+                summary.bytes_freed += item.size;
+                summary.moved.push(item.filename);
+            }
+            Err(_) if is_cancelled() => {
+                summary.cancelled = true;
+                break;
+            }
+            Err(error) => summary.failures.push(PruneFailure {
+                filename: item.filename,
+                error,
+            }),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Copy a model and its companions (`paths`, relative to `Models`) to the stash and
+/// verify them all, then delete them from the Mac. `size` is their combined size.
+fn move_verified(
+    mac_models: &Path,
+    stash_models: &Path,
+    paths: &[&String],
+    size: u64,
+    cancel: Option<&AtomicBool>,
+    on_progress: &mut dyn FnMut(&CopyProgress),
+) -> AppResult<()> {
+    file_ops::require_space(stash_models, size)?;
+
+    for path in paths {
+        file_ops::copy_verified(
+            &mac_models.join(path),
+            &stash_models.join(path),
+            false,
+            cancel,
+            on_progress,
+        )?;
+    }
+    for path in paths {
+        file_ops::delete_file(mac_models.join(path))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Mac and stash trees with one listed model, its VAE (referenced but not
+    /// listed) and two orphans, one with a `-tensordata` companion
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let root = tempfile::tempdir().unwrap();
+        let mac = root.path().join("mac");
+        let stash = root.path().join("stash");
+        fs::create_dir_all(mac.join("Models")).unwrap();
+        fs::create_dir_all(stash.join("Models")).unwrap();
+
+        let models = mac.join("Models");
+        fs::write(
+            models.join("custom.json"),
+            r#"[{"name":"SD","file":"sd_v1.5_f16.ckpt","autoencoder":"vae_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        fs::write(models.join("sd_v1.5_f16.ckpt"), b"model").unwrap();
+        fs::write(models.join("vae_f16.ckpt"), b"vae").unwrap();
+        fs::write(models.join("old_model_f16.ckpt"), b"orphan-1").unwrap();
+        fs::write(models.join("old_model_f16.ckpt-tensordata"), b"weights").unwrap();
+        fs::write(models.join("style.safetensors"), b"orphan-22").unwrap();
+
+        (root, mac, stash)
+    }
+
+    #[test]
+    fn test_plan_prune() {
+        let (_root, mac, _stash) = setup();
+        let plan = plan_prune(&mac, &ExtensionPolicy::default()).unwrap();
+
+        let names: Vec<_> = plan.items.iter().map(|i| i.filename.as_str()).collect();
+        assert_eq!(names, vec!["old_model_f16.ckpt", "style.safetensors"]);
+        assert_eq!(
+            plan.items[0].companions,
+            vec!["old_model_f16.ckpt-tensordata"]
+        );
+        assert_eq!(plan.items[0].size, 15);
+        assert_eq!(plan.total_bytes, 24);
+        assert_eq!(plan.skipped_referenced.len(), 1);
+        assert_eq!(plan.skipped_referenced[0].filename, "vae_f16.ckpt");
+        assert_eq!(plan.skipped_referenced[0].parents, vec!["sd_v1.5_f16.ckpt"]);
+    }
+
+    #[test]
+    fn test_corrupt_registry_plans_nothing() {
+        let (_root, mac, stash) = setup();
+        fs::write(
+            mac.join("Models/custom.json"),
+            r#"[{"name":"SD","file":"sd_v1.5_f16.ckpt","autoenc"#,
+        )
+        .unwrap();
+
+        let error = plan_prune(&mac, &ExtensionPolicy::default()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidJsonStructure);
+        assert!(error.details.contains_key("diagnostics"));
+
+        let error = prune_mac(
+            &mac,
+            &stash,
+            &ExtensionPolicy::default(),
+            false,
+            None,
+            |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidJsonStructure);
+        assert!(mac.join("Models/sd_v1.5_f16.ckpt").exists());
+        assert!(mac.join("Models/vae_f16.ckpt").exists());
+        assert_eq!(fs::read_dir(stash.join("Models")).unwrap().count(), 0);
+
+        // A skipped entry (no name) refuses too
+        fs::write(
+            mac.join("Models/custom.json"),
+            r#"[{"file":"sd_v1.5_f16.ckpt","autoencoder":"vae_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        assert!(plan_prune(&mac, &ExtensionPolicy::default()).is_err());
+    }
+
+    #[test]
+    fn test_dry_run_touches_nothing() {
+        let (_root, mac, stash) = setup();
+        let summary = prune_mac(
+            &mac,
+            &stash,
+            &ExtensionPolicy::default(),
+            true,
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(summary.dry_run);
+        assert_eq!(summary.planned.len(), 2);
+        assert_eq!(summary.planned_bytes, 24);
+        assert!(summary.moved.is_empty());
+        assert_eq!(summary.bytes_freed, 0);
+        assert!(mac.join("Models/old_model_f16.ckpt").exists());
+        assert!(!stash.join("Models/old_model_f16.ckpt").exists());
+    }
+
+    #[test]
+    fn test_prune_moves_and_verifies() {
+        let (_root, mac, stash) = setup();
+
+        // Same name already in the stash with other contents: must not be deleted from the Mac
+        fs::write(stash.join("Models/style.safetensors"), b"different").unwrap();
+
+        let summary = prune_mac(
+            &mac,
+            &stash,
+            &ExtensionPolicy::default(),
+            false,
+            None,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(summary.moved, vec!["old_model_f16.ckpt"]);
+        assert_eq!(summary.bytes_freed, 15);
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].filename, "style.safetensors");
+        assert_eq!(summary.failures[0].error.code, ErrorCode::AlreadyExists);
+        assert_eq!(summary.skipped_referenced.len(), 1);
+
+        assert!(!mac.join("Models/old_model_f16.ckpt").exists());
+        assert_eq!(
+            fs::read(stash.join("Models/old_model_f16.ckpt")).unwrap(),
+            b"orphan-1"
+        );
+        assert!(!stash.join("Models/old_model_f16.ckpt.partial").exists());
+        assert!(!mac.join("Models/old_model_f16.ckpt-tensordata").exists());
+        assert_eq!(
+            fs::read(stash.join("Models/old_model_f16.ckpt-tensordata")).unwrap(),
+            b"weights"
+        );
+        assert!(mac.join("Models/style.safetensors").exists());
+        assert!(mac.join("Models/vae_f16.ckpt").exists());
+
+        // An identical copy already in the stash just frees the Mac copy
+        fs::write(stash.join("Models/style.safetensors"), b"orphan-22").unwrap();
+        let summary = prune_mac(
+            &mac,
+            &stash,
+            &ExtensionPolicy::default(),
+            false,
+            None,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(summary.moved, vec!["style.safetensors"]);
+        assert!(!mac.join("Models/style.safetensors").exists());
+    }
+
+    #[test]
+    fn test_cancelled_prune_leaves_mac_untouched() {
+        let (_root, mac, stash) = setup();
+        let cancel = AtomicBool::new(true);
+
+        let summary = prune_mac(
+            &mac,
+            &stash,
+            &ExtensionPolicy::default(),
+            false,
+            Some(&cancel),
+            |_| {},
+        )
+        .unwrap();
+        assert!(summary.cancelled);
+        assert!(summary.moved.is_empty());
+        assert!(summary.failures.is_empty());
+        assert!(mac.join("Models/old_model_f16.ckpt").exists());
+        assert_eq!(fs::read_dir(stash.join("Models")).unwrap().count(), 0);
+    }
+}
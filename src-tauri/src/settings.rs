@@ -0,0 +1,199 @@
+use crate::atomic_write::write_atomic;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_ops;
+use crate::file_policy::ExtensionPolicy;
+use serde_json::{Map, Value};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Same default as the frontend's `.env` defaults
+pub const DEFAULT_APP_DIR: &str = "~/.drawthings_companion";
+
+/// Load `.env` from the current directory, or the project root when run from `src-tauri`.
+/// Variables already set in the environment win.
+pub fn load_env() {
+    if dotenvy::dotenv().is_err() {
+        let _ = dotenvy::from_filename("../.env");
+    }
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_path(path: &str) -> PathBuf {
+    let home = || env::var("HOME").ok().map(PathBuf::from);
+    if path == "~" {
+        if let Some(home) = home() {
+            return home;
+        }
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = home() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// DTC_APP_DIR from the environment, else `~/.drawthings_companion`
+pub fn default_app_dir() -> PathBuf {
+    let dir = env::var("DTC_APP_DIR").unwrap_or_else(|_| DEFAULT_APP_DIR.to_string());
+    expand_path(&dir)
+}
+
+/// `<DTC_APP_DIR>/settings.json`, as written by the settings UI. Kept as a JSON object so
+/// keys the backend knows nothing about survive a `set` and `save`.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    path: PathBuf,
+    values: Map<String, Value>,
+}
+
+impl Settings {
+    /// Read the settings in `app_dir`. A missing file reads as empty settings.
+    pub fn load<P: AsRef<Path>>(app_dir: P) -> AppResult<Self> {
+        let path = app_dir.as_ref().join(SETTINGS_FILE);
+
+        if !path.exists() {
+            return Ok(Self {
+                path,
+                values: Map::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", &path))?;
+        let values = match serde_json::from_str(&content) {
+            Ok(Value::Object(values)) => values,
+            Ok(_) => {
+                return Err(AppError::new(
+                    ErrorCode::SettingsCorrupt,
+                    "Settings file corrupt: not a JSON object",
+                )
+                .with("path", &path))
+            }
+            Err(e) => {
+                return Err(AppError::new(
+                    ErrorCode::SettingsInvalidJson,
+                    format!("Settings file invalid JSON: {}", e),
+                )
+                .with("path", &path))
+            }
+        };
+
+        Ok(Self { path, values })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn values(&self) -> &Map<String, Value> {
+        &self.values
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    pub fn set(&mut self, key: &str, value: Value) {
+        self.values.insert(key.to_string(), value);
+    }
+
+    /// Write atomically, formatted like the frontend writes it (2-space indent)
+    pub fn save(&self) -> AppResult<()> {
+        if let Some(parent) = self.path.parent() {
+            file_ops::ensure_directory(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&self.values)
+            .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?;
+        write_atomic(&self.path, content.as_bytes())
+            .map_err(|e| AppError::io(ErrorCode::FileWrite, &e).with("path", &self.path))
+    }
+
+    /// A directory setting: settings.json first, then the environment (`.env`), `~` expanded
+    pub fn path_setting(&self, key: &str) -> Option<PathBuf> {
+        let value = match self.get(key) {
+            Some(Value::String(value)) if !value.is_empty() => value.clone(),
+            _ => env::var(key).ok().filter(|v| !v.is_empty())?,
+        };
+        Some(expand_path(&value))
+    }
+
+    pub fn dt_base_dir(&self) -> AppResult<PathBuf> {
+        self.path_setting("DT_BASE_DIR")
+            .ok_or_else(|| ErrorCode::DtBaseDirNotConfigured.into())
+    }
+
+    pub fn stash_dir(&self) -> AppResult<PathBuf> {
+        self.path_setting("STASH_DIR")
+            .ok_or_else(|| ErrorCode::StashDirNotConfigured.into())
+    }
+
+    /// `model_extensions` / `companion_suffixes` (and `partial_extensions`), defaults for the rest
+    pub fn extension_policy(&self) -> ExtensionPolicy {
+        let defaults = ExtensionPolicy::default();
+        let list = |key: &str, default: Vec<String>| {
+            self.get(key)
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or(default)
+        };
+        ExtensionPolicy {
+            model_extensions: list("model_extensions", defaults.model_extensions),
+            companion_suffixes: list("companion_suffixes", defaults.companion_suffixes),
+            partial_extensions: list("partial_extensions", defaults.partial_extensions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_path() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(
+            expand_path("~/DrawThings_Stash"),
+            PathBuf::from(&home).join("DrawThings_Stash")
+        );
+        assert_eq!(expand_path("~"), PathBuf::from(&home));
+        assert_eq!(
+            expand_path("/Volumes/Stash"),
+            PathBuf::from("/Volumes/Stash")
+        );
+    }
+
+    #[test]
+    fn test_load_set_save() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Missing file: empty settings, policy defaults
+        let mut settings = Settings::load(dir.path()).unwrap();
+        assert!(settings.values().is_empty());
+        assert_eq!(settings.extension_policy(), ExtensionPolicy::default());
+
+        settings.set("STASH_DIR", Value::from("/Volumes/Stash"));
+        settings.set("model_extensions", serde_json::json!(["ckpt"]));
+        settings.save().unwrap();
+
+        let settings = Settings::load(dir.path()).unwrap();
+        assert_eq!(
+            settings.stash_dir().unwrap(),
+            PathBuf::from("/Volumes/Stash")
+        );
+        assert_eq!(settings.extension_policy().model_extensions, vec!["ckpt"]);
+        assert_eq!(
+            settings.extension_policy().companion_suffixes,
+            vec!["-tensordata"]
+        );
+
+        fs::write(dir.path().join(SETTINGS_FILE), "{ not json").unwrap();
+        let error = Settings::load(dir.path()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::SettingsInvalidJson);
+        fs::write(dir.path().join(SETTINGS_FILE), "[]").unwrap();
+        let error = Settings::load(dir.path()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::SettingsCorrupt);
+    }
+}
@@ -0,0 +1,520 @@
+use crate::atomic_write::write_atomic;
+use crate::dt_json::DrawThingsConfig;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_hash::calculate_blake3;
+use crate::file_ops;
+use crate::file_policy::ExtensionPolicy;
+use crate::model_scan;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Recorded hashes, kept on the stash itself (next to the settings backup) so they
+/// travel with the disk
+pub const AUDIT_MANIFEST: &str = "App_Data/stash_hashes.json";
+
+/// Tauri event carrying `AuditProgress` payloads
+pub const AUDIT_EVENT: &str = "audit-progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    /// Matches its recorded hash
+    Ok,
+    /// First seen; its hash is now recorded
+    New,
+    /// Differs from the record but matches the Mac copy, or was rewritten since it was
+    /// recorded (another mtime) and doesn't contradict the Mac copy; re-recorded
+    Updated,
+    /// Smaller than recorded (or than the Mac copy, for a new file)
+    Truncated,
+    /// Same size or larger, different content
+    Corrupt,
+    /// Not recorded yet and differs from the Mac copy, so there is no good hash to record
+    MacMismatch,
+    /// Recorded or listed in a stash registry, but not on disk
+    Missing,
+    Unreadable,
+}
+
+impl AuditStatus {
+    pub fn is_problem(&self) -> bool {
+        !matches!(self, Self::Ok | Self::New | Self::Updated)
+    }
+}
+
+/// What the last audits learned about one stash file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashRecord {
+    pub size: u64,
+    pub blake3: String,
+    /// File mtime when the hash was recorded; a file with another mtime is a new version
+    pub modified: Option<String>,
+    pub recorded: String,
+    pub last_verified: Option<String>,
+    pub last_checked: String,
+    pub last_status: AuditStatus,
+}
+
+/// `<stash>/App_Data/stash_hashes.json`: records keyed by path relative to `Models`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditManifest {
+    pub files: BTreeMap<String, HashRecord>,
+}
+
+impl AuditManifest {
+    pub fn path<P: AsRef<Path>>(stash_base_dir: P) -> PathBuf {
+        stash_base_dir.as_ref().join(AUDIT_MANIFEST)
+    }
+
+    /// The stash's manifest; none yet reads as empty
+    pub fn load<P: AsRef<Path>>(stash_base_dir: P) -> AppResult<Self> {
+        let path = Self::path(stash_base_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", &path))?;
+        serde_json::from_str(&content).map_err(|e| {
+            AppError::new(
+                ErrorCode::JsonParse,
+                format!("Failed to parse {}: {}", AUDIT_MANIFEST, e),
+            )
+            .with("path", &path)
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, stash_base_dir: P) -> AppResult<()> {
+        let path = Self::path(stash_base_dir);
+        if let Some(parent) = path.parent() {
+            file_ops::ensure_directory(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?;
+        write_atomic(&path, content.as_bytes())
+            .map_err(|e| AppError::io(ErrorCode::FileWrite, &e).with("path", &path))
+    }
+
+    /// Drop the records of files the app itself deleted from the stash, so audits don't
+    /// report them missing. Without a manifest there is nothing to do.
+    pub fn forget<P: AsRef<Path>>(stash_base_dir: P, relative_paths: &[String]) -> AppResult<()> {
+        Self::update(stash_base_dir, |manifest| {
+            let before = manifest.files.len();
+            for path in relative_paths {
+                manifest.files.remove(path);
+            }
+            manifest.files.len() != before
+        })
+    }
+
+    /// Move records along with files the app renamed, as (from, to) relative paths
+    pub fn rename<P: AsRef<Path>>(
+        stash_base_dir: P,
+        renames: &[(String, String)],
+    ) -> AppResult<()> {
+        Self::update(stash_base_dir, |manifest| {
+            let mut changed = false;
+            for (from, to) in renames {
+                if let Some(record) = manifest.files.remove(from) {
+                    manifest.files.insert(to.clone(), record);
+                    changed = true;
+                }
+            }
+            changed
+        })
+    }
+
+    fn update<P: AsRef<Path>>(
+        stash_base_dir: P,
+        change: impl FnOnce(&mut Self) -> bool,
+    ) -> AppResult<()> {
+        if !Self::path(&stash_base_dir).exists() {
+            return Ok(());
+        }
+        let mut manifest = Self::load(&stash_base_dir)?;
+        match change(&mut manifest) {
+            true => manifest.save(&stash_base_dir),
+            false => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub relative_path: String,
+    pub status: AuditStatus,
+    pub size: Option<u64>,
+    pub expected_size: Option<u64>,
+    pub hash: Option<String>,
+    pub expected_hash: Option<String>,
+    /// Whether the Mac copy has the same content; None without a Mac copy or `mac_base_dir`
+    pub mac_match: Option<bool>,
+    pub last_verified: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub stash_models_dir: String,
+    pub manifest: String,
+    pub started: String,
+    pub finished: String,
+    pub files: Vec<AuditEntry>,
+    pub counts: BTreeMap<AuditStatus, usize>,
+    pub bytes_hashed: u64,
+    /// Stopped early by `cancel`; files not reached keep their previous records
+    pub cancelled: bool,
+}
+
+impl AuditReport {
+    pub fn problems(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.files.iter().filter(|f| f.status.is_problem())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditProgress {
+    pub current: String,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AuditOptions {
+    /// Also hash the Mac copy of each stash file, when there is one
+    pub mac_base_dir: Option<PathBuf>,
+    pub policy: ExtensionPolicy,
+}
+
+/// Hash every model and companion in `<stash_base_dir>/Models` in full and compare it with
+/// the hash recorded by earlier audits (and with the Mac copy, if asked). New files get a
+/// record; files that still match get a new `last_verified`. A bad file never overwrites a
+/// good record, but a file rewritten since (another mtime, as a sync or copy leaves it) is a
+/// new version and is re-recorded unless the Mac copy contradicts it. Recorded files, and files the stash's registries list, that are gone are
+/// reported missing. The manifest is saved even when cancelled (between files).
+pub fn audit_stash<P, F>(
+    stash_base_dir: P,
+    options: &AuditOptions,
+    cancel: Option<&AtomicBool>,
+    mut on_progress: F,
+) -> AppResult<AuditReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(&AuditProgress),
+{
+    let started = Utc::now().to_rfc3339();
+    let listing = model_scan::scan_models_dir_with(&stash_base_dir, &options.policy)?;
+    let stash_models = Path::new(&listing.models_dir);
+    let mut manifest = AuditManifest::load(&stash_base_dir)?;
+
+    let files: Vec<_> = listing
+        .files
+        .into_iter()
+        .filter(|f| f.class.is_synced())
+        .collect();
+    let mut progress = AuditProgress {
+        current: String::new(),
+        files_done: 0,
+        files_total: files.len(),
+        bytes_done: 0,
+        bytes_total: files.iter().map(|f| f.file_size).sum(),
+    };
+
+    let mut report = AuditReport {
+        stash_models_dir: listing.models_dir.clone(),
+        manifest: AuditManifest::path(&stash_base_dir)
+            .to_string_lossy()
+            .to_string(),
+        started,
+        finished: String::new(),
+        files: Vec::new(),
+        counts: BTreeMap::new(),
+        bytes_hashed: 0,
+        cancelled: false,
+    };
+
+    for file in &files {
+        if cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false) {
+            report.cancelled = true;
+            break;
+        }
+        progress.current = file.relative_path.clone();
+        on_progress(&progress);
+
+        let record = manifest.files.get(&file.relative_path);
+        let mut entry = AuditEntry {
+            relative_path: file.relative_path.clone(),
+            status: AuditStatus::Ok,
+            size: Some(file.file_size),
+            expected_size: record.map(|r| r.size),
+            hash: None,
+            expected_hash: record.map(|r| r.blake3.clone()),
+            mac_match: None,
+            last_verified: record.and_then(|r| r.last_verified.clone()),
+            error: None,
+        };
+
+        let hash = match calculate_blake3(stash_models.join(&file.relative_path)) {
+            Ok(hash) => hash,
+            Err(e) => {
+                entry.status = AuditStatus::Unreadable;
+                entry.error = Some(e.to_string());
+                if let Some(record) = manifest.files.get_mut(&file.relative_path) {
+                    record.last_checked = Utc::now().to_rfc3339();
+                    record.last_status = AuditStatus::Unreadable;
+                }
+                report.files.push(entry);
+                progress.files_done += 1;
+                progress.bytes_done += file.file_size;
+                continue;
+            }
+        };
+        report.bytes_hashed += file.file_size;
+
+        let mac = options
+            .mac_base_dir
+            .as_ref()
+            .map(|base| base.join("Models").join(&file.relative_path))
+            .filter(|path| path.is_file());
+        let mac_size = mac
+            .as_ref()
+            .and_then(|p| fs::metadata(p).ok())
+            .map(|m| m.len());
+        entry.mac_match = match (&mac, mac_size) {
+            (Some(_), Some(size)) if size != file.file_size => Some(false),
+            (Some(path), Some(_)) => calculate_blake3(path).ok().map(|h| h == hash),
+            _ => None,
+        };
+
+        let rewritten = |r: &HashRecord| r.modified.is_some() && r.modified != file.file_date;
+        entry.status = match record {
+            Some(r) if r.blake3 == hash && r.size == file.file_size => AuditStatus::Ok,
+            Some(_) if entry.mac_match == Some(true) => AuditStatus::Updated,
+            Some(r) if rewritten(r) && entry.mac_match.is_none() => AuditStatus::Updated,
+            Some(r) if file.file_size < r.size => AuditStatus::Truncated,
+            Some(_) => AuditStatus::Corrupt,
+            None if entry.mac_match == Some(false) => match mac_size {
+                Some(size) if file.file_size < size => AuditStatus::Truncated,
+                _ => AuditStatus::MacMismatch,
+            },
+            None => AuditStatus::New,
+        };
+
+        let now = Utc::now().to_rfc3339();
+        match entry.status {
+            AuditStatus::Ok | AuditStatus::New | AuditStatus::Updated => {
+                let recorded = match (entry.status, record) {
+                    (AuditStatus::Ok, Some(r)) => r.recorded.clone(),
+                    _ => now.clone(),
+                };
+                manifest.files.insert(
+                    file.relative_path.clone(),
+                    HashRecord {
+                        size: file.file_size,
+                        blake3: hash.clone(),
+                        modified: file.file_date.clone(),
+                        recorded,
+                        last_verified: Some(now.clone()),
+                        last_checked: now.clone(),
+                        last_status: entry.status,
+                    },
+                );
+                entry.last_verified = Some(now);
+            }
+            status => {
+                if let Some(record) = manifest.files.get_mut(&file.relative_path) {
+                    record.last_checked = now;
+                    record.last_status = status;
+                }
+            }
+        }
+
+        entry.hash = Some(hash);
+        report.files.push(entry);
+
+        progress.files_done += 1;
+        progress.bytes_done += file.file_size;
+        on_progress(&progress);
+    }
+
+    if !report.cancelled {
+        let on_disk: HashSet<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        let now = Utc::now().to_rfc3339();
+
+        for (relative_path, record) in manifest.files.iter_mut() {
+            if !on_disk.contains(relative_path.as_str()) {
+                record.last_checked = now.clone();
+                record.last_status = AuditStatus::Missing;
+                report
+                    .files
+                    .push(missing_entry(relative_path, Some(record)));
+            }
+        }
+
+        // Listed in a stash registry (as a model or an encoder) but never stashed
+        let config = DrawThingsConfig::parse_from_directory(stash_models)?;
+        let mut listed: Vec<&String> = config.file_to_model_type.keys().collect();
+        listed.sort();
+        for file in listed {
+            if !on_disk.contains(file.as_str()) && !manifest.files.contains_key(file) {
+                report.files.push(missing_entry(file, None));
+            }
+        }
+    }
+
+    manifest.save(&stash_base_dir)?;
+
+    for entry in &report.files {
+        *report.counts.entry(entry.status).or_default() += 1;
+    }
+    report.finished = Utc::now().to_rfc3339();
+    Ok(report)
+}
+
+fn missing_entry(relative_path: &str, record: Option<&HashRecord>) -> AuditEntry {
+    AuditEntry {
+        relative_path: relative_path.to_string(),
+        status: AuditStatus::Missing,
+        size: None,
+        expected_size: record.map(|r| r.size),
+        hash: None,
+        expected_hash: record.map(|r| r.blake3.clone()),
+        mac_match: None,
+        last_verified: record.and_then(|r| r.last_verified.clone()),
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_of(report: &AuditReport, path: &str) -> AuditStatus {
+        report
+            .files
+            .iter()
+            .find(|f| f.relative_path == path)
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn test_audit_records_then_detects_damage() {
+        let root = tempfile::tempdir().unwrap();
+        let stash = root.path().join("stash");
+        let models = stash.join("Models");
+        fs::create_dir_all(&models).unwrap();
+        fs::write(models.join("good.ckpt"), b"good model").unwrap();
+        fs::write(models.join("rot.ckpt"), b"bits bits").unwrap();
+        fs::write(models.join("short.ckpt"), b"complete file").unwrap();
+        fs::write(models.join("gone.ckpt"), b"gone").unwrap();
+        fs::write(
+            models.join("custom.json"),
+            r#"[{"name":"Never stashed","file":"never_f16.ckpt"}]"#,
+        )
+        .unwrap();
+
+        let options = AuditOptions::default();
+        let first = audit_stash(&stash, &options, None, |_| {}).unwrap();
+        assert_eq!(first.counts.get(&AuditStatus::New), Some(&4));
+        assert_eq!(status_of(&first, "never_f16.ckpt"), AuditStatus::Missing);
+        assert!(AuditManifest::path(&stash).exists());
+
+        // Damage leaves the mtime alone; a new version (as a sync writes it) doesn't
+        damage(&models.join("rot.ckpt"), b"bits bitz");
+        damage(&models.join("short.ckpt"), b"compl");
+        fs::remove_file(models.join("gone.ckpt")).unwrap();
+        fs::write(models.join("good.ckpt"), b"good model v2").unwrap();
+        set_mtime_ago(&models.join("good.ckpt"), 60);
+
+        let second = audit_stash(&stash, &options, None, |_| {}).unwrap();
+        assert_eq!(status_of(&second, "good.ckpt"), AuditStatus::Updated);
+        assert_eq!(status_of(&second, "rot.ckpt"), AuditStatus::Corrupt);
+        assert_eq!(status_of(&second, "short.ckpt"), AuditStatus::Truncated);
+        assert_eq!(status_of(&second, "gone.ckpt"), AuditStatus::Missing);
+        assert_eq!(second.problems().count(), 4);
+
+        // The good hash is kept, so the damage is still reported next time
+        let manifest = AuditManifest::load(&stash).unwrap();
+        assert_eq!(
+            manifest.files["rot.ckpt"].blake3,
+            calculate_blake3_of(b"bits bits")
+        );
+        assert_eq!(manifest.files["rot.ckpt"].last_status, AuditStatus::Corrupt);
+        assert!(manifest.files["good.ckpt"].last_verified.is_some());
+
+        // Files the app removes or renames itself aren't reported missing
+        AuditManifest::forget(&stash, &["gone.ckpt".to_string()]).unwrap();
+        fs::rename(models.join("good.ckpt"), models.join("better.ckpt")).unwrap();
+        AuditManifest::rename(&stash, &[("good.ckpt".into(), "better.ckpt".into())]).unwrap();
+        let third = audit_stash(&stash, &options, None, |_| {}).unwrap();
+        assert_eq!(status_of(&third, "better.ckpt"), AuditStatus::Ok);
+        assert!(third.files.iter().all(|f| f.relative_path != "gone.ckpt"));
+    }
+
+    fn set_mtime_ago(path: &Path, secs: u64) {
+        let time = std::time::SystemTime::now() - std::time::Duration::from_secs(secs);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    /// Overwrite in place, keeping the mtime, as bit rot or a bad sector would
+    fn damage(path: &Path, bytes: &[u8]) {
+        let modified = fs::metadata(path).unwrap().modified().unwrap();
+        fs::write(path, bytes).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_audit_against_mac() {
+        let root = tempfile::tempdir().unwrap();
+        let (mac, stash) = (root.path().join("mac"), root.path().join("stash"));
+        fs::create_dir_all(mac.join("Models")).unwrap();
+        fs::create_dir_all(stash.join("Models")).unwrap();
+        fs::write(stash.join("Models/model.ckpt"), b"version 1").unwrap();
+        fs::write(mac.join("Models/half.ckpt"), b"full length").unwrap();
+        fs::write(stash.join("Models/half.ckpt"), b"full").unwrap();
+
+        let options = AuditOptions {
+            mac_base_dir: Some(mac.clone()),
+            ..Default::default()
+        };
+        let report = audit_stash(&stash, &options, None, |_| {}).unwrap();
+        assert_eq!(status_of(&report, "model.ckpt"), AuditStatus::New);
+        // A partial copy of a Mac file is never recorded as good
+        assert_eq!(status_of(&report, "half.ckpt"), AuditStatus::Truncated);
+        assert!(!AuditManifest::load(&stash)
+            .unwrap()
+            .files
+            .contains_key("half.ckpt"));
+
+        // Synced a newer version: differs from the record, but the Mac confirms it
+        fs::write(mac.join("Models/model.ckpt"), b"version 22").unwrap();
+        fs::write(stash.join("Models/model.ckpt"), b"version 22").unwrap();
+        let report = audit_stash(&stash, &options, None, |_| {}).unwrap();
+        assert_eq!(status_of(&report, "model.ckpt"), AuditStatus::Updated);
+        let entry = report
+            .files
+            .iter()
+            .find(|f| f.relative_path == "model.ckpt");
+        assert_eq!(entry.unwrap().mac_match, Some(true));
+    }
+
+    fn calculate_blake3_of(bytes: &[u8]) -> String {
+        blake3::hash(bytes).to_hex().to_string()
+    }
+}
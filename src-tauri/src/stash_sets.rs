@@ -0,0 +1,614 @@
+use crate::atomic_write::write_atomic;
+use crate::copy_engine::CopyProgress;
+use crate::dt_json::{CustomJsonFile, CustomJsonKind, DrawThingsConfig};
+use crate::dt_lint::ENCODER_KEYS;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::file_ops;
+use crate::file_policy::ExtensionPolicy;
+use crate::model_scan::{self, FileClass};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Stash Sets are kept in `<DTC_APP_DIR>/stash_sets.json`
+pub const SETS_FILE: &str = "stash_sets.json";
+
+/// A named group of models, e.g. "SDXL" or "Flux Full", that can be made the only
+/// models Draw Things shows. Each list is a list of registry `file`s in display order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StashSet {
+    pub name: String,
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub loras: Vec<String>,
+    #[serde(default)]
+    pub controlnets: Vec<String>,
+    /// Set by `save_set`
+    #[serde(default)]
+    pub updated: Option<String>,
+}
+
+impl StashSet {
+    /// The registries a set controls, with its files for each
+    pub fn members(&self) -> [(CustomJsonKind, &[String]); 3] {
+        [
+            (CustomJsonKind::Model, &self.models),
+            (CustomJsonKind::Lora, &self.loras),
+            (CustomJsonKind::Control, &self.controlnets),
+        ]
+    }
+}
+
+/// Every saved set. A missing file means no sets yet.
+pub fn load_sets<P: AsRef<Path>>(app_dir: P) -> AppResult<Vec<StashSet>> {
+    let path = app_dir.as_ref().join(SETS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| AppError::io(ErrorCode::FileRead, &e).with("path", &path))?;
+    serde_json::from_str(&content).map_err(|e| {
+        AppError::new(
+            ErrorCode::JsonParse,
+            format!("Failed to parse {}: {}", SETS_FILE, e),
+        )
+        .with("path", &path)
+    })
+}
+
+fn write_sets(app_dir: &Path, sets: &[StashSet]) -> AppResult<()> {
+    file_ops::ensure_directory(app_dir)?;
+    let path = app_dir.join(SETS_FILE);
+    let content = serde_json::to_string_pretty(sets)
+        .map_err(|e| AppError::new(ErrorCode::Unknown, e.to_string()))?;
+    write_atomic(&path, content.as_bytes())
+        .map_err(|e| AppError::io(ErrorCode::FileWrite, &e).with("path", &path))
+}
+
+/// The saved set called `name`
+pub fn find_set<P: AsRef<Path>>(app_dir: P, name: &str) -> AppResult<StashSet> {
+    load_sets(app_dir)?
+        .into_iter()
+        .find(|set| set.name == name)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::RecordNotFound,
+                format!("No Stash Set named {}", name),
+            )
+            .with("name", name)
+        })
+}
+
+/// Add a set, or replace the one with the same name. Returns all sets.
+pub fn save_set<P: AsRef<Path>>(app_dir: P, mut set: StashSet) -> AppResult<Vec<StashSet>> {
+    if set.name.trim().is_empty() {
+        return Err(AppError::new(
+            ErrorCode::InvalidJsonStructure,
+            "A Stash Set needs a name",
+        ));
+    }
+    set.updated = Some(Utc::now().to_rfc3339());
+
+    let mut sets = load_sets(&app_dir)?;
+    match sets.iter_mut().find(|s| s.name == set.name) {
+        Some(existing) => *existing = set,
+        None => sets.push(set),
+    }
+    write_sets(app_dir.as_ref(), &sets)?;
+    Ok(sets)
+}
+
+/// Remove the set called `name`. Returns the remaining sets.
+pub fn delete_set<P: AsRef<Path>>(app_dir: P, name: &str) -> AppResult<Vec<StashSet>> {
+    let mut sets = load_sets(&app_dir)?;
+    let count = sets.len();
+    sets.retain(|set| set.name != name);
+    if sets.len() == count {
+        return Err(AppError::new(
+            ErrorCode::RecordNotFound,
+            format!("No Stash Set named {}", name),
+        )
+        .with("name", name));
+    }
+    write_sets(app_dir.as_ref(), &sets)?;
+    Ok(sets)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ActivateOptions {
+    /// Move models that aren't in the set off the Mac (to the stash, if not there yet)
+    pub evict: bool,
+    /// Only report what would happen
+    pub dry_run: bool,
+    pub policy: ExtensionPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetFailure {
+    pub file: String,
+    pub error: AppError,
+}
+
+/// Result of `activate_set`. With `dry_run`, the lists are what would happen.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Activation {
+    pub set: String,
+    pub dry_run: bool,
+    /// Members, their encoders and companions copied from the stash to the Mac
+    pub copied: Vec<String>,
+    pub bytes_copied: u64,
+    /// Members or encoders found neither on the Mac nor in the stash, or members
+    /// listed in no registry. Activation doesn't start while any are missing.
+    pub missing: Vec<String>,
+    /// Registries rewritten to list only the set
+    pub registries: Vec<String>,
+    /// Mac entries the set displaced, saved to the stash's registries so another
+    /// set can list them again, as "custom.json: file"
+    pub stashed: Vec<String>,
+    /// Files moved off the Mac
+    pub evicted: Vec<String>,
+    pub bytes_freed: u64,
+    pub failures: Vec<SetFailure>,
+    /// Stopped early by `cancel`
+    pub cancelled: bool,
+}
+
+/// Make `set` the models Draw Things shows: copy its members (with their encoders and
+/// companion files) from the stash to `<mac_base_dir>/Models`, rewrite custom.json,
+/// custom_lora.json and custom_controlnet.json to list only the set in its order (backed up
+/// under `<app_dir>/backups` first), then optionally evict the models that aren't in it.
+/// Registry entries come from the Mac's JSON when present, else from the stash's; the
+/// Mac entries the set displaces are upserted into the stash's JSON before the rewrite.
+/// Embeddings, upscalers and face restorers are left alone.
+pub fn activate_set<P, Q, R, F>(
+    mac_base_dir: P,
+    stash_base_dir: Q,
+    app_dir: R,
+    set: &StashSet,
+    options: &ActivateOptions,
+    cancel: Option<&AtomicBool>,
+    mut on_progress: F,
+) -> AppResult<Activation>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+    R: AsRef<Path>,
+    F: FnMut(&CopyProgress),
+{
+    let mac_models = mac_base_dir.as_ref().join("Models");
+    let stash_models = stash_base_dir.as_ref().join("Models");
+    let is_cancelled = || cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false);
+
+    let mut activation = Activation {
+        set: set.name.clone(),
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    // The new registries, and every file they need
+    let mut registries = Vec::new();
+    let mut needed = BTreeSet::new();
+    for (kind, files) in set.members() {
+        let mac_entries = entries_by_file(&mac_models, kind)?;
+        let stash_entries = entries_by_file(&stash_models, kind)?;
+
+        let displaced: Vec<Value> = CustomJsonFile::read(&mac_models, kind)?
+            .entries::<Value>()?
+            .into_iter()
+            .filter(|entry| match entry.get("file").and_then(Value::as_str) {
+                Some(file) => !files.iter().any(|f| f == file),
+                None => false,
+            })
+            .collect();
+        activation
+            .stashed
+            .extend(displaced.iter().filter_map(|entry| {
+                let file = entry.get("file")?.as_str()?;
+                Some(format!("{}: {}", kind.file_name(), file))
+            }));
+
+        let mut entries = Vec::new();
+        for file in files {
+            match mac_entries.get(file).or_else(|| stash_entries.get(file)) {
+                Some(entry) => {
+                    needed.extend(encoders(entry));
+                    entries.push(entry.clone());
+                }
+                None => activation
+                    .missing
+                    .push(format!("{}: {}", kind.file_name(), file)),
+            }
+            needed.insert(file.clone());
+        }
+        registries.push((kind, entries, displaced));
+    }
+    for file in needed.clone() {
+        needed.extend(options.policy.companion_names(&file));
+    }
+
+    let mut to_copy = Vec::new();
+    for file in &needed {
+        if mac_models.join(file).is_file() {
+            continue;
+        }
+        match fs::metadata(stash_models.join(file)) {
+            Ok(meta) if meta.is_file() => to_copy.push((file.clone(), meta.len())),
+            // Companions are optional
+            _ if options.policy.companion_of(file).is_some() => {}
+            _ => activation.missing.push(file.clone()),
+        }
+    }
+
+    let to_evict = match options.evict {
+        true => eviction_candidates(&mac_base_dir, &mac_models, &needed, &options.policy)?,
+        false => Vec::new(),
+    };
+
+    if options.dry_run {
+        activation.bytes_copied = to_copy.iter().map(|(_, size)| size).sum();
+        activation.copied = to_copy.into_iter().map(|(file, _)| file).collect();
+        activation.registries = registries
+            .iter()
+            .map(|(kind, _, _)| kind.file_name().to_string())
+            .collect();
+        activation.bytes_freed = to_evict.iter().map(|(_, size)| size).sum();
+        activation.evicted = to_evict.into_iter().map(|(path, _)| path).collect();
+        return Ok(activation);
+    }
+
+    if !activation.missing.is_empty() {
+        return Err(AppError::new(
+            ErrorCode::ChildFilesMissing,
+            format!(
+                "{} file(s) of Stash Set {} are neither on the Mac nor in the stash",
+                activation.missing.len(),
+                set.name
+            ),
+        )
+        .with("missing", &activation.missing));
+    }
+
+    // 1. Copy what's missing on the Mac
+    file_ops::require_space(&mac_models, to_copy.iter().map(|(_, size)| size).sum())?;
+    for (file, size) in to_copy {
+        if is_cancelled() {
+            activation.cancelled = true;
+            return Ok(activation);
+        }
+        match file_ops::copy_verified(
+            &stash_models.join(&file),
+            &mac_models.join(&file),
+            false,
+            cancel,
+            &mut on_progress,
+        ) {
+            Ok(()) => {
+                activation.bytes_copied += size;
+                activation.copied.push(file);
+            }
+            Err(_) if is_cancelled() => {
+                activation.cancelled = true;
+                return Ok(activation);
+            }
+            Err(error) => activation.failures.push(SetFailure { file, error }),
+        }
+    }
+    // Never point Draw Things at files that didn't arrive
+    if !activation.failures.is_empty() {
+        return Ok(activation);
+    }
+
+    // 2. Show only the set, keeping what it displaces in the stash's registries
+    for (kind, _, displaced) in &registries {
+        let mut json = CustomJsonFile::read(&stash_models, *kind)?;
+        if json.upsert_entries(displaced)? {
+            json.write(&stash_models, &app_dir)?;
+        }
+    }
+    for (kind, entries, _) in registries {
+        let mut json = CustomJsonFile::read(&mac_models, kind)?;
+        json.replace_entries(&entries)?;
+        json.write(&mac_models, &app_dir)?;
+        activation.registries.push(kind.file_name().to_string());
+    }
+
+    // 3. Evict the rest, keeping a verified copy in the stash
+    for (relative_path, size) in to_evict {
+        if is_cancelled() {
+            activation.cancelled = true;
+            break;
+        }
+        let result = file_ops::require_space(&stash_models, size)
+            .and_then(|()| {
+                file_ops::copy_verified(
+                    &mac_models.join(&relative_path),
+                    &stash_models.join(&relative_path),
+                    false,
+                    cancel,
+                    &mut on_progress,
+                )
+            })
+            .and_then(|()| file_ops::delete_file(mac_models.join(&relative_path)));
+        match result {
+            Ok(()) => {
+                activation.bytes_freed += size;
+                activation.evicted.push(relative_path);
+            }
+            Err(_) if is_cancelled() => {
+                activation.cancelled = true;
+                break;
+            }
+            Err(error) => activation.failures.push(SetFailure {
+                file: relative_path,
+                error,
+            }),
+        }
+    }
+
+    Ok(activation)
+}
+
+/// Entries of one registry keyed by `file`; a missing registry is empty
+fn entries_by_file(models_dir: &Path, kind: CustomJsonKind) -> AppResult<HashMap<String, Value>> {
+    let json = CustomJsonFile::read(models_dir, kind)?;
+    Ok(json
+        .entries::<Value>()?
+        .into_iter()
+        .filter_map(|entry| {
+            let file = entry.get("file")?.as_str()?.to_string();
+            Some((file, entry))
+        })
+        .collect())
+}
+
+fn encoders(entry: &Value) -> Vec<String> {
+    ENCODER_KEYS
+        .iter()
+        .filter_map(|key| entry.get(*key)?.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// Model files (and companions) on the Mac that the set doesn't need, as (relative path, size).
+/// Files listed as embeddings, upscalers or face restorers stay. Refuses (41) while any
+/// Mac registry has errors, since the files it lists can't be told apart from unused ones.
+fn eviction_candidates<P: AsRef<Path>>(
+    mac_base_dir: P,
+    mac_models: &Path,
+    needed: &BTreeSet<String>,
+    policy: &ExtensionPolicy,
+) -> AppResult<Vec<(String, u64)>> {
+    let listing = model_scan::scan_models_dir_with(mac_base_dir, policy)?;
+    let config = DrawThingsConfig::parse_checked(mac_models)?;
+    let keep_type = |file: &str| {
+        matches!(
+            config.get_model_type(file).as_deref(),
+            Some("embedding" | "upscaler" | "face_restorer")
+        )
+    };
+
+    Ok(listing
+        .files
+        .into_iter()
+        .filter(|file| {
+            let owner = match (file.class, &file.companion_of) {
+                (FileClass::Companion, Some(model)) => model.as_str(),
+                (class, _) if class.is_model() => file.ckpt_filename.as_str(),
+                _ => return false,
+            };
+            !needed.contains(owner) && !keep_type(owner)
+        })
+        .map(|file| (file.relative_path, file.file_size))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_save_and_delete_sets() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_sets(dir.path()).unwrap().is_empty());
+
+        let set = StashSet {
+            name: "SDXL".to_string(),
+            models: vec!["sdxl_base_f16.ckpt".to_string()],
+            loras: Vec::new(),
+            controlnets: Vec::new(),
+            updated: None,
+        };
+        save_set(dir.path(), set.clone()).unwrap();
+        let sets = save_set(
+            dir.path(),
+            StashSet {
+                loras: vec!["detail_lora_f16.ckpt".to_string()],
+                ..set
+            },
+        )
+        .unwrap();
+        assert_eq!(sets.len(), 1);
+        assert_eq!(
+            find_set(dir.path(), "SDXL").unwrap().loras,
+            vec!["detail_lora_f16.ckpt"]
+        );
+        assert!(sets[0].updated.is_some());
+
+        assert!(delete_set(dir.path(), "SDXL").unwrap().is_empty());
+        assert_eq!(
+            delete_set(dir.path(), "SDXL").unwrap_err().code,
+            ErrorCode::RecordNotFound
+        );
+    }
+
+    /// Mac with SD 1.5 active; stash with SDXL (+ VAE, companion), a LoRA and SD 1.5
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let root = tempfile::tempdir().unwrap();
+        let mac = root.path().join("mac");
+        let stash = root.path().join("stash");
+        let (mac_models, stash_models) = (mac.join("Models"), stash.join("Models"));
+        fs::create_dir_all(&mac_models).unwrap();
+        fs::create_dir_all(&stash_models).unwrap();
+
+        fs::write(
+            mac_models.join("custom.json"),
+            r#"[{"name":"SD 1.5","file":"sd_v1.5_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        fs::write(mac_models.join("sd_v1.5_f16.ckpt"), b"sd15").unwrap();
+        fs::write(
+            mac_models.join("custom_textual_inversions.json"),
+            r#"[{"name":"Neg","file":"neg_ti_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        fs::write(mac_models.join("neg_ti_f16.ckpt"), b"ti").unwrap();
+
+        fs::write(
+            stash_models.join("custom.json"),
+            r#"[{"name":"SDXL","file":"sdxl_f16.ckpt","autoencoder":"sdxl_vae_f16.ckpt"},{"name":"SD 1.5 (stash)","file":"sd_v1.5_f16.ckpt"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            stash_models.join("custom_lora.json"),
+            r#"[{"name":"Detail","file":"detail_lora_f16.ckpt","weight":{"value":0.6}}]"#,
+        )
+        .unwrap();
+        fs::write(stash_models.join("sdxl_f16.ckpt"), b"sdxl").unwrap();
+        fs::write(stash_models.join("sdxl_f16.ckpt-tensordata"), b"weights").unwrap();
+        fs::write(stash_models.join("sdxl_vae_f16.ckpt"), b"vae").unwrap();
+        fs::write(stash_models.join("detail_lora_f16.ckpt"), b"lora").unwrap();
+
+        (root, mac, stash)
+    }
+
+    #[test]
+    fn test_activate_set() {
+        let (root, mac, stash) = setup();
+        let app_dir = root.path().join("app");
+        let set = StashSet {
+            name: "SDXL".to_string(),
+            models: vec!["sdxl_f16.ckpt".to_string()],
+            loras: vec!["detail_lora_f16.ckpt".to_string()],
+            controlnets: Vec::new(),
+            updated: None,
+        };
+        let mut options = ActivateOptions {
+            evict: true,
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let plan = activate_set(&mac, &stash, &app_dir, &set, &options, None, |_| {}).unwrap();
+        assert_eq!(
+            plan.copied,
+            vec![
+                "detail_lora_f16.ckpt",
+                "sdxl_f16.ckpt",
+                "sdxl_f16.ckpt-tensordata",
+                "sdxl_vae_f16.ckpt"
+            ]
+        );
+        assert_eq!(plan.evicted, vec!["sd_v1.5_f16.ckpt"]);
+        assert!(!mac.join("Models/sdxl_f16.ckpt").exists());
+
+        options.dry_run = false;
+        let done = activate_set(&mac, &stash, &app_dir, &set, &options, None, |_| {}).unwrap();
+        assert!(done.failures.is_empty());
+        assert_eq!(done.bytes_copied, 18);
+        assert_eq!(done.evicted, vec!["sd_v1.5_f16.ckpt"]);
+        assert_eq!(
+            fs::read(mac.join("Models/sdxl_f16.ckpt-tensordata")).unwrap(),
+            b"weights"
+        );
+        assert!(!mac.join("Models/sd_v1.5_f16.ckpt").exists());
+        // Evicted files land in the stash; embeddings stay on the Mac
+        assert!(stash.join("Models/sd_v1.5_f16.ckpt").exists());
+        assert!(mac.join("Models/neg_ti_f16.ckpt").exists());
+
+        let config = DrawThingsConfig::parse_from_directory(mac.join("Models")).unwrap();
+        assert_eq!(config.models.len(), 1);
+        assert_eq!(config.models[0].file, "sdxl_f16.ckpt");
+        assert_eq!(config.loras[0].file, "detail_lora_f16.ckpt");
+        assert_eq!(config.embeddings.len(), 1);
+
+        // A member nobody has stops the activation before anything changes
+        let broken = StashSet {
+            models: vec!["gone_f16.ckpt".to_string()],
+            ..set
+        };
+        let error =
+            activate_set(&mac, &stash, &app_dir, &broken, &options, None, |_| {}).unwrap_err();
+        assert_eq!(error.code, ErrorCode::ChildFilesMissing);
+        assert!(mac.join("Models/sdxl_f16.ckpt").exists());
+    }
+
+    #[test]
+    fn test_switch_sets_keeps_entries() {
+        let (root, mac, stash) = setup();
+        let app_dir = root.path().join("app");
+        // A model only the Mac's registry knows about
+        fs::write(
+            mac.join("Models/custom.json"),
+            r#"[{"name":"SD 1.5","file":"sd_v1.5_f16.ckpt"},{"name":"Mix","file":"mix_f16.ckpt","version":"v1"}]"#,
+        )
+        .unwrap();
+        fs::write(mac.join("Models/mix_f16.ckpt"), b"mix").unwrap();
+
+        let a = StashSet {
+            name: "SD 1.5".to_string(),
+            models: vec!["mix_f16.ckpt".to_string(), "sd_v1.5_f16.ckpt".to_string()],
+            loras: Vec::new(),
+            controlnets: Vec::new(),
+            updated: None,
+        };
+        let b = StashSet {
+            name: "SDXL".to_string(),
+            models: vec!["sdxl_f16.ckpt".to_string()],
+            loras: vec!["detail_lora_f16.ckpt".to_string()],
+            ..a.clone()
+        };
+        let options = ActivateOptions {
+            evict: true,
+            ..Default::default()
+        };
+
+        let done = activate_set(&mac, &stash, &app_dir, &b, &options, None, |_| {}).unwrap();
+        assert_eq!(
+            done.stashed,
+            vec!["custom.json: sd_v1.5_f16.ckpt", "custom.json: mix_f16.ckpt"]
+        );
+        let stashed = DrawThingsConfig::parse_from_directory(stash.join("Models")).unwrap();
+        // The Mac's entry wins over the stash's older one
+        assert_eq!(
+            stashed.get_display_name("sd_v1.5_f16.ckpt").as_deref(),
+            Some("SD 1.5")
+        );
+        assert_eq!(
+            stashed.get_display_name("mix_f16.ckpt").as_deref(),
+            Some("Mix")
+        );
+
+        let done = activate_set(&mac, &stash, &app_dir, &a, &options, None, |_| {}).unwrap();
+        assert!(done.failures.is_empty());
+        assert_eq!(done.evicted.len(), 4);
+        let config = DrawThingsConfig::parse_from_directory(mac.join("Models")).unwrap();
+        let files: Vec<&str> = config.models.iter().map(|m| m.file.as_str()).collect();
+        assert_eq!(files, vec!["mix_f16.ckpt", "sd_v1.5_f16.ckpt"]);
+        assert_eq!(config.models[0].version.as_deref(), Some("v1"));
+        assert!(config.loras.is_empty());
+        assert_eq!(fs::read(mac.join("Models/mix_f16.ckpt")).unwrap(), b"mix");
+
+        // Going back to B finds SDXL and the LoRA where A left them
+        let done = activate_set(&mac, &stash, &app_dir, &b, &options, None, |_| {}).unwrap();
+        assert!(done.failures.is_empty());
+        let config = DrawThingsConfig::parse_from_directory(mac.join("Models")).unwrap();
+        assert_eq!(config.models[0].file, "sdxl_f16.ckpt");
+        assert_eq!(config.loras[0].file, "detail_lora_f16.ckpt");
+    }
+}
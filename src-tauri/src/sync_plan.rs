@@ -0,0 +1,711 @@
+use crate::copy_engine::CopyProgress;
+use crate::dt_json::{CustomJsonFile, CustomJsonKind};
+use crate::error::{AppError, AppResult};
+use crate::file_hash::{HashCache, HashMode};
+use crate::file_ops::{self, DiskSpace};
+use crate::file_policy::ExtensionPolicy;
+use crate::model_scan::{self, ScannedFile};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Modification times closer than this count as equal (FAT/exFAT timestamps are coarse)
+const MTIME_TOLERANCE_SECS: i64 = 2;
+
+/// How a file or registry entry compares between the Mac and the stash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    NewOnMac,
+    NewInStash,
+    /// Differs, and one side is newer
+    Changed,
+    Identical,
+    /// Differs, but neither side is newer, so there is no safe direction
+    Conflicting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Mac,
+    Stash,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileState {
+    pub size: u64,
+    pub modified: Option<String>,
+}
+
+/// One model file, matched by its path relative to `Models`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub relative_path: String,
+    pub filename: String,
+    pub status: SyncStatus,
+    pub mac: Option<FileState>,
+    pub stash: Option<FileState>,
+    /// Set for `Changed`
+    pub newer: Option<Side>,
+}
+
+/// One registry entry, matched by its `file` key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonEntryDiff {
+    pub kind: CustomJsonKind,
+    pub file: String,
+    pub status: SyncStatus,
+    pub mac: Option<Value>,
+    pub stash: Option<Value>,
+    /// Set for `Changed`: the side whose model file is newer (or the only one present)
+    pub newer: Option<Side>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Model files per status. Bytes are what a sync would copy: the newer side for
+/// `Changed`, the Mac copy for `Conflicting`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncTotals {
+    pub new_on_mac: Tally,
+    pub new_in_stash: Tally,
+    pub changed: Tally,
+    pub identical: Tally,
+    pub conflicting: Tally,
+}
+
+/// Everything that differs between `<mac>/Models` and `<stash>/Models`. Nothing is touched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub mac_models_dir: String,
+    pub stash_models_dir: String,
+    pub files: Vec<FileDiff>,
+    pub json_entries: Vec<JsonEntryDiff>,
+    pub totals: SyncTotals,
+    pub stash_space: Option<DiskSpace>,
+    /// Unreadable files or registries, risky stash volume
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncFailure {
+    /// Relative path of the file, or the registry file name
+    pub relative_path: String,
+    pub error: AppError,
+}
+
+/// Result of `sync_to_stash`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncSummary {
+    /// Relative paths copied to the stash
+    pub copied: Vec<String>,
+    pub bytes_copied: u64,
+    /// Registry entries added or updated in the stash, as `<json file>: <file>`
+    pub json_updated: Vec<String>,
+    /// Files and entries that differ with no newer side, left for the user
+    pub conflicts: Vec<String>,
+    pub failures: Vec<SyncFailure>,
+    /// Stopped early by `cancel`; registries are not touched then
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlanOptions {
+    /// Compare BLAKE3 hashes of same-sized files. Without it, same-sized files count as
+    /// identical only while their mtimes agree; otherwise they are `Changed`.
+    pub compare_hashes: bool,
+    /// Which files are models and companions
+    pub policy: ExtensionPolicy,
+}
+
+/// Compare the model files (with their companions) and Draw Things registries of the Mac and the stash
+pub fn plan_sync<P: AsRef<Path>, Q: AsRef<Path>>(
+    mac_base_dir: P,
+    stash_base_dir: Q,
+    options: &PlanOptions,
+    cache: &HashCache,
+) -> AppResult<SyncPlan> {
+    let mac = model_scan::scan_models_dir_with(&mac_base_dir, &options.policy)?;
+    let stash = model_scan::scan_models_dir_with(&stash_base_dir, &options.policy)?;
+    let mac_models = Path::new(&mac.models_dir);
+    let stash_models = Path::new(&stash.models_dir);
+
+    let mut warnings: Vec<String> = mac.errors.iter().chain(&stash.errors).cloned().collect();
+
+    let stash_space = file_ops::disk_space(stash_models).ok();
+    if let Some(warning) = stash_space.as_ref().and_then(|s| s.warning) {
+        warnings.push(warning.message().to_string());
+    }
+
+    // Relative path -> (Mac file, stash file)
+    let mut pairs: BTreeMap<String, (Option<ScannedFile>, Option<ScannedFile>)> = BTreeMap::new();
+    for (files, on_mac) in [(mac.files, true), (stash.files, false)] {
+        for file in files {
+            if !file.class.is_synced() {
+                continue;
+            }
+            let pair = pairs.entry(file.relative_path.clone()).or_default();
+            match on_mac {
+                true => pair.0 = Some(file),
+                false => pair.1 = Some(file),
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut totals = SyncTotals::default();
+    // The side holding the newer copy of each model file known to differ in content
+    let mut file_newer: HashMap<String, Side> = HashMap::new();
+
+    for (relative_path, pair) in pairs {
+        let diff = match pair {
+            (Some(m), None) => file_diff(relative_path, Some(&m), None, SyncStatus::NewOnMac, None),
+            (None, Some(s)) => {
+                file_diff(relative_path, None, Some(&s), SyncStatus::NewInStash, None)
+            }
+            (Some(m), Some(s)) => {
+                let newer = newer_side(parse_time(&m.file_date), parse_time(&s.file_date));
+                let same = if m.file_size != s.file_size {
+                    false
+                } else if options.compare_hashes {
+                    match same_content(mac_models, stash_models, &relative_path, cache) {
+                        Ok(same) => same,
+                        Err(e) => {
+                            warnings.push(format!("{}: {}", relative_path, e));
+                            continue;
+                        }
+                    }
+                } else {
+                    // Same size but touched since: a rewrite with new bytes is just as likely
+                    newer.is_none()
+                };
+
+                let status = match (same, newer) {
+                    (true, _) => SyncStatus::Identical,
+                    (false, Some(_)) => SyncStatus::Changed,
+                    (false, None) => SyncStatus::Conflicting,
+                };
+                // Same size and unhashed: only the mtime differs, which says nothing
+                // about who edited a registry entry
+                let differs = m.file_size != s.file_size || options.compare_hashes;
+                if let (false, true, Some(side)) = (same, differs, newer) {
+                    file_newer.insert(m.ckpt_filename.clone(), side);
+                }
+                file_diff(
+                    relative_path,
+                    Some(&m),
+                    Some(&s),
+                    status,
+                    newer.filter(|_| !same),
+                )
+            }
+            (None, None) => continue,
+        };
+        match diff.status {
+            SyncStatus::NewOnMac => file_newer.insert(diff.filename.clone(), Side::Mac),
+            SyncStatus::NewInStash => file_newer.insert(diff.filename.clone(), Side::Stash),
+            _ => None,
+        };
+
+        let tally = match diff.status {
+            SyncStatus::NewOnMac => &mut totals.new_on_mac,
+            SyncStatus::NewInStash => &mut totals.new_in_stash,
+            SyncStatus::Changed => &mut totals.changed,
+            SyncStatus::Identical => &mut totals.identical,
+            SyncStatus::Conflicting => &mut totals.conflicting,
+        };
+        tally.files += 1;
+        tally.bytes += transfer_size(&diff);
+        files.push(diff);
+    }
+
+    let mut json_entries = Vec::new();
+    for kind in CustomJsonKind::ALL {
+        match json_diff(mac_models, stash_models, kind, &file_newer) {
+            Ok(diffs) => json_entries.extend(diffs),
+            Err(e) => warnings.push(e.message),
+        }
+    }
+
+    Ok(SyncPlan {
+        mac_models_dir: mac.models_dir,
+        stash_models_dir: stash.models_dir,
+        files,
+        json_entries,
+        totals,
+        stash_space,
+        warnings,
+    })
+}
+
+/// Bring the stash up to date with the Mac as planned: copy files that are new or newer on
+/// the Mac (each verified by size and hash), then add or update their registry entries in the
+/// stash's JSON files, backed up under `<app_dir>/backups` first. Nothing is deleted: files
+/// newer in the stash are left alone and conflicts are only reported.
+pub fn sync_to_stash<P, F>(
+    plan: &SyncPlan,
+    app_dir: P,
+    cancel: Option<&AtomicBool>,
+    mut on_progress: F,
+) -> AppResult<SyncSummary>
+where
+    P: AsRef<Path>,
+    F: FnMut(&CopyProgress),
+{
+    let mac_models = Path::new(&plan.mac_models_dir);
+    let stash_models = Path::new(&plan.stash_models_dir);
+    let is_cancelled = || cancel.map(|c| c.load(Ordering::Relaxed)).unwrap_or(false);
+
+    let mut summary = SyncSummary::default();
+    let to_copy: Vec<&FileDiff> = plan
+        .files
+        .iter()
+        .filter(|f| from_mac(f.status, f.newer))
+        .collect();
+
+    let bytes: u64 = to_copy.iter().map(|f| transfer_size(f)).sum();
+    file_ops::require_space(stash_models, bytes)?;
+
+    for file in to_copy {
+        if is_cancelled() {
+            summary.cancelled = true;
+            return Ok(summary);
+        }
+        match file_ops::copy_verified(
+            &mac_models.join(&file.relative_path),
+            &stash_models.join(&file.relative_path),
+            file.status == SyncStatus::Changed,
+            cancel,
+            &mut on_progress,
+        ) {
+            Ok(()) => {
+                summary.bytes_copied += transfer_size(file);
+                summary.copied.push(file.relative_path.clone());
+            }
+            Err(_) if is_cancelled() => {
+                summary.cancelled = true;
+                return Ok(summary);
+            }
+            Err(error) => summary.failures.push(SyncFailure {
+                relative_path: file.relative_path.clone(),
+                error,
+            }),
+        }
+    }
+
+    for kind in CustomJsonKind::ALL {
+        let updates: Vec<&JsonEntryDiff> = plan
+            .json_entries
+            .iter()
+            .filter(|e| e.kind == kind && from_mac(e.status, e.newer) && e.mac.is_some())
+            .collect();
+        if updates.is_empty() {
+            continue;
+        }
+
+        match upsert_entries(stash_models, app_dir.as_ref(), kind, &updates) {
+            Ok(()) => summary.json_updated.extend(
+                updates
+                    .iter()
+                    .map(|e| format!("{}: {}", kind.file_name(), e.file)),
+            ),
+            Err(error) => summary.failures.push(SyncFailure {
+                relative_path: kind.file_name().to_string(),
+                error,
+            }),
+        }
+    }
+
+    let conflicting_files = plan
+        .files
+        .iter()
+        .filter(|f| f.status == SyncStatus::Conflicting)
+        .map(|f| f.relative_path.clone());
+    let conflicting_entries = plan
+        .json_entries
+        .iter()
+        .filter(|e| e.status == SyncStatus::Conflicting)
+        .map(|e| format!("{}: {}", e.kind.file_name(), e.file));
+    summary.conflicts = conflicting_files.chain(conflicting_entries).collect();
+
+    Ok(summary)
+}
+
+/// New on the Mac, or changed and newer there
+fn from_mac(status: SyncStatus, newer: Option<Side>) -> bool {
+    matches!(
+        (status, newer),
+        (SyncStatus::NewOnMac, _) | (SyncStatus::Changed, Some(Side::Mac))
+    )
+}
+
+/// Replace the stash's entries for these files with the Mac's, appending new ones
+fn upsert_entries(
+    stash_models: &Path,
+    app_dir: &Path,
+    kind: CustomJsonKind,
+    updates: &[&JsonEntryDiff],
+) -> AppResult<()> {
+    let updates: Vec<Value> = updates.iter().filter_map(|u| u.mac.clone()).collect();
+    let mut json = CustomJsonFile::read(stash_models, kind)?;
+    if json.upsert_entries(&updates)? {
+        json.write(stash_models, app_dir)?;
+    }
+    Ok(())
+}
+
+fn file_diff(
+    relative_path: String,
+    mac: Option<&ScannedFile>,
+    stash: Option<&ScannedFile>,
+    status: SyncStatus,
+    newer: Option<Side>,
+) -> FileDiff {
+    let state = |file: &ScannedFile| FileState {
+        size: file.file_size,
+        modified: file.file_date.clone(),
+    };
+    FileDiff {
+        filename: mac
+            .or(stash)
+            .map(|f| f.ckpt_filename.clone())
+            .unwrap_or_default(),
+        relative_path,
+        status,
+        mac: mac.map(state),
+        stash: stash.map(state),
+        newer,
+    }
+}
+
+/// Bytes a sync would copy for this file
+fn transfer_size(diff: &FileDiff) -> u64 {
+    let size = |state: &Option<FileState>| state.as_ref().map(|s| s.size).unwrap_or(0);
+    match (diff.status, diff.newer) {
+        (SyncStatus::NewInStash, _) | (SyncStatus::Changed, Some(Side::Stash)) => size(&diff.stash),
+        _ => size(&diff.mac),
+    }
+}
+
+fn same_content(
+    mac_models: &Path,
+    stash_models: &Path,
+    relative_path: &str,
+    cache: &HashCache,
+) -> std::io::Result<bool> {
+    let hash = |path: &Path| cache.hash_with_metadata(path, &fs::metadata(path)?, HashMode::Blake3);
+    Ok(hash(&mac_models.join(relative_path))? == hash(&stash_models.join(relative_path))?)
+}
+
+fn parse_time(date: &Option<String>) -> Option<DateTime<Utc>> {
+    date.as_deref()
+        .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+        .map(|d| d.with_timezone(&Utc))
+}
+
+/// The side modified later, unless the times are within `MTIME_TOLERANCE_SECS` or unknown
+fn newer_side(mac: Option<DateTime<Utc>>, stash: Option<DateTime<Utc>>) -> Option<Side> {
+    let seconds = (mac? - stash?).num_seconds();
+    if seconds > MTIME_TOLERANCE_SECS {
+        Some(Side::Mac)
+    } else if seconds < -MTIME_TOLERANCE_SECS {
+        Some(Side::Stash)
+    } else {
+        None
+    }
+}
+
+/// Compare one registry entry by entry. A registry file's mtime says nothing about which of
+/// its entries was edited, so a differing entry is `Changed` only when its model file differs
+/// with a newer side (`file_newer`) and `Conflicting` otherwise.
+fn json_diff(
+    mac_models: &Path,
+    stash_models: &Path,
+    kind: CustomJsonKind,
+    file_newer: &HashMap<String, Side>,
+) -> AppResult<Vec<JsonEntryDiff>> {
+    let entries = |models_dir: &Path| -> AppResult<BTreeMap<String, Value>> {
+        let json = CustomJsonFile::read(models_dir, kind)?;
+        Ok(json
+            .entries::<Value>()?
+            .into_iter()
+            .filter_map(|entry| {
+                let file = entry.get("file")?.as_str()?.to_string();
+                Some((file, entry))
+            })
+            .collect())
+    };
+    let mut mac = entries(mac_models)?;
+    let mut stash = entries(stash_models)?;
+
+    let mut files: Vec<String> = mac.keys().chain(stash.keys()).cloned().collect();
+    files.sort();
+    files.dedup();
+
+    Ok(files
+        .into_iter()
+        .map(|file| {
+            let (mac, stash) = (mac.remove(&file), stash.remove(&file));
+            let (status, newer) = match (&mac, &stash) {
+                (Some(_), None) => (SyncStatus::NewOnMac, None),
+                (None, Some(_)) => (SyncStatus::NewInStash, None),
+                (Some(m), Some(s)) if m == s => (SyncStatus::Identical, None),
+                _ => match file_newer.get(&file) {
+                    Some(&side) => (SyncStatus::Changed, Some(side)),
+                    None => (SyncStatus::Conflicting, None),
+                },
+            };
+            JsonEntryDiff {
+                kind,
+                file,
+                status,
+                mac,
+                stash,
+                newer,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    fn set_mtime(path: &Path, secs_ago: u64) {
+        let time = SystemTime::now() - Duration::from_secs(secs_ago);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    fn setup() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let root = tempfile::tempdir().unwrap();
+        let mac = root.path().join("mac/Models");
+        let stash = root.path().join("stash/Models");
+        fs::create_dir_all(&mac).unwrap();
+        fs::create_dir_all(&stash).unwrap();
+
+        fs::write(mac.join("only_mac.ckpt"), b"mac").unwrap();
+        fs::write(stash.join("only_stash.safetensors"), b"stash!").unwrap();
+        fs::write(mac.join("same.ckpt"), b"same").unwrap();
+        fs::write(stash.join("same.ckpt"), b"same").unwrap();
+        // Same size, different bytes: only a hash tells them apart
+        fs::write(mac.join("flipped.ckpt"), b"abcd").unwrap();
+        fs::write(stash.join("flipped.ckpt"), b"abcx").unwrap();
+        fs::write(mac.join("newer_on_mac.ckpt"), b"version 2").unwrap();
+        fs::write(stash.join("newer_on_mac.ckpt"), b"v1").unwrap();
+        set_mtime(&stash.join("newer_on_mac.ckpt"), 3600);
+        fs::write(mac.join("notes.txt"), b"not a model").unwrap();
+
+        (root, mac, stash)
+    }
+
+    fn status_of(plan: &SyncPlan, name: &str) -> SyncStatus {
+        plan.files
+            .iter()
+            .find(|f| f.filename == name)
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn test_plan_model_files() {
+        let (_root, mac, stash) = setup();
+        let (mac_base, stash_base) = (mac.parent().unwrap(), stash.parent().unwrap());
+        let cache = HashCache::new();
+
+        let plan = plan_sync(mac_base, stash_base, &PlanOptions::default(), &cache).unwrap();
+        assert_eq!(plan.files.len(), 5);
+        assert_eq!(status_of(&plan, "only_mac.ckpt"), SyncStatus::NewOnMac);
+        assert_eq!(
+            status_of(&plan, "only_stash.safetensors"),
+            SyncStatus::NewInStash
+        );
+        assert_eq!(status_of(&plan, "same.ckpt"), SyncStatus::Identical);
+        assert_eq!(status_of(&plan, "flipped.ckpt"), SyncStatus::Identical);
+        assert_eq!(status_of(&plan, "newer_on_mac.ckpt"), SyncStatus::Changed);
+
+        let changed = plan
+            .files
+            .iter()
+            .find(|f| f.filename == "newer_on_mac.ckpt");
+        assert_eq!(changed.unwrap().newer, Some(Side::Mac));
+        assert_eq!(plan.totals.new_on_mac, Tally { files: 1, bytes: 3 });
+        assert_eq!(plan.totals.new_in_stash, Tally { files: 1, bytes: 6 });
+        assert_eq!(plan.totals.changed, Tally { files: 1, bytes: 9 });
+        assert_eq!(plan.totals.identical.files, 2);
+
+        // Same size with a different mtime may be new bytes: without hashing it is changed
+        set_mtime(&stash.join("same.ckpt"), 3600);
+        let plan = plan_sync(mac_base, stash_base, &PlanOptions::default(), &cache).unwrap();
+        assert_eq!(status_of(&plan, "same.ckpt"), SyncStatus::Changed);
+
+        // Hashing catches the same-size difference; equal mtimes leave no safe direction
+        let options = PlanOptions {
+            compare_hashes: true,
+            ..Default::default()
+        };
+        let plan = plan_sync(mac_base, stash_base, &options, &cache).unwrap();
+        assert_eq!(status_of(&plan, "flipped.ckpt"), SyncStatus::Conflicting);
+        assert_eq!(status_of(&plan, "same.ckpt"), SyncStatus::Identical);
+        assert_eq!(plan.totals.conflicting, Tally { files: 1, bytes: 4 });
+
+        // Planning touches nothing
+        assert_eq!(fs::read(stash.join("newer_on_mac.ckpt")).unwrap(), b"v1");
+        assert!(!stash.join("only_mac.ckpt").exists());
+    }
+
+    #[test]
+    fn test_plan_json_entries() {
+        let (_root, mac, stash) = setup();
+        fs::write(
+            mac.join("custom_lora.json"),
+            r#"[{"file":"a.ckpt","name":"A"},{"file":"b.ckpt","name":"B"},{"file":"c.ckpt","name":"C"},{"file":"newer_on_mac.ckpt","name":"N v2"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            stash.join("custom_lora.json"),
+            r#"[{"file":"b.ckpt","name":"B"},{"file":"c.ckpt","name":"Old C"},{"file":"d.ckpt","name":"D"},{"file":"newer_on_mac.ckpt","name":"N"}]"#,
+        )
+        .unwrap();
+        // The newer registry file doesn't make every entry in it newer
+        set_mtime(&stash.join("custom_lora.json"), 3600);
+
+        let plan = plan_sync(
+            mac.parent().unwrap(),
+            stash.parent().unwrap(),
+            &PlanOptions::default(),
+            &HashCache::new(),
+        )
+        .unwrap();
+
+        let statuses: Vec<_> = plan
+            .json_entries
+            .iter()
+            .map(|e| (e.kind, e.file.as_str(), e.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (CustomJsonKind::Lora, "a.ckpt", SyncStatus::NewOnMac),
+                (CustomJsonKind::Lora, "b.ckpt", SyncStatus::Identical),
+                (CustomJsonKind::Lora, "c.ckpt", SyncStatus::Conflicting),
+                (CustomJsonKind::Lora, "d.ckpt", SyncStatus::NewInStash),
+                (
+                    CustomJsonKind::Lora,
+                    "newer_on_mac.ckpt",
+                    SyncStatus::Changed
+                ),
+            ]
+        );
+        assert_eq!(plan.json_entries[2].newer, None);
+        // Follows its model file, which is newer on the Mac
+        assert_eq!(plan.json_entries[4].newer, Some(Side::Mac));
+    }
+
+    #[test]
+    fn test_sync_to_stash() {
+        let (root, mac, stash) = setup();
+        fs::write(
+            mac.join("custom_lora.json"),
+            r#"[{"file":"a.ckpt","name":"A"},{"file":"c.ckpt","name":"C"},{"file":"newer_on_mac.ckpt","name":"N v2"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            stash.join("custom_lora.json"),
+            r#"[{"file":"newer_on_mac.ckpt","name":"N"},{"file":"c.ckpt","name":"Old C"},{"file":"d.ckpt","name":"D"}]"#,
+        )
+        .unwrap();
+        set_mtime(&stash.join("custom_lora.json"), 3600);
+
+        let options = PlanOptions {
+            compare_hashes: true,
+            ..Default::default()
+        };
+        let plan = plan_sync(
+            mac.parent().unwrap(),
+            stash.parent().unwrap(),
+            &options,
+            &HashCache::new(),
+        )
+        .unwrap();
+        let app_dir = root.path().join("app");
+        let summary = sync_to_stash(&plan, &app_dir, None, |_| {}).unwrap();
+
+        assert_eq!(summary.copied, vec!["newer_on_mac.ckpt", "only_mac.ckpt"]);
+        assert_eq!(summary.bytes_copied, 12);
+        assert_eq!(
+            summary.conflicts,
+            vec!["flipped.ckpt", "custom_lora.json: c.ckpt"]
+        );
+        assert!(summary.failures.is_empty());
+        assert_eq!(
+            fs::read(stash.join("newer_on_mac.ckpt")).unwrap(),
+            b"version 2"
+        );
+        assert_eq!(fs::read(stash.join("flipped.ckpt")).unwrap(), b"abcx");
+        assert!(stash.join("only_stash.safetensors").exists());
+        assert!(mac.join("only_mac.ckpt").exists());
+
+        assert_eq!(
+            summary.json_updated,
+            vec![
+                "custom_lora.json: a.ckpt",
+                "custom_lora.json: newer_on_mac.ckpt"
+            ]
+        );
+        let json = CustomJsonFile::read(&stash, CustomJsonKind::Lora).unwrap();
+        let names: Vec<String> = json
+            .entries::<Value>()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, vec!["N v2", "Old C", "D", "A"]);
+        assert!(app_dir.join("backups").is_dir());
+    }
+
+    #[test]
+    fn test_plan_after_sync_is_identical() {
+        let root = tempfile::tempdir().unwrap();
+        let (mac, stash) = (root.path().join("mac"), root.path().join("stash"));
+        fs::create_dir_all(mac.join("Models")).unwrap();
+        fs::create_dir_all(stash.join("Models")).unwrap();
+        fs::write(mac.join("Models/a_f16.ckpt"), b"weights").unwrap();
+        set_mtime(&mac.join("Models/a_f16.ckpt"), 3600);
+        let registry = mac.join("Models/custom.json");
+        fs::write(&registry, r#"[{"name":"A","file":"a_f16.ckpt"}]"#).unwrap();
+
+        let cache = HashCache::new();
+        let options = PlanOptions::default();
+        let plan = plan_sync(&mac, &stash, &options, &cache).unwrap();
+        sync_to_stash(&plan, root.path().join("app"), None, |_| {}).unwrap();
+
+        let plan = plan_sync(&mac, &stash, &options, &cache).unwrap();
+        assert!(plan.files.iter().all(|f| f.status == SyncStatus::Identical));
+        assert!(plan
+            .json_entries
+            .iter()
+            .all(|e| e.status == SyncStatus::Identical));
+
+        // An edit with no newer model file behind it has no safe direction
+        fs::write(&registry, r#"[{"name":"A v2","file":"a_f16.ckpt"}]"#).unwrap();
+        let plan = plan_sync(&mac, &stash, &options, &cache).unwrap();
+        assert_eq!(plan.json_entries[0].status, SyncStatus::Conflicting);
+        assert_eq!(plan.json_entries[0].newer, None);
+    }
+}